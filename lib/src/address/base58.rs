@@ -0,0 +1,126 @@
+use sha2::{Digest, Sha256};
+
+use super::AddressError;
+
+const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+pub fn encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|&&byte| byte == 0).count();
+    let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+    for &byte in &data[zeros..] {
+        let mut carry = byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    let mut encoded = String::with_capacity(zeros + digits.len());
+    encoded.extend(std::iter::repeat_n('1', zeros));
+    encoded.extend(digits.iter().rev().map(|&digit| ALPHABET[digit as usize] as char));
+    encoded
+}
+
+pub fn decode(encoded: &str) -> Result<Vec<u8>, AddressError> {
+    let zeros = encoded.bytes().take_while(|&byte| byte == b'1').count();
+    let mut bytes: Vec<u8> = Vec::with_capacity(encoded.len());
+    for character in encoded.bytes().skip(zeros) {
+        let mut carry = ALPHABET
+            .iter()
+            .position(|&symbol| symbol == character)
+            .ok_or(AddressError::InvalidCharacter(character as char))? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut decoded = vec![0; zeros];
+    decoded.extend(bytes.iter().rev());
+    Ok(decoded)
+}
+
+pub fn encode_check(data: &[u8]) -> String {
+    let mut payload = data.to_owned();
+    payload.extend_from_slice(&checksum(data));
+    encode(&payload)
+}
+
+pub fn decode_check(encoded: &str) -> Result<Vec<u8>, AddressError> {
+    let mut decoded = decode(encoded)?;
+    if decoded.len() < 4 {
+        return Err(AddressError::InvalidLength(decoded.len()));
+    }
+    let data_len = decoded.len() - 4;
+    if decoded[data_len..] != checksum(&decoded[..data_len]) {
+        return Err(AddressError::InvalidChecksum);
+    }
+    decoded.truncate(data_len);
+    Ok(decoded)
+}
+
+fn checksum(data: &[u8]) -> [u8; 4] {
+    let hash = Sha256::digest(&Sha256::digest(data)[..]);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the base58_encode_decode vectors of the reference client
+    #[test]
+    fn encodes_and_decodes() {
+        let vectors = [
+            ("", ""),
+            ("61", "2g"),
+            ("626262", "a3gV"),
+            ("636363", "aPEr"),
+            (
+                "73696d706c792061206c6f6e6720737472696e67",
+                "2cFupjhnEsSn59qHXstmK2ffpLv2",
+            ),
+            (
+                "00eb15231dfceb60925886b67d065299925915aeb172c06647",
+                "1NS17iag9jJgTHD1VXjvLCEnZuQ3rJDE9L",
+            ),
+            ("516b6fcd0f", "ABnLTmg"),
+            ("bf4f89001e670274dd", "3SEo3LWLoPntC"),
+            ("572e4794", "3EFU7m"),
+            ("ecac89cad93923c02321", "EJDM8drfXA6uyA"),
+            ("10c8511e", "Rt5zm"),
+            ("00000000000000000000", "1111111111"),
+            (
+                "000111d38e5fc9071ffcd20b4a763cc9ae4f252bb4e48fd66a835e252ada93ff480d6dd43dc62a641155a5",
+                "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz",
+            ),
+        ];
+        for &(data, encoded) in &vectors {
+            let data = hex::decode(data).unwrap();
+            assert_eq!(encode(&data), encoded);
+            assert_eq!(decode(encoded).unwrap(), data);
+        }
+        assert!(matches!(decode("2g0"), Err(AddressError::InvalidCharacter('0'))));
+    }
+
+    #[test]
+    fn checks_the_checksum() {
+        // the address paid by the genesis block
+        let data = hex::decode("0062e907b15cbf27d5425399ebf6f0fb50ebb88f18").unwrap();
+        assert_eq!(encode_check(&data), "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa");
+        assert_eq!(decode_check("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa").unwrap(), data);
+        assert!(matches!(
+            decode_check("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb"),
+            Err(AddressError::InvalidChecksum)
+        ));
+        assert!(matches!(decode_check("2g"), Err(AddressError::InvalidLength(1))));
+    }
+}
@@ -0,0 +1,217 @@
+use super::AddressError;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+const MAX_LENGTH: usize = 90;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Variant {
+    Bech32,
+    Bech32m,
+}
+
+impl Variant {
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc830a3,
+        }
+    }
+
+    fn from_constant(constant: u32) -> Option<Self> {
+        match constant {
+            1 => Some(Variant::Bech32),
+            0x2bc830a3 => Some(Variant::Bech32m),
+            _ => None,
+        }
+    }
+
+    pub fn for_witness_version(version: u8) -> Self {
+        if version == 0 {
+            Variant::Bech32
+        } else {
+            Variant::Bech32m
+        }
+    }
+}
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+    values.fold(1, |checksum, value| {
+        let top = checksum >> 25;
+        let checksum = (checksum & 0x1ffffff) << 5 ^ value as u32;
+        GENERATOR
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| (top >> bit) & 1 == 1)
+            .fold(checksum, |checksum, (_, generator)| checksum ^ generator)
+    })
+}
+
+fn expand_hrp(hrp: &str) -> impl Iterator<Item = u8> + '_ {
+    hrp.bytes()
+        .map(|byte| byte >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.bytes().map(|byte| byte & 0x1f))
+}
+
+pub fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, AddressError> {
+    let mut accumulator: u32 = 0;
+    let mut bits: u32 = 0;
+    let mut converted = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    let max = (1 << to) - 1;
+    for &value in data {
+        if (value as u32) >> from != 0 {
+            return Err(AddressError::InvalidPadding);
+        }
+        accumulator = accumulator << from | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            converted.push((accumulator >> bits & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            converted.push((accumulator << (to - bits) & max) as u8);
+        }
+    } else if bits >= from || (accumulator << (to - bits)) & max != 0 {
+        return Err(AddressError::InvalidPadding);
+    }
+    Ok(converted)
+}
+
+pub fn encode(hrp: &str, data: &[u8], variant: Variant) -> String {
+    let checksum = polymod(
+        expand_hrp(hrp)
+            .chain(data.iter().copied())
+            .chain([0u8; 6].iter().copied()),
+    ) ^ variant.constant();
+    let mut encoded = String::with_capacity(hrp.len() + 1 + data.len() + 6);
+    encoded.push_str(hrp);
+    encoded.push('1');
+    encoded.extend(data.iter().map(|&value| CHARSET[value as usize] as char));
+    encoded.extend((0..6).map(|index| CHARSET[(checksum >> (5 * (5 - index)) & 0x1f) as usize] as char));
+    encoded
+}
+
+pub fn decode(encoded: &str) -> Result<(String, Vec<u8>, Variant), AddressError> {
+    if encoded.len() > MAX_LENGTH {
+        return Err(AddressError::InvalidLength(encoded.len()));
+    }
+    let has_lower = encoded.bytes().any(|byte| byte.is_ascii_lowercase());
+    let has_upper = encoded.bytes().any(|byte| byte.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err(AddressError::MixedCase);
+    }
+    let encoded = encoded.to_ascii_lowercase();
+    let separator = encoded.rfind('1').ok_or(AddressError::MissingSeparator)?;
+    if separator == 0 || separator + 7 > encoded.len() {
+        return Err(AddressError::InvalidLength(encoded.len()));
+    }
+    let (hrp, data) = (&encoded[..separator], &encoded[separator + 1..]);
+    if let Some(invalid) = hrp.chars().find(|character| !(33..=126).contains(&(*character as u32))) {
+        return Err(AddressError::InvalidCharacter(invalid));
+    }
+    let values = data
+        .chars()
+        .map(|character| {
+            CHARSET
+                .iter()
+                .position(|&symbol| symbol as char == character)
+                .map(|value| value as u8)
+                .ok_or(AddressError::InvalidCharacter(character))
+        })
+        .collect::<Result<Vec<u8>, _>>()?;
+    let variant = Variant::from_constant(polymod(expand_hrp(hrp).chain(values.iter().copied())))
+        .ok_or(AddressError::InvalidChecksum)?;
+    Ok((hrp.to_owned(), values[..values.len() - 6].to_owned(), variant))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_the_valid_strings_of_bip173_and_bip350() {
+        let vectors = [
+            ("A12UEL5L", Variant::Bech32),
+            ("a12uel5l", Variant::Bech32),
+            (
+                "an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs",
+                Variant::Bech32,
+            ),
+            ("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw", Variant::Bech32),
+            (
+                "11qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqc8247j",
+                Variant::Bech32,
+            ),
+            (
+                "split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w",
+                Variant::Bech32,
+            ),
+            ("?1ezyfcl", Variant::Bech32),
+            ("A1LQFN3A", Variant::Bech32m),
+            ("a1lqfn3a", Variant::Bech32m),
+            (
+                "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+                Variant::Bech32m,
+            ),
+            ("abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx", Variant::Bech32m),
+            (
+                "11llllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllludsr8",
+                Variant::Bech32m,
+            ),
+            (
+                "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+                Variant::Bech32m,
+            ),
+            ("?1v759aa", Variant::Bech32m),
+        ];
+        for &(encoded, variant) in &vectors {
+            let (hrp, data, found) = decode(encoded).unwrap();
+            assert_eq!(found, variant);
+            assert_eq!(encode(&hrp, &data, variant), encoded.to_ascii_lowercase());
+        }
+    }
+
+    #[test]
+    fn refuses_the_invalid_strings_of_bip173_and_bip350() {
+        let vectors = [
+            "\x201nwldj5",
+            "\x7f1axkwrx",
+            "an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx",
+            "pzry9x0s0muk",
+            "1pzry9x0s0muk",
+            "x1b4n0q5v",
+            "li1dgmt3",
+            "A1G7SGD8",
+            "10a06t8",
+            "1qzzfhee",
+            "M1VUXWEZ",
+            "16plkw9",
+            "1p2gdwpf",
+            "qyrz8wqd2c9m",
+            "y1b0jsk6g",
+            "lt1igcx5c0",
+            "in1muywd",
+            "mm1crxm3i",
+            "au1s5cgom",
+        ];
+        for encoded in &vectors {
+            assert!(decode(encoded).is_err(), "{}", encoded);
+        }
+        assert!(matches!(decode("a12UEL5L"), Err(AddressError::MixedCase)));
+    }
+
+    #[test]
+    fn converts_between_bit_groups() {
+        let data = [0xff, 0x00, 0x80];
+        let groups = convert_bits(&data, 8, 5, true).unwrap();
+        assert_eq!(groups, vec![31, 28, 0, 8, 0]);
+        assert_eq!(convert_bits(&groups, 5, 8, false).unwrap(), data);
+        // more than four bits of padding, or padding that is not zero
+        assert!(convert_bits(&[31, 28, 0, 8, 0, 0], 5, 8, false).is_err());
+        assert!(convert_bits(&[31, 28, 0, 8, 1], 5, 8, false).is_err());
+        assert!(convert_bits(&[32], 5, 8, true).is_err());
+    }
+}
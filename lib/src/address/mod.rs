@@ -0,0 +1,317 @@
+#[cfg(feature = "writer")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+pub mod base58;
+pub mod bech32;
+
+use crate::blockchain::script::opcodes::*;
+use crate::blockchain::script::{BitcoinScript, StandardScript, ANCHOR_PROGRAM};
+use crate::network::Network;
+use bech32::Variant;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Payload {
+    PubkeyHash([u8; 20]),
+    ScriptHash([u8; 20]),
+    WitnessProgram { version: u8, program: Vec<u8> },
+}
+
+impl Payload {
+    pub fn from_script(script: &StandardScript) -> Option<Self> {
+        match *script {
+            StandardScript::P2pkh(hash) => Some(Payload::PubkeyHash(hash)),
+            StandardScript::P2sh(hash) => Some(Payload::ScriptHash(hash)),
+            StandardScript::P2wpkh(hash) => Some(Payload::WitnessProgram {
+                version: 0,
+                program: hash.to_vec(),
+            }),
+            StandardScript::P2wsh(hash) => Some(Payload::WitnessProgram {
+                version: 0,
+                program: hash.to_vec(),
+            }),
+            StandardScript::P2tr(key) => Some(Payload::WitnessProgram {
+                version: 1,
+                program: key.to_vec(),
+            }),
+            StandardScript::Anchor => Some(Payload::WitnessProgram {
+                version: 1,
+                program: ANCHOR_PROGRAM.to_vec(),
+            }),
+            StandardScript::WitnessUnknown { version, ref program } => Some(Payload::WitnessProgram {
+                version,
+                program: program.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn script_pubkey(&self) -> BitcoinScript {
+        let mut script = vec![];
+        match *self {
+            Payload::PubkeyHash(ref hash) => {
+                script.extend_from_slice(&[OP_DUP, OP_HASH160, 0x14]);
+                script.extend_from_slice(hash);
+                script.extend_from_slice(&[OP_EQUALVERIFY, OP_CHECKSIG]);
+            }
+            Payload::ScriptHash(ref hash) => {
+                script.extend_from_slice(&[OP_HASH160, 0x14]);
+                script.extend_from_slice(hash);
+                script.push(OP_EQUAL);
+            }
+            Payload::WitnessProgram { version, ref program } => {
+                script.push(if version == 0 { OP_0 } else { OP_1 + version - 1 });
+                script.push(program.len() as u8);
+                script.extend_from_slice(program);
+            }
+        }
+        BitcoinScript::new(script)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Address {
+    network: Network,
+    payload: Payload,
+}
+
+impl Address {
+    pub fn new(network: Network, payload: Payload) -> Self {
+        Self { network, payload }
+    }
+
+    pub fn from_script(script: &StandardScript, network: Network) -> Option<Self> {
+        Payload::from_script(script).map(|payload| Self::new(network, payload))
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    pub fn payload(&self) -> &Payload {
+        &self.payload
+    }
+
+    pub fn script_pubkey(&self) -> BitcoinScript {
+        self.payload.script_pubkey()
+    }
+
+    pub fn decode(address: &str, network: Network) -> Result<Self, AddressError> {
+        let decoded = Self::from_str(address)?;
+        if decoded.is_valid_for_network(network) {
+            Ok(Self::new(network, decoded.payload))
+        } else {
+            Err(AddressError::NetworkMismatch(network))
+        }
+    }
+
+    pub fn is_valid_for_network(&self, network: Network) -> bool {
+        match self.payload {
+            Payload::PubkeyHash(_) | Payload::ScriptHash(_) => self.network.p2pkh_prefix() == network.p2pkh_prefix(),
+            Payload::WitnessProgram { .. } => self.network.bech32_hrp() == network.bech32_hrp(),
+        }
+    }
+
+    fn decode_base58(address: &str) -> Result<Self, AddressError> {
+        let data = base58::decode_check(address)?;
+        if data.len() != 21 {
+            return Err(AddressError::InvalidLength(data.len()));
+        }
+        let mut hash = [0; 20];
+        hash.copy_from_slice(&data[1..]);
+        match data[0] {
+            0x00 => Ok(Self::new(Network::Bitcoin, Payload::PubkeyHash(hash))),
+            0x05 => Ok(Self::new(Network::Bitcoin, Payload::ScriptHash(hash))),
+            0x6f => Ok(Self::new(Network::Testnet, Payload::PubkeyHash(hash))),
+            0xc4 => Ok(Self::new(Network::Testnet, Payload::ScriptHash(hash))),
+            prefix => Err(AddressError::UnknownPrefix(prefix)),
+        }
+    }
+
+    fn decode_segwit(address: &str) -> Result<Self, AddressError> {
+        let (hrp, data, variant) = bech32::decode(address)?;
+        let network = match hrp.as_str() {
+            "bc" => Network::Bitcoin,
+            "tb" => Network::Testnet,
+            "bcrt" => Network::Regtest,
+            _ => return Err(AddressError::UnknownHrp(hrp)),
+        };
+        let (&version, program) = data.split_first().ok_or(AddressError::InvalidLength(0))?;
+        if version > 16 {
+            return Err(AddressError::InvalidWitnessVersion(version));
+        }
+        if variant != Variant::for_witness_version(version) {
+            return Err(AddressError::InvalidVariant(version));
+        }
+        let program = bech32::convert_bits(program, 5, 8, false)?;
+        if program.len() < 2 || program.len() > 40 || (version == 0 && program.len() != 20 && program.len() != 32) {
+            return Err(AddressError::InvalidProgramLength(program.len()));
+        }
+        Ok(Self::new(network, Payload::WitnessProgram { version, program }))
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.payload {
+            Payload::PubkeyHash(ref hash) | Payload::ScriptHash(ref hash) => {
+                let prefix = match self.payload {
+                    Payload::PubkeyHash(_) => self.network.p2pkh_prefix(),
+                    _ => self.network.p2sh_prefix(),
+                };
+                let mut data = Vec::with_capacity(21);
+                data.push(prefix);
+                data.extend_from_slice(hash);
+                formatter.write_str(&base58::encode_check(&data))
+            }
+            Payload::WitnessProgram { version, ref program } => {
+                let mut data = vec![version];
+                data.extend(bech32::convert_bits(program, 8, 5, true).expect("8 bit values are always in range"));
+                let variant = Variant::for_witness_version(version);
+                formatter.write_str(&bech32::encode(self.network.bech32_hrp(), &data, variant))
+            }
+        }
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        let lowercase = address.to_ascii_lowercase();
+        if ["bc1", "tb1", "bcrt1"].iter().any(|hrp| lowercase.starts_with(hrp)) {
+            Self::decode_segwit(address)
+        } else {
+            Self::decode_base58(address)
+        }
+    }
+}
+
+#[cfg(feature = "writer")]
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "writer")]
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = <&str as Deserialize<'de>>::deserialize(deserializer)?;
+        Address::from_str(address).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AddressError {
+    #[error("invalid character: {0}")]
+    InvalidCharacter(char),
+    #[error("invalid data length: {0}")]
+    InvalidLength(usize),
+    #[error("invalid checksum")]
+    InvalidChecksum,
+    #[error("mixed case in bech32 string")]
+    MixedCase,
+    #[error("missing bech32 separator")]
+    MissingSeparator,
+    #[error("invalid padding in bech32 data")]
+    InvalidPadding,
+    #[error("unknown base58 version prefix: {0:#04x}")]
+    UnknownPrefix(u8),
+    #[error("unknown bech32 human readable part: {0}")]
+    UnknownHrp(String),
+    #[error("invalid witness version: {0}")]
+    InvalidWitnessVersion(u8),
+    #[error("wrong checksum variant for witness version {0}")]
+    InvalidVariant(u8),
+    #[error("invalid witness program length: {0}")]
+    InvalidProgramLength(usize),
+    #[error("address is not valid for network {0}")]
+    NetworkMismatch(Network),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the valid segwit addresses of BIP173 and BIP350 with their script
+    #[test]
+    fn decodes_the_valid_segwit_addresses() {
+        let vectors = [
+            (
+                "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            (
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+            ),
+            (
+                "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y",
+                "5128751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            ("BC1SW50QGDZ25J", "6002751e"),
+            (
+                "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs",
+                "5210751e76e8199196d454941c45d1b3a323",
+            ),
+            (
+                "tb1qqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesrxh6hy",
+                "0020000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433",
+            ),
+            (
+                "tb1pqqqqp399et2xygdj5xreqhjjvcmzhxw4aywxecjdzew6hylgvsesf3hn0c",
+                "5120000000c4a5cad46221b2a187905e5266362b99d5e91c6ce24d165dab93e86433",
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            ),
+        ];
+        for &(encoded, script) in &vectors {
+            let address = Address::from_str(encoded).unwrap();
+            assert_eq!(hex::encode(address.script_pubkey().as_bytes()), script);
+            assert_eq!(address.to_string(), encoded.to_ascii_lowercase());
+        }
+    }
+
+    #[test]
+    fn refuses_the_invalid_segwit_addresses() {
+        let vectors = [
+            "tc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq5zuyut",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+            "tb1z0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqglt7rf",
+            "BC1S0XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ54WELL",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+            "tb1q0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq24jc47",
+            "bc1p38j9r5y49hruaue7wxjce0updqjuyyx0kh56v8s25huc6995vvpql3jow4",
+            "BC130XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ7ZWS8R",
+            "bc1pw5dgrnzv",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v8n0nx0muaewav253zgeav",
+            "BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P",
+            "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq47Zagq",
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7v07qwwzcrf",
+            "tb1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vpggkg4j",
+            "bc1gmk9yu",
+        ];
+        for encoded in &vectors {
+            assert!(Address::from_str(encoded).is_err(), "{}", encoded);
+        }
+    }
+
+    #[test]
+    fn decodes_the_base58_addresses() {
+        let address = Address::from_str("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa").unwrap();
+        assert_eq!(address.network(), Network::Bitcoin);
+        assert_eq!(
+            hex::encode(address.script_pubkey().as_bytes()),
+            "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac"
+        );
+        assert!(Address::decode("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa", Network::Testnet).is_err());
+        let script = Address::from_str("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy").unwrap();
+        assert!(matches!(script.payload(), Payload::ScriptHash(_)));
+        assert_eq!(script.to_string(), "3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy");
+    }
+}
@@ -0,0 +1,404 @@
+use serde::ser::{
+    self, Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+    SerializeTupleStruct, SerializeTupleVariant,
+};
+use serde::{Serialize, Serializer};
+use std::marker::PhantomData;
+
+use super::{serialize_btc_decimal, Amount, AmountFormat};
+
+// the name of the newtype amounts are serialized as, serializers that do not know it write the satoshis inside
+pub(super) const AMOUNT_TOKEN: &str = "$bplib::private::Amount";
+
+// a value serialized with its amounts in the format given
+pub struct WithAmounts<'a, T: ?Sized> {
+    value: &'a T,
+    format: AmountFormat,
+}
+
+impl<'a, T: ?Sized> WithAmounts<'a, T> {
+    pub fn new(value: &'a T, format: AmountFormat) -> Self {
+        Self { value, format }
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for WithAmounts<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(AmountSerializer::new(serializer, self.format))
+    }
+}
+
+// forwards everything to the serializer it wraps, writing the amounts met on the way in the format given
+pub struct AmountSerializer<S> {
+    inner: S,
+    format: AmountFormat,
+}
+
+impl<S> AmountSerializer<S> {
+    pub fn new(inner: S, format: AmountFormat) -> Self {
+        Self { inner, format }
+    }
+}
+
+impl<S: Serializer> Serializer for AmountSerializer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Compound<S::SerializeSeq>;
+    type SerializeTuple = Compound<S::SerializeTuple>;
+    type SerializeTupleStruct = Compound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = Compound<S::SerializeTupleVariant>;
+    type SerializeMap = Compound<S::SerializeMap>;
+    type SerializeStruct = Compound<S::SerializeStruct>;
+    type SerializeStructVariant = Compound<S::SerializeStructVariant>;
+
+    fn serialize_bool(self, value: bool) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_bool(value)
+    }
+
+    fn serialize_i8(self, value: i8) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i8(value)
+    }
+
+    fn serialize_i16(self, value: i16) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i16(value)
+    }
+
+    fn serialize_i32(self, value: i32) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i32(value)
+    }
+
+    fn serialize_i64(self, value: i64) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i64(value)
+    }
+
+    fn serialize_i128(self, value: i128) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i128(value)
+    }
+
+    fn serialize_u8(self, value: u8) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u8(value)
+    }
+
+    fn serialize_u16(self, value: u16) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u16(value)
+    }
+
+    fn serialize_u32(self, value: u32) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u32(value)
+    }
+
+    fn serialize_u64(self, value: u64) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u64(value)
+    }
+
+    fn serialize_u128(self, value: u128) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u128(value)
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_f32(value)
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_f64(value)
+    }
+
+    fn serialize_char(self, value: char) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_char(value)
+    }
+
+    fn serialize_str(self, value: &str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_str(value)
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_bytes(value)
+    }
+
+    fn serialize_none(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_none()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_some(&WithAmounts::new(value, self.format))
+    }
+
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, index: u32, variant: &'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_variant(name, index, variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, name: &'static str, value: &T) -> Result<S::Ok, S::Error> {
+        if name != AMOUNT_TOKEN {
+            return self
+                .inner
+                .serialize_newtype_struct(name, &WithAmounts::new(value, self.format));
+        }
+        match self.format {
+            AmountFormat::Satoshis => value.serialize(self.inner),
+            AmountFormat::Bitcoins => {
+                let satoshis = value.serialize(SatoshiSerializer(PhantomData))?;
+                serialize_btc_decimal(&Amount::from_sat(satoshis), self.inner)
+            }
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner
+            .serialize_newtype_variant(name, index, variant, &WithAmounts::new(value, self.format))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        let format = self.format;
+        let inner = self.inner.serialize_seq(len)?;
+        Ok(Compound { inner, format })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        let format = self.format;
+        let inner = self.inner.serialize_tuple(len)?;
+        Ok(Compound { inner, format })
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, S::Error> {
+        let format = self.format;
+        let inner = self.inner.serialize_tuple_struct(name, len)?;
+        Ok(Compound { inner, format })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        let format = self.format;
+        let inner = self.inner.serialize_tuple_variant(name, index, variant, len)?;
+        Ok(Compound { inner, format })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        let format = self.format;
+        let inner = self.inner.serialize_map(len)?;
+        Ok(Compound { inner, format })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, S::Error> {
+        let format = self.format;
+        let inner = self.inner.serialize_struct(name, len)?;
+        Ok(Compound { inner, format })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        let format = self.format;
+        let inner = self.inner.serialize_struct_variant(name, index, variant, len)?;
+        Ok(Compound { inner, format })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+// the elements, fields and entries of a compound value, each with its amounts in the format given
+pub struct Compound<C> {
+    inner: C,
+    format: AmountFormat,
+}
+
+impl<C: SerializeSeq> SerializeSeq for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_element(&WithAmounts::new(value, self.format))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTuple> SerializeTuple for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_element(&WithAmounts::new(value, self.format))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTupleStruct> SerializeTupleStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_field(&WithAmounts::new(value, self.format))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTupleVariant> SerializeTupleVariant for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_field(&WithAmounts::new(value, self.format))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeMap> SerializeMap for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), C::Error> {
+        self.inner.serialize_key(&WithAmounts::new(key, self.format))
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_value(&WithAmounts::new(value, self.format))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeStruct> SerializeStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_field(key, &WithAmounts::new(value, self.format))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeStructVariant> SerializeStructVariant for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_field(key, &WithAmounts::new(value, self.format))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+// takes the satoshis out of an amount, which is the only value it accepts
+struct SatoshiSerializer<E>(PhantomData<E>);
+
+macro_rules! reject {
+    ($($method:ident($($arg:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ok, E> {
+                Err(ser::Error::custom("amounts are serialized from their satoshis"))
+            }
+        )*
+    };
+}
+
+impl<E: ser::Error> Serializer for SatoshiSerializer<E> {
+    type Ok = u64;
+    type Error = E;
+    type SerializeSeq = Impossible<u64, E>;
+    type SerializeTuple = Impossible<u64, E>;
+    type SerializeTupleStruct = Impossible<u64, E>;
+    type SerializeTupleVariant = Impossible<u64, E>;
+    type SerializeMap = Impossible<u64, E>;
+    type SerializeStruct = Impossible<u64, E>;
+    type SerializeStructVariant = Impossible<u64, E>;
+
+    fn serialize_u64(self, satoshis: u64) -> Result<u64, E> {
+        Ok(satoshis)
+    }
+
+    reject! {
+        serialize_bool(bool) -> u64;
+        serialize_i8(i8) -> u64;
+        serialize_i16(i16) -> u64;
+        serialize_i32(i32) -> u64;
+        serialize_i64(i64) -> u64;
+        serialize_u8(u8) -> u64;
+        serialize_u16(u16) -> u64;
+        serialize_u32(u32) -> u64;
+        serialize_f32(f32) -> u64;
+        serialize_f64(f64) -> u64;
+        serialize_char(char) -> u64;
+        serialize_str(&str) -> u64;
+        serialize_bytes(&[u8]) -> u64;
+        serialize_none() -> u64;
+        serialize_unit() -> u64;
+        serialize_unit_struct(&'static str) -> u64;
+        serialize_unit_variant(&'static str, u32, &'static str) -> u64;
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant;
+        serialize_map(Option<usize>) -> Self::SerializeMap;
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct;
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<u64, E> {
+        Err(ser::Error::custom("amounts are serialized from their satoshis"))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _: &'static str, _: &T) -> Result<u64, E> {
+        Err(ser::Error::custom("amounts are serialized from their satoshis"))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<u64, E> {
+        Err(ser::Error::custom("amounts are serialized from their satoshis"))
+    }
+}
@@ -0,0 +1,314 @@
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "writer")]
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[cfg(feature = "writer")]
+mod format;
+
+#[cfg(feature = "writer")]
+pub use format::{AmountSerializer, WithAmounts};
+
+pub const COIN: u64 = 100_000_000;
+pub const MAX_MONEY: Amount = Amount(21_000_000 * COIN);
+
+pub const INITIAL_SUBSIDY: Amount = Amount(50 * COIN);
+pub const HALVING_INTERVAL: u32 = 210_000;
+
+// a value in satoshis, the arithmetic is checked against MAX_MONEY as the reference client does with MoneyRange
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Amount(u64);
+
+impl Amount {
+    pub const ZERO: Amount = Amount(0);
+    pub const ONE_SAT: Amount = Amount(1);
+    pub const ONE_BTC: Amount = Amount(COIN);
+
+    pub const fn from_sat(satoshis: u64) -> Self {
+        Self(satoshis)
+    }
+
+    pub fn from_btc(bitcoins: u64) -> Option<Self> {
+        bitcoins.checked_mul(COIN).map(Self).filter(Amount::is_valid)
+    }
+
+    pub const fn to_sat(self) -> u64 {
+        self.0
+    }
+
+    pub fn to_btc(self) -> f64 {
+        self.0 as f64 / COIN as f64
+    }
+
+    // values can be above the bound in blocks that are not valid, they are kept as they are
+    pub fn is_valid(&self) -> bool {
+        *self <= MAX_MONEY
+    }
+
+    pub fn checked_add(self, other: Amount) -> Option<Amount> {
+        self.0.checked_add(other.0).map(Self).filter(Amount::is_valid)
+    }
+
+    pub fn checked_sub(self, other: Amount) -> Option<Amount> {
+        self.0.checked_sub(other.0).map(Self)
+    }
+
+    pub fn checked_mul(self, factor: u64) -> Option<Amount> {
+        self.0.checked_mul(factor).map(Self).filter(Amount::is_valid)
+    }
+
+    pub fn checked_div(self, divisor: u64) -> Option<Amount> {
+        self.0.checked_div(divisor).map(Self)
+    }
+
+    pub fn saturating_add(self, other: Amount) -> Amount {
+        self.checked_add(other).unwrap_or(MAX_MONEY)
+    }
+
+    pub fn saturating_sub(self, other: Amount) -> Amount {
+        Self(self.0.saturating_sub(other.0))
+    }
+
+    pub fn checked_sum<I: IntoIterator<Item = Amount>>(amounts: I) -> Option<Amount> {
+        amounts
+            .into_iter()
+            .try_fold(Amount::ZERO, |total, amount| total.checked_add(amount))
+    }
+
+    pub fn from_str_in(number: &str, denomination: Denomination) -> Result<Amount, AmountError> {
+        let (integer, fraction) = match number.split_once('.') {
+            Some((integer, fraction)) => (integer, fraction),
+            None => (number, ""),
+        };
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(AmountError::Empty);
+        }
+        if let Some(character) = integer.chars().chain(fraction.chars()).find(|c| !c.is_ascii_digit()) {
+            return Err(match character {
+                '-' => AmountError::Negative,
+                character => AmountError::InvalidCharacter(character),
+            });
+        }
+        let precision = denomination.precision();
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.len() > precision {
+            return Err(AmountError::TooPrecise);
+        }
+        let digits = format!("{}{}{}", integer, fraction, "0".repeat(precision - fraction.len()));
+        let digits = digits.trim_start_matches('0');
+        if digits.is_empty() {
+            return Ok(Amount::ZERO);
+        }
+        digits
+            .parse()
+            .ok()
+            .map(Amount)
+            .filter(Amount::is_valid)
+            .ok_or(AmountError::TooBig)
+    }
+
+    pub fn to_string_in(self, denomination: Denomination) -> String {
+        let precision = denomination.precision();
+        if precision == 0 {
+            return self.0.to_string();
+        }
+        let unit = 10u64.pow(precision as u32);
+        format!("{}.{:0width$}", self.0 / unit, self.0 % unit, width = precision)
+    }
+
+    pub fn to_string_with_denomination(self, denomination: Denomination) -> String {
+        format!("{} {}", self.to_string_in(denomination), denomination)
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.pad(&self.to_string_with_denomination(Denomination::Bitcoin))
+    }
+}
+
+// expects the denomination after the number, as in "0.5 BTC" or "1000 sat"
+impl FromStr for Amount {
+    type Err = AmountError;
+
+    fn from_str(amount: &str) -> Result<Self, Self::Err> {
+        let (number, denomination) = amount.trim().split_once(' ').ok_or(AmountError::MissingDenomination)?;
+        Amount::from_str_in(number, denomination.trim().parse()?)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Denomination {
+    Bitcoin,
+    MilliBitcoin,
+    Satoshi,
+}
+
+impl Denomination {
+    pub fn precision(self) -> usize {
+        match self {
+            Denomination::Bitcoin => 8,
+            Denomination::MilliBitcoin => 5,
+            Denomination::Satoshi => 0,
+        }
+    }
+}
+
+impl fmt::Display for Denomination {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.pad(match self {
+            Denomination::Bitcoin => "BTC",
+            Denomination::MilliBitcoin => "mBTC",
+            Denomination::Satoshi => "sat",
+        })
+    }
+}
+
+impl FromStr for Denomination {
+    type Err = AmountError;
+
+    fn from_str(denomination: &str) -> Result<Self, Self::Err> {
+        match denomination {
+            "BTC" | "btc" => Ok(Denomination::Bitcoin),
+            "mBTC" | "mbtc" => Ok(Denomination::MilliBitcoin),
+            "sat" | "sats" | "satoshi" | "satoshis" => Ok(Denomination::Satoshi),
+            _ => Err(AmountError::UnknownDenomination(denomination.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum AmountError {
+    #[error("the amount is empty")]
+    Empty,
+    #[error("amounts can not be negative")]
+    Negative,
+    #[error("invalid character {0:?} in the amount")]
+    InvalidCharacter(char),
+    #[error("the amount has more decimals than the denomination allows")]
+    TooPrecise,
+    #[error("the amount is above the maximum money supply")]
+    TooBig,
+    #[error("the amount is missing its denomination")]
+    MissingDenomination,
+    #[error("unknown denomination {0}")]
+    UnknownDenomination(String),
+}
+
+// the subsidy halves every 210000 blocks until the shift leaves nothing
+pub fn block_subsidy(height: u32) -> Amount {
+    match height / HALVING_INTERVAL {
+        halvings @ 0..=63 => Amount(INITIAL_SUBSIDY.0 >> halvings),
+        _ => Amount::ZERO,
+    }
+}
+
+// the total issued by the subsidies of the blocks up to the height given, both included
+pub fn subsidy_supply(height: u32) -> Amount {
+    let mut supply = Amount::ZERO;
+    let mut start = 0u64;
+    while start <= height as u64 {
+        let subsidy = block_subsidy(start as u32);
+        if subsidy == Amount::ZERO {
+            break;
+        }
+        let end = (start + HALVING_INTERVAL as u64).min(height as u64 + 1);
+        supply = Amount(supply.0 + subsidy.0 * (end - start));
+        start += HALVING_INTERVAL as u64;
+    }
+    supply
+}
+
+// how the amounts of a value are serialized when it is wrapped in WithAmounts, satoshis otherwise
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AmountFormat {
+    Satoshis,
+    Bitcoins,
+}
+
+// a newtype only an AmountSerializer recognizes, the others write the satoshis it holds
+#[cfg(feature = "writer")]
+impl Serialize for Amount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(format::AMOUNT_TOKEN, &self.0)
+    }
+}
+
+// a raw number keeps the eight decimals of the reference client, which a float would lose
+#[cfg(all(feature = "writer", feature = "json"))]
+fn serialize_btc_decimal<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+    json::value::RawValue::from_string(amount.to_string_in(Denomination::Bitcoin))
+        .map_err(serde::ser::Error::custom)?
+        .serialize(serializer)
+}
+
+#[cfg(all(feature = "writer", not(feature = "json")))]
+fn serialize_btc_decimal<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+    as_btc::serialize(amount, serializer)
+}
+
+// accepts both formats, integers as satoshis and decimals as bitcoins
+#[cfg(feature = "writer")]
+impl<'de> Deserialize<'de> for Amount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(AmountVisitor)
+    }
+}
+
+#[cfg(feature = "writer")]
+struct AmountVisitor;
+
+#[cfg(feature = "writer")]
+impl<'de> serde::de::Visitor<'de> for AmountVisitor {
+    type Value = Amount;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("an amount in satoshis or in bitcoins")
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, satoshis: u64) -> Result<Amount, E> {
+        Ok(Amount::from_sat(satoshis))
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, satoshis: i64) -> Result<Amount, E> {
+        u64::try_from(satoshis)
+            .map(Amount::from_sat)
+            .map_err(|_| E::custom(AmountError::Negative))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, bitcoins: f64) -> Result<Amount, E> {
+        Amount::from_str_in(&format!("{:.8}", bitcoins), Denomination::Bitcoin).map_err(E::custom)
+    }
+}
+
+#[cfg(feature = "writer")]
+pub mod as_sat {
+    use super::Amount;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(amount.to_sat())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        u64::deserialize(deserializer).map(Amount::from_sat)
+    }
+}
+
+#[cfg(feature = "writer")]
+pub mod as_btc {
+    use super::{Amount, Denomination};
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(amount: &Amount, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(amount.to_btc())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Amount, D::Error> {
+        let bitcoins = f64::deserialize(deserializer)?;
+        Amount::from_str_in(&format!("{:.8}", bitcoins), Denomination::Bitcoin).map_err(de::Error::custom)
+    }
+}
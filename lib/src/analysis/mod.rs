@@ -0,0 +1,2 @@
+pub mod pubkeys;
+pub mod signatures;
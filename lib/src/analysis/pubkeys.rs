@@ -0,0 +1,360 @@
+use k256::elliptic_curve::point::DecompressPoint;
+use k256::elliptic_curve::sec1::FromEncodedPoint;
+use k256::elliptic_curve::subtle::Choice;
+use k256::{AffinePoint, EncodedPoint, FieldBytes};
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::{Entry, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::blockchain::script::ScriptType;
+use crate::blockchain::script::StandardScript;
+#[cfg(feature = "writer")]
+use crate::blockchain::serde_custom;
+use crate::blockchain::spend::SpendType;
+use crate::blockchain::transactions::Transaction;
+use crate::types::Txid;
+#[cfg(feature = "parser")]
+use crate::{blkparser::SerialTransaction, visitor::ChainVisitor};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "writer", serde(rename_all = "snake_case"))]
+pub enum KeyFormat {
+    Compressed,
+    Uncompressed,
+    XOnly,
+    Hybrid,
+}
+
+impl fmt::Display for KeyFormat {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            KeyFormat::Compressed => formatter.pad("compressed"),
+            KeyFormat::Uncompressed => formatter.pad("uncompressed"),
+            KeyFormat::XOnly => formatter.pad("x_only"),
+            KeyFormat::Hybrid => formatter.pad("hybrid"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct PublicKey {
+    #[cfg_attr(feature = "writer", serde(with = "serde_custom::hex_bytes"))]
+    bytes: Vec<u8>,
+    format: KeyFormat,
+}
+
+impl PublicKey {
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let format = match (bytes.len(), bytes.first()?) {
+            (33, 0x02) | (33, 0x03) => KeyFormat::Compressed,
+            (65, 0x04) => KeyFormat::Uncompressed,
+            (65, 0x06) | (65, 0x07) => KeyFormat::Hybrid,
+            (32, _) => KeyFormat::XOnly,
+            _ => return None,
+        };
+        Some(Self {
+            bytes: bytes.to_owned(),
+            format,
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn format(&self) -> KeyFormat {
+        self.format
+    }
+
+    // the x coordinate identifies the same key across the different encodings
+    pub fn x_coordinate(&self) -> &[u8] {
+        match self.format {
+            KeyFormat::XOnly => &self.bytes,
+            _ => &self.bytes[1..33],
+        }
+    }
+
+    // whether y is odd. BIP340 x-only keys stand for the point with an even y, and hybrid keys take it from
+    // the y coordinate they carry like uncompressed keys do, the prefix is only checked against it
+    pub fn parity(&self) -> bool {
+        match self.format {
+            KeyFormat::XOnly => false,
+            KeyFormat::Compressed => self.bytes[0] & 1 == 1,
+            KeyFormat::Uncompressed | KeyFormat::Hybrid => self.bytes[64] & 1 == 1,
+        }
+    }
+
+    pub fn point(&self) -> KeyPoint {
+        KeyPoint {
+            x: <[u8; 32]>::try_from(self.x_coordinate()).expect("x coordinates have 32 bytes"),
+            parity: self.parity(),
+        }
+    }
+
+    pub fn is_on_curve(&self) -> bool {
+        match self.format {
+            KeyFormat::XOnly => {
+                let x = <[u8; 32]>::try_from(&self.bytes[..]).expect("x-only keys have 32 bytes");
+                bool::from(AffinePoint::decompress(&FieldBytes::from(x), Choice::from(0)).is_some())
+            }
+            KeyFormat::Compressed | KeyFormat::Uncompressed => Self::decode_sec1(&self.bytes),
+            // hybrid keys are uncompressed keys whose prefix also commits to the parity of y
+            KeyFormat::Hybrid => {
+                let mut uncompressed = self.bytes.clone();
+                uncompressed[0] = 0x04;
+                self.bytes[0] & 1 == self.bytes[64] & 1 && Self::decode_sec1(&uncompressed)
+            }
+        }
+    }
+
+    fn decode_sec1(bytes: &[u8]) -> bool {
+        EncodedPoint::from_bytes(bytes)
+            .map(|point| bool::from(AffinePoint::from_encoded_point(&point).is_some()))
+            .unwrap_or(false)
+    }
+}
+
+// the point a key is encoded from, the same for its compressed, uncompressed, hybrid and x-only encodings
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyPoint {
+    x: [u8; 32],
+    parity: bool,
+}
+
+impl KeyPoint {
+    pub fn x(&self) -> &[u8; 32] {
+        &self.x
+    }
+
+    pub fn parity(&self) -> bool {
+        self.parity
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "writer", serde(tag = "kind", rename_all = "snake_case"))]
+pub enum KeyLocation {
+    Output { vout: u32, script_type: ScriptType },
+    Input { vin: u32, spend_type: SpendType },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct KeyOccurrence {
+    txid: Txid,
+    #[cfg_attr(feature = "writer", serde(flatten))]
+    location: KeyLocation,
+}
+
+impl KeyOccurrence {
+    pub fn txid(&self) -> &Txid {
+        &self.txid
+    }
+
+    pub fn location(&self) -> KeyLocation {
+        self.location
+    }
+
+    pub fn is_output(&self) -> bool {
+        matches!(self.location, KeyLocation::Output { .. })
+    }
+}
+
+// keys locked in outputs (p2pk, bare multisig and taproot) are exposed before being spent
+pub fn transaction_keys(transaction: &Transaction) -> Vec<(PublicKey, KeyLocation)> {
+    let mut keys = vec![];
+    for (vout, output) in transaction.outputs().iter().enumerate() {
+        let location = KeyLocation::Output {
+            vout: vout as u32,
+            script_type: output.script_type(),
+        };
+        let script_keys = match output.classify() {
+            StandardScript::P2pk(key) => vec![key],
+            StandardScript::Multisig { keys, .. } => keys,
+            StandardScript::P2tr(key) => vec![key.to_vec()],
+            _ => continue,
+        };
+        keys.extend(
+            script_keys
+                .iter()
+                .filter_map(|key| PublicKey::parse(key))
+                .map(|key| (key, location)),
+        );
+    }
+    for (vin, input) in transaction.inputs().iter().enumerate() {
+        if input.utxo().is_coinbase() {
+            continue;
+        }
+        let info = input.spend_info();
+        let location = KeyLocation::Input {
+            vin: vin as u32,
+            spend_type: info.kind(),
+        };
+        keys.extend(
+            info.pubkeys()
+                .iter()
+                .filter_map(|key| PublicKey::parse(key))
+                .map(|key| (key, location)),
+        );
+    }
+    keys
+}
+
+// an encoding a key was seen in, the hybrid and off curve ones can differ for the same point
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct KeyEncoding {
+    #[cfg_attr(feature = "writer", serde(flatten))]
+    key: PublicKey,
+    on_curve: bool,
+}
+
+impl KeyEncoding {
+    fn new(key: PublicKey) -> Self {
+        let on_curve = key.is_on_curve();
+        Self { key, on_curve }
+    }
+
+    pub fn key(&self) -> &PublicKey {
+        &self.key
+    }
+
+    pub fn is_on_curve(&self) -> bool {
+        self.on_curve
+    }
+}
+
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct KeyEntry {
+    // in the order they were first seen
+    encodings: Vec<KeyEncoding>,
+    occurrences: Vec<KeyOccurrence>,
+}
+
+impl KeyEntry {
+    // the key as first seen
+    pub fn key(&self) -> &PublicKey {
+        &self.encodings[0].key
+    }
+
+    pub fn encodings(&self) -> &[KeyEncoding] {
+        &self.encodings
+    }
+
+    // every encoding seen decodes to a point on the curve
+    pub fn is_on_curve(&self) -> bool {
+        self.encodings.iter().all(KeyEncoding::is_on_curve)
+    }
+
+    pub fn occurrences(&self) -> &[KeyOccurrence] {
+        &self.occurrences
+    }
+
+    // occurrences are recorded in transaction order, so equal txids are adjacent
+    pub fn transactions(&self) -> usize {
+        let mut txids = self.occurrences.iter().map(KeyOccurrence::txid).collect::<Vec<_>>();
+        txids.dedup();
+        txids.len()
+    }
+
+    pub fn is_reused(&self) -> bool {
+        self.transactions() > 1
+    }
+
+    pub fn is_exposed_in_output(&self) -> bool {
+        self.occurrences.iter().any(KeyOccurrence::is_output)
+    }
+
+    // an encoding not seen yet is only checked against the curve once
+    fn add_encoding(&mut self, key: PublicKey) {
+        if !self.encodings.iter().any(|encoding| encoding.key == key) {
+            self.encodings.push(KeyEncoding::new(key));
+        }
+    }
+}
+
+// the keys by the point they encode, so a key seen in several encodings is a single entry
+#[derive(Default)]
+pub struct KeyIndex {
+    keys: HashMap<KeyPoint, KeyEntry>,
+}
+
+impl KeyIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, key: PublicKey, occurrence: KeyOccurrence) {
+        match self.keys.entry(key.point()) {
+            Entry::Occupied(mut entry) => {
+                let entry = entry.get_mut();
+                entry.add_encoding(key);
+                entry.occurrences.push(occurrence);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(KeyEntry {
+                    encodings: vec![KeyEncoding::new(key)],
+                    occurrences: vec![occurrence],
+                });
+            }
+        }
+    }
+
+    pub fn record_transaction(&mut self, txid: &Txid, transaction: &Transaction) {
+        for (key, location) in transaction_keys(transaction) {
+            self.record(key, KeyOccurrence { txid: *txid, location });
+        }
+    }
+
+    pub fn merge(&mut self, other: KeyIndex) {
+        for (point, other_entry) in other.keys {
+            match self.keys.entry(point) {
+                Entry::Occupied(mut entry) => {
+                    let entry = entry.get_mut();
+                    for encoding in other_entry.encodings {
+                        if !entry.encodings.contains(&encoding) {
+                            entry.encodings.push(encoding);
+                        }
+                    }
+                    entry.occurrences.extend(other_entry.occurrences);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(other_entry);
+                }
+            }
+        }
+    }
+
+    // the entry of the key in any of its encodings
+    pub fn get(&self, key: &PublicKey) -> Option<&KeyEntry> {
+        self.keys.get(&key.point())
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &KeyEntry> {
+        self.keys.values()
+    }
+
+    pub fn reused(&self) -> impl Iterator<Item = &KeyEntry> {
+        self.entries().filter(|entry| entry.is_reused())
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+#[cfg(feature = "parser")]
+impl ChainVisitor for KeyIndex {
+    fn on_transaction(&mut self, _height: u32, transaction: &SerialTransaction) {
+        self.record_transaction(transaction.hash(), transaction.transaction());
+    }
+}
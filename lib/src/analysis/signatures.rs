@@ -0,0 +1,176 @@
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::blockchain::signature::EcdsaSignature;
+use crate::blockchain::transactions::{Input, Transaction};
+use crate::types::BlockHash;
+#[cfg(feature = "parser")]
+use crate::{visitor::ChainVisitor, TransactionBlock};
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct SignatureStats {
+    signatures: u64,
+    invalid: u64,
+    strict_der: u64,
+    low_s: u64,
+    low_r: u64,
+    canonical: u64,
+    sighash_types: BTreeMap<String, u64>,
+}
+
+impl SignatureStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, signature: &EcdsaSignature) {
+        self.signatures += 1;
+        self.strict_der += signature.is_strict_der() as u64;
+        self.low_s += signature.is_low_s() as u64;
+        self.low_r += signature.is_low_r() as u64;
+        self.canonical += signature.is_canonical() as u64;
+        *self
+            .sighash_types
+            .entry(signature.sighash_type().to_string())
+            .or_default() += 1;
+    }
+
+    // schnorr signatures of taproot spends are left out, they have a single valid encoding
+    pub fn record_input(&mut self, input: &Input) {
+        if input.spend_type().is_taproot() || input.utxo().is_coinbase() {
+            return;
+        }
+        for signature in Self::candidates(input) {
+            match EcdsaSignature::parse(signature) {
+                Ok(signature) => self.record(&signature),
+                Err(_) => {
+                    self.signatures += 1;
+                    self.invalid += 1;
+                }
+            }
+        }
+    }
+
+    // the pushes and witness items besides the redeem and witness scripts. Spend inference only keeps the
+    // well formed signatures, the malformed ones have to be picked from the raw input to be counted
+    fn candidates(input: &Input) -> Vec<&[u8]> {
+        let info = input.spend_info();
+        let mut pushes = input
+            .signature()
+            .instructions()
+            .filter_map(|instruction| instruction.ok().and_then(|instruction| instruction.push_bytes()))
+            .collect::<Vec<_>>();
+        if info.redeem_script().is_some() {
+            pushes.pop();
+        }
+        let mut items = input
+            .witness()
+            .map(|witness| witness.iter().map(Vec::as_slice).collect::<Vec<_>>())
+            .unwrap_or_default();
+        if info.witness_script().is_some() {
+            items.pop();
+        }
+        pushes
+            .into_iter()
+            .chain(items)
+            .filter(|item| is_der_candidate(item))
+            .collect()
+    }
+
+    pub fn record_transaction(&mut self, transaction: &Transaction) {
+        transaction.inputs().iter().for_each(|input| self.record_input(input));
+    }
+
+    pub fn merge(&mut self, other: &SignatureStats) {
+        self.signatures += other.signatures;
+        self.invalid += other.invalid;
+        self.strict_der += other.strict_der;
+        self.low_s += other.low_s;
+        self.low_r += other.low_r;
+        self.canonical += other.canonical;
+        for (sighash, count) in &other.sighash_types {
+            *self.sighash_types.entry(sighash.clone()).or_default() += count;
+        }
+    }
+
+    pub fn signatures(&self) -> u64 {
+        self.signatures
+    }
+
+    pub fn invalid(&self) -> u64 {
+        self.invalid
+    }
+
+    pub fn strict_der(&self) -> u64 {
+        self.strict_der
+    }
+
+    pub fn low_s(&self) -> u64 {
+        self.low_s
+    }
+
+    pub fn low_r(&self) -> u64 {
+        self.low_r
+    }
+
+    pub fn canonical(&self) -> u64 {
+        self.canonical
+    }
+
+    pub fn sighash_types(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.sighash_types
+            .iter()
+            .map(|(sighash, &count)| (sighash.as_str(), count))
+    }
+
+    // without grinding about half of the signatures have a low R
+    pub fn low_r_ratio(&self) -> f64 {
+        let parsed = self.signatures - self.invalid;
+        if parsed == 0 {
+            0.0
+        } else {
+            self.low_r as f64 / parsed as f64
+        }
+    }
+}
+
+// a DER sequence whose length covers the item but its sighash byte is a candidate, the signature parser
+// tells the malformed and non strict ones apart
+pub fn is_der_candidate(item: &[u8]) -> bool {
+    match item {
+        [0x30, length, ..] => *length as usize + 3 == item.len(),
+        _ => false,
+    }
+}
+
+#[cfg(feature = "parser")]
+impl<B: TransactionBlock> ChainVisitor<B> for SignatureStats {
+    fn on_input(&mut self, _transaction: &B::Transaction, _vin: usize, input: &Input) {
+        self.record_input(input);
+    }
+}
+
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct BlockSignatureStats {
+    hash: BlockHash,
+    #[cfg_attr(feature = "writer", serde(flatten))]
+    stats: SignatureStats,
+}
+
+impl BlockSignatureStats {
+    pub fn new<'a>(hash: BlockHash, transactions: impl Iterator<Item = &'a Transaction>) -> Self {
+        let mut stats = SignatureStats::new();
+        transactions.for_each(|transaction| stats.record_transaction(transaction));
+        Self { hash, stats }
+    }
+
+    pub fn hash(&self) -> &BlockHash {
+        &self.hash
+    }
+
+    pub fn stats(&self) -> &SignatureStats {
+        &self.stats
+    }
+}
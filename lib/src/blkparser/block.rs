@@ -4,55 +4,124 @@ use byteorder::{LittleEndian, ReadBytesExt};
 use serde::{Deserialize, Serialize};
 
 use super::helpers::read_var_int;
-use super::transaction::SerialTransaction;
+use super::transaction::{decode_transaction, SerialTransaction};
 use crate::blockchain::block::Block;
+use crate::blockchain::block::BlockBuilder;
 use crate::blockchain::block::BlockHeader;
 use crate::blockchain::block::MiningInfo;
 use crate::cursor::Cursor;
 use crate::parser::{BlockError, BlockErrorKind};
 use crate::parser::{HeaderError, HeaderErrorKind};
 use crate::types::BitcoinHash as BHash;
+use crate::types::BlockHash;
 use crate::types::BlockTarget;
+use crate::BlockBuilder as BlockBuilderTrait;
 use crate::TransactionBlock;
 
 #[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
 pub struct SerialBlock {
     size: u32,
-    hash: BHash,
+    hash: BlockHash,
     #[cfg_attr(feature = "writer", serde(flatten))]
     contents: Block<SerialTransaction>,
 }
 
 impl SerialBlock {
-    pub fn from_raw_data(mut cursor: Cursor<'_>) -> Result<Self, BlockError> {
-        let size = cursor.size() as u32;
-        let raw_header = cursor
-            .bytes_to_cursor(80)
-            .map_err(|err| BlockError::new(BlockErrorKind::ReadError, Some(Box::new(err))))?;
-        let hash = BHash::hash_header(raw_header.get_ref());
-        let header = SerialHeader::build_header(raw_header)?;
-        let transactions = Self::read_transactions(cursor)?;
-
-        Ok(Self {
+    pub fn from_raw_data(cursor: Cursor<'_>) -> Result<Self, BlockError> {
+        decode_block(cursor)
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn hash(&self) -> &BlockHash {
+        &self.hash
+    }
+
+    pub fn block(&self) -> &Block<SerialTransaction> {
+        &self.contents
+    }
+
+    pub fn header(&self) -> &BlockHeader {
+        self.contents.header()
+    }
+
+    pub fn transactions(&self) -> &[SerialTransaction] {
+        self.contents.transactions()
+    }
+}
+
+impl TransactionBlock for SerialBlock {
+    type Transaction = SerialTransaction;
+    type Builder = SerialBlockBuilder;
+
+    fn hash(&self) -> BlockHash {
+        self.hash
+    }
+
+    fn previous(&self) -> BlockHash {
+        *self.header().previous()
+    }
+
+    fn time(&self) -> u32 {
+        self.header().time()
+    }
+
+    fn bits(&self) -> BlockTarget {
+        *self.header().bits()
+    }
+
+    fn transactions(&self) -> &[SerialTransaction] {
+        self.contents.transactions()
+    }
+}
+
+pub struct SerialBlockBuilder {
+    size: u32,
+    hash: BlockHash,
+    contents: BlockBuilder<SerialTransaction>,
+}
+
+impl BlockBuilderTrait for SerialBlockBuilder {
+    type Block = SerialBlock;
+
+    fn new(size: u32, hash: BlockHash, header: BlockHeader, transactions: u64) -> Self {
+        Self {
             size,
             hash,
-            contents: Block::new(header, transactions),
-        })
+            contents: BlockBuilder::new(size, hash, header, transactions),
+        }
+    }
+
+    fn transaction(&mut self, transaction: SerialTransaction) {
+        self.contents.transaction(transaction);
     }
 
-    fn read_transactions(mut cursor: Cursor<'_>) -> Result<Vec<SerialTransaction>, BlockError> {
-        let txcount =
-            read_var_int(&mut cursor).map_err(|err| BlockError::new(BlockErrorKind::ReadError, Some(Box::new(err))))?;
-        let mut transactions = vec![];
-        for _ in 0..*txcount {
-            transactions.push(SerialTransaction::from_raw_data(&mut cursor)?);
+    fn build(self) -> SerialBlock {
+        SerialBlock {
+            size: self.size,
+            hash: self.hash,
+            contents: self.contents.build(),
         }
-        Ok(transactions)
     }
 }
 
-impl TransactionBlock for SerialBlock {
-    type Transaction = SerialTransaction;
+// decodes a block without its blk file framing into any model
+pub fn decode_block<T: TransactionBlock>(mut cursor: Cursor<'_>) -> Result<T, BlockError> {
+    let size = cursor.size() as u32;
+    let raw_header = cursor
+        .bytes_to_cursor(80)
+        .map_err(|err| BlockError::new(BlockErrorKind::ReadError, Some(Box::new(err))))?;
+    let hash = BlockHash::hash(raw_header.get_ref());
+    let header = SerialHeader::build_header(raw_header)?;
+    let txcount =
+        read_var_int(&mut cursor).map_err(|err| BlockError::new(BlockErrorKind::ReadError, Some(Box::new(err))))?;
+    let mut builder = T::Builder::new(size, hash, header, *txcount);
+    for _ in 0..*txcount {
+        builder.transaction(decode_transaction(&mut cursor)?);
+    }
+    Ok(builder.build())
 }
 
 struct SerialHeader;
@@ -82,11 +151,11 @@ impl SerialHeader {
         ))
     }
 
-    fn build_hash(cursor: &mut Cursor<'_>) -> Result<BHash, HeaderError> {
+    fn build_hash(cursor: &mut Cursor<'_>) -> Result<BlockHash, HeaderError> {
         let data = cursor
             .read_bytes(32)
             .map_err(|err| HeaderError::new(HeaderErrorKind::HashError, err))?;
-        Ok(BHash::new(array_ref!(data, 0, 32).to_owned()))
+        Ok(BlockHash::new(array_ref!(data, 0, 32).to_owned()))
     }
 
     fn build_merkle_root(cursor: &mut Cursor<'_>) -> Result<BHash, HeaderError> {
@@ -1,27 +1,33 @@
+use arrayref::array_ref;
 use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
 use std::fs::File;
 use std::io;
-use std::io::Read;
-use std::path::Path;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 
+use crate::blockchain::index::ChainIndex;
 use crate::blockchain::BlockChain;
 #[cfg(feature = "parallel")]
 use crate::parser::ParallelParser;
 use crate::parser::Parser;
 use crate::parser::{ParseError, ParseErrorKind};
+use crate::types::{BlockHash, BlockTarget};
+use crate::visitor::ChainVisitor;
+use crate::TransactionBlock;
 
 mod block;
 mod helpers;
 mod transaction;
 
 use crate::cursor::Cursor;
-pub use block::SerialBlock;
-pub use transaction::SerialTransaction;
+pub use block::{decode_block, SerialBlock, SerialBlockBuilder};
+pub use transaction::{decode_transaction, SerialTransaction, SerialTransactionBuilder};
 
 pub const BLOCK_FILE_SIZE: u64 = 128 * 1024 * 1024;
 pub const MAGIC_BYTES: u32 = 0xf9beb4d9;
+const HEADER_SIZE: usize = 80;
 
 #[derive(Default)]
 pub struct BitcoinParser {
@@ -48,34 +54,190 @@ impl BitcoinParser {
         file.read_to_end(&mut self.blkbuffer)?;
         Ok(&self.blkbuffer)
     }
+
+    // drives the visitor over the best chain found in the files, a block at a time in height order. Files that
+    // do not start at the genesis block are placed by the block the visitor is anchored on
+    pub fn visit<B: TransactionBlock, V: ChainVisitor<B> + ?Sized>(
+        &mut self,
+        files: &[PathBuf],
+        visitor: &mut V,
+    ) -> Result<(), ParseError> {
+        let (locations, chain) = Self::read_chain(files)?;
+        let base = Self::base_height(&chain, &locations, visitor.anchor())?;
+        let mut reader = BlockReader::default();
+        visitor.start();
+        for (offset, &position) in chain.main().iter().enumerate() {
+            let location = &locations[position];
+            let data = reader.read(location, &files[location.file])?;
+            let block: B = decode_block(Cursor::new(data))?;
+            visitor.visit_block(base + offset as u32, &block);
+        }
+        visitor.finish();
+        Ok(())
+    }
+
+    // the height of a block of the best chain found in the files, which have to start at the genesis block
+    pub fn locate(&self, files: &[PathBuf], hash: &BlockHash) -> Result<Option<u32>, ParseError> {
+        let (locations, chain) = Self::read_chain(files)?;
+        let base = Self::base_height(&chain, &locations, None)?;
+        let offset = chain
+            .main()
+            .iter()
+            .position(|&position| locations[position].hash == *hash);
+        Ok(offset.map(|offset| base + offset as u32))
+    }
+
+    fn read_chain(files: &[PathBuf]) -> Result<(Vec<BlockLocation>, ChainIndex), ParseError> {
+        let mut locations = vec![];
+        for (index, file) in files.iter().enumerate() {
+            locations.extend(Self::read_headers(index, file)?);
+        }
+        let links = locations
+            .iter()
+            .map(|location| (location.hash, location.previous, location.bits))
+            .collect::<Vec<_>>();
+        let chain = ChainIndex::from_links(&links, 0);
+        Ok((locations, chain))
+    }
+
+    // the height of the first block of the best chain: zero for the genesis block, or the one that follows
+    // from the anchor when it is in the chain or right before it
+    fn base_height(
+        chain: &ChainIndex,
+        locations: &[BlockLocation],
+        anchor: Option<(u32, BlockHash)>,
+    ) -> Result<u32, ParseError> {
+        let first = match chain.main().first() {
+            Some(&position) => &locations[position],
+            None => return Ok(0),
+        };
+        if first.previous.is_zero() {
+            return Ok(0);
+        }
+        let base = anchor.and_then(|(height, hash)| {
+            match chain
+                .main()
+                .iter()
+                .position(|&position| locations[position].hash == hash)
+            {
+                Some(offset) => height.checked_sub(offset as u32),
+                None if first.previous == hash => height.checked_add(1),
+                None => None,
+            }
+        });
+        base.ok_or_else(|| {
+            let reason = "The files neither start at the genesis block nor reach the block the visitor is at";
+            ParseError::new(ParseErrorKind::HeightError, Some(reason.into()))
+        })
+    }
+
+    // reads only the framing and the header of each block to locate it and link it to the previous one
+    fn read_headers(index: usize, file: &Path) -> Result<Vec<BlockLocation>, ParseError> {
+        let mut reader = BufReader::new(File::open(file)?);
+        let mut blocks = vec![];
+        let mut position = 0;
+        let mut header = [0; HEADER_SIZE];
+        loop {
+            match reader.read_u32::<BigEndian>() {
+                Ok(delimiter) if delimiter == MAGIC_BYTES => {}
+                // the reference client preallocates the files, leaving zeros after the last block
+                Ok(0) => break,
+                Ok(delimiter) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "Expected to find the magic bytes: {} but found instead {}",
+                            MAGIC_BYTES, delimiter
+                        ),
+                    )
+                    .into())
+                }
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err.into()),
+            }
+            let size = reader.read_u32::<LittleEndian>()? as usize;
+            if size < HEADER_SIZE {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "The block is smaller than its header").into());
+            }
+            reader.read_exact(&mut header)?;
+            let previous = BlockHash::new(*array_ref!(header, 4, 32));
+            let bits = BlockTarget::new(*array_ref!(header, 72, 4));
+            blocks.push(BlockLocation {
+                file: index,
+                start: position + 8,
+                size,
+                hash: BlockHash::hash(&header),
+                previous,
+                bits,
+            });
+            reader.seek(SeekFrom::Current((size - HEADER_SIZE) as i64))?;
+            position += 8 + size;
+        }
+        Ok(blocks)
+    }
+}
+
+struct BlockLocation {
+    file: usize,
+    start: usize,
+    size: usize,
+    hash: BlockHash,
+    previous: BlockHash,
+    bits: BlockTarget,
+}
+
+// reads the blocks one at a time from where their headers were found, keeping the last file open as the
+// blocks that follow each other in the chain are mostly stored close together
+#[derive(Default)]
+struct BlockReader {
+    file: Option<(usize, File)>,
+    buffer: Vec<u8>,
+}
+
+impl BlockReader {
+    fn read(&mut self, location: &BlockLocation, path: &Path) -> io::Result<&[u8]> {
+        let file = match self.file {
+            Some((index, ref mut file)) if index == location.file => file,
+            _ => &mut self.file.insert((location.file, File::open(path)?)).1,
+        };
+        file.seek(SeekFrom::Start(location.start as u64))?;
+        self.buffer.resize(location.size, 0);
+        file.read_exact(&mut self.buffer).map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "The block goes past the end of the file")
+            }
+            _ => err,
+        })?;
+        Ok(&self.buffer)
+    }
 }
 
-impl Parser<SerialBlock> for BitcoinParser {
-    fn parse<P: AsRef<Path>>(&mut self, file: P) -> Result<BlockChain<SerialBlock>, ParseError> {
+impl<T: TransactionBlock> Parser<T> for BitcoinParser {
+    fn parse<P: AsRef<Path>>(&mut self, file: P) -> Result<BlockChain<T>, ParseError> {
         let buffer = self.read_file_contents(file)?;
-        Ok(Self::read_raw_blocks(&buffer)
+        Self::read_raw_blocks(buffer)
             .map(|block| {
                 block
                     .map_err(|err| ParseError::new(ParseErrorKind::ReadError, Some(Box::new(err))))
-                    .and_then(|data| SerialBlock::from_raw_data(data).map_err(From::from))
+                    .and_then(|data| decode_block(data).map_err(From::from))
             })
-            .collect::<Result<BlockChain<SerialBlock>, _>>()?)
+            .collect::<Result<BlockChain<T>, _>>()
     }
 }
 
 #[cfg(feature = "parallel")]
-impl ParallelParser<SerialBlock> for BitcoinParser {
-    fn parse<P: AsRef<Path>>(&mut self, file: P) -> Result<BlockChain<SerialBlock>, ParseError> {
+impl<T: TransactionBlock + Send> ParallelParser<T> for BitcoinParser {
+    fn parse<P: AsRef<Path>>(&mut self, file: P) -> Result<BlockChain<T>, ParseError> {
         let buffer = self.read_file_contents(file)?;
-        Ok(Self::read_raw_blocks(&buffer)
+        Ok(Self::read_raw_blocks(buffer)
             .par_bridge()
             .into_par_iter()
             .map(|block| {
                 block
                     .map_err(|err| ParseError::new(ParseErrorKind::ReadError, Some(Box::new(err))))
-                    .and_then(|data| SerialBlock::from_raw_data(data).map_err(From::from))
+                    .and_then(|data| decode_block(data).map_err(From::from))
             })
-            .collect::<Result<Vec<SerialBlock>, _>>()?
+            .collect::<Result<Vec<T>, _>>()?
             .into())
     }
 }
@@ -5,114 +5,158 @@ use byteorder::LittleEndian;
 use serde::{Deserialize, Serialize};
 
 use super::helpers::{read_var_int, read_var_int_marker};
-use crate::blockchain::script::BitcoinScript as BScript;
-use crate::blockchain::transactions::Input;
-use crate::blockchain::transactions::Output;
-use crate::blockchain::transactions::Transaction;
-use crate::blockchain::transactions::Utxo;
-use crate::blockchain::transactions::Witness;
+use crate::amount::Amount;
+use crate::blockchain::transactions::TransactionBuilder;
+use crate::blockchain::transactions::{Input, Output, Transaction};
 use crate::cursor::Cursor;
 use crate::parser::{TransactionError as TxError, TransactionErrorKind as TxErrorKind};
-use crate::types::BitcoinHash as BHash;
 use crate::types::BitcoinHashBuilder as BHashBuilder;
+use crate::types::Txid;
 use crate::types::VarInt;
 use crate::Transaction as TransactionTrait;
+use crate::TransactionBuilder as TransactionBuilderTrait;
 
 #[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
 pub struct SerialTransaction {
     txin: VarInt,
     txout: VarInt,
-    hash: BHash,
+    hash: Txid,
     #[cfg_attr(feature = "writer", serde(flatten))]
     contents: Transaction,
 }
 
 impl SerialTransaction {
-    pub fn from_raw_data<'a>(cursor: &mut Cursor<'a>) -> Result<Self, TxError> {
-        let mut txparser = TxParser::new(cursor);
-        let segwit;
-        let version = txparser.parse_version()?;
-        let marker = txparser.parse_bytes_hash(1, |bytes| bytes[0] != 0x00)?[0];
-        segwit = marker == 0x00;
-        let (txin, mut inputs, txout, outputs);
-        if segwit {
-            let flag = txparser.parse_bytes(1)?[0];
-            if flag != 0x01 {
-                return Err(TxError::new(TxErrorKind::FlagError(flag as u64), None));
-            }
-            txin = txparser.parse_var_int_hash()?;
-        } else {
-            txin = txparser.parse_var_int_marker(marker)?;
-        }
-        inputs = Self::read_inputs(*txin, &mut txparser)?;
-        txout = txparser.parse_var_int_hash()?;
-        outputs = Self::read_outputs(*txout, &mut txparser)?;
-        if segwit {
-            Self::read_witnesses(&mut inputs, &mut txparser)?;
-        }
-        let locktime = txparser.parse_locktime()?;
-        Ok(Self {
-            txin,
-            txout,
-            hash: txparser.generate_txhash(),
-            contents: Transaction::new(version, inputs, outputs, locktime),
-        })
-    }
-
-    fn read_inputs(amount: u64, parser: &mut TxParser<'_, '_>) -> Result<Vec<Input>, TxError> {
-        let mut inputs = vec![];
-        for _ in 0..amount {
-            let txid = parser.parse_txid()?;
-            let vout = parser.parse_vout()?;
-            let (_scriptsize, signature) = parser.parse_script()?;
-            let sequence = parser.parse_sequence()?;
-
-            let utxo = Utxo::new(txid, vout);
-            let script = BScript::new(signature);
-            let input = Input::new(utxo, script, sequence, None);
-            inputs.push(input);
-        }
-        Ok(inputs)
+    pub fn from_raw_data(cursor: &mut Cursor<'_>) -> Result<Self, TxError> {
+        decode_transaction(cursor)
+    }
+
+    pub fn hash(&self) -> &Txid {
+        &self.hash
+    }
+
+    pub fn input_count(&self) -> u64 {
+        *self.txin
+    }
+
+    pub fn output_count(&self) -> u64 {
+        *self.txout
+    }
+
+    pub fn transaction(&self) -> &Transaction {
+        &self.contents
+    }
+}
+
+impl TransactionTrait for SerialTransaction {
+    type Builder = SerialTransactionBuilder;
+
+    fn txid(&self) -> Txid {
+        self.hash
+    }
+
+    fn inputs(&self) -> &[Input] {
+        self.contents.inputs()
+    }
+
+    fn outputs(&self) -> &[Output] {
+        self.contents.outputs()
     }
+}
+
+pub struct SerialTransactionBuilder {
+    txin: VarInt,
+    txout: VarInt,
+    contents: TransactionBuilder,
+}
+
+impl TransactionBuilderTrait for SerialTransactionBuilder {
+    type Transaction = SerialTransaction;
 
-    fn read_outputs(amount: u64, parser: &mut TxParser<'_, '_>) -> Result<Vec<Output>, TxError> {
-        let mut outputs = vec![];
-        for _ in 0..amount {
-            let value = parser.parse_out_value()?;
-            let (_scriptsize, pubkey) = parser.parse_script()?;
-            let output = Output::new(value, BScript::new(pubkey));
-            outputs.push(output);
+    fn new(version: u32, segwit: bool) -> Self {
+        Self {
+            txin: VarInt::new(0),
+            txout: VarInt::new(0),
+            contents: TransactionBuilder::new(version, segwit),
         }
-        Ok(outputs)
     }
 
-    fn read_witnesses(inputs: &mut [Input], parser: &mut TxParser<'_, '_>) -> Result<(), TxError> {
-        for input in inputs {
-            let witness = Self::read_witness(parser)?;
-            if let Some(witness) = witness {
-                input.assign_witness(witness);
-            }
+    fn inputs(&mut self, count: u64) {
+        self.txin = VarInt::new(count);
+    }
+
+    fn input(&mut self, txid: Txid, vout: u32, script: &[u8], sequence: u32) {
+        self.contents.input(txid, vout, script, sequence);
+    }
+
+    fn outputs(&mut self, count: u64) {
+        self.txout = VarInt::new(count);
+    }
+
+    fn output(&mut self, value: Amount, script: &[u8]) {
+        self.contents.output(value, script);
+    }
+
+    fn witness(&mut self, input: usize, items: &[&[u8]]) {
+        self.contents.witness(input, items);
+    }
+
+    fn build(self, txid: Txid, locktime: u32) -> SerialTransaction {
+        SerialTransaction {
+            txin: self.txin,
+            txout: self.txout,
+            hash: txid,
+            contents: self.contents.build(txid, locktime),
         }
-        Ok(())
     }
+}
 
-    fn read_witness(parser: &mut TxParser<'_, '_>) -> Result<Option<Witness>, TxError> {
-        let witness_stack_size = parser.parse_var_int()?;
-        if *witness_stack_size == 0x00 {
-            return Ok(None);
+// decodes a transaction into any model, the txid is computed on the way over the non witness fields
+pub fn decode_transaction<T: TransactionTrait>(cursor: &mut Cursor<'_>) -> Result<T, TxError> {
+    let mut txparser = TxParser::new(cursor);
+    let version = txparser.parse_version()?;
+    let marker = txparser.parse_bytes_hash(1, |bytes| bytes[0] != 0x00)?[0];
+    let segwit = marker == 0x00;
+    let txin = if segwit {
+        let flag = txparser.parse_bytes(1)?[0];
+        if flag != 0x01 {
+            return Err(TxError::new(TxErrorKind::FlagError(flag as u64), None));
         }
+        txparser.parse_var_int_hash()?
+    } else {
+        txparser.parse_var_int_marker(marker)?
+    };
+    let mut builder = T::Builder::new(version, segwit);
+    builder.inputs(*txin);
+    for _ in 0..*txin {
+        let txid = txparser.parse_txid()?;
+        let vout = txparser.parse_vout()?;
+        let signature = txparser.parse_script()?;
+        let sequence = txparser.parse_sequence()?;
+        builder.input(txid, vout, signature, sequence);
+    }
+    let txout = txparser.parse_var_int_hash()?;
+    builder.outputs(*txout);
+    for _ in 0..*txout {
+        let value = txparser.parse_out_value()?;
+        let pubkey = txparser.parse_script()?;
+        builder.output(value, pubkey);
+    }
+    if segwit {
         let mut items = vec![];
-        for __ in 0..*witness_stack_size {
-            let item_length = parser.parse_var_int()?;
-            let item = parser.parse_bytes(*item_length as usize)?.to_owned();
-            items.push(item)
+        for input in 0..*txin as usize {
+            items.clear();
+            let witness_stack_size = txparser.parse_var_int()?;
+            for _ in 0..*witness_stack_size {
+                let item_length = txparser.parse_var_int()?;
+                items.push(txparser.parse_bytes(*item_length as usize)?);
+            }
+            builder.witness(input, &items);
         }
-        Ok(Some(Witness::new(items)))
     }
+    let locktime = txparser.parse_locktime()?;
+    Ok(builder.build(txparser.generate_txhash(), locktime))
 }
 
-impl TransactionTrait for SerialTransaction {}
-
 struct TxParser<'a, 'b: 'a> {
     hasher: BHashBuilder,
     cursor: &'a mut Cursor<'b>,
@@ -126,8 +170,8 @@ impl<'a, 'b: 'a> TxParser<'a, 'b> {
         }
     }
 
-    fn generate_txhash(self) -> BHash {
-        self.hasher.into_hash()
+    fn generate_txhash(self) -> Txid {
+        Txid::from_raw_hash(self.hasher.into_hash())
     }
 
     fn parse_bytes(&mut self, bytes: usize) -> Result<&'b [u8], TxError> {
@@ -148,7 +192,7 @@ impl<'a, 'b: 'a> TxParser<'a, 'b> {
     }
 
     fn parse_var_int(&mut self) -> Result<VarInt, TxError> {
-        Ok(read_var_int(self.cursor).map_err(|err| TxError::new(TxErrorKind::ReadError, Some(Box::new(err))))?)
+        read_var_int(self.cursor).map_err(|err| TxError::new(TxErrorKind::ReadError, Some(Box::new(err))))
     }
 
     fn parse_var_int_hash(&mut self) -> Result<VarInt, TxError> {
@@ -159,9 +203,14 @@ impl<'a, 'b: 'a> TxParser<'a, 'b> {
         Ok(bytes)
     }
 
+    // the marker is already hashed, the bytes that follow it for the larger numbers are not
     fn parse_var_int_marker(&mut self, marker: u8) -> Result<VarInt, TxError> {
-        Ok(read_var_int_marker(marker, self.cursor)
-            .map_err(|err| TxError::new(TxErrorKind::ReadError, Some(Box::new(err))))?)
+        let position = self.cursor.position();
+        let bytes = read_var_int_marker(marker, self.cursor)
+            .map_err(|err| TxError::new(TxErrorKind::ReadError, Some(Box::new(err))))?;
+        self.hasher
+            .add_digest(&self.cursor.get_ref()[position..self.cursor.position()]);
+        Ok(bytes)
     }
 
     fn parse_version(&mut self) -> Result<u32, TxError> {
@@ -170,8 +219,8 @@ impl<'a, 'b: 'a> TxParser<'a, 'b> {
         Ok(LittleEndian::read_u32(bytes))
     }
 
-    fn parse_txid(&mut self) -> Result<BHash, TxError> {
-        Ok(BHash::new(
+    fn parse_txid(&mut self) -> Result<Txid, TxError> {
+        Ok(Txid::new(
             array_ref!(self.parse_bytes_hash(32, |_| true)?, 0, 32).to_owned(),
         ))
     }
@@ -182,10 +231,9 @@ impl<'a, 'b: 'a> TxParser<'a, 'b> {
         Ok(LittleEndian::read_u32(bytes))
     }
 
-    fn parse_script(&mut self) -> Result<(VarInt, Vec<u8>), TxError> {
+    fn parse_script(&mut self) -> Result<&'b [u8], TxError> {
         let size = self.parse_var_int_hash()?;
-        let script = self.parse_bytes_hash(*size as usize, |_| true)?.to_owned();
-        Ok((size, script))
+        self.parse_bytes_hash(*size as usize, |_| true)
     }
 
     fn parse_sequence(&mut self) -> Result<u32, TxError> {
@@ -194,10 +242,10 @@ impl<'a, 'b: 'a> TxParser<'a, 'b> {
         Ok(LittleEndian::read_u32(bytes))
     }
 
-    fn parse_out_value(&mut self) -> Result<u64, TxError> {
+    fn parse_out_value(&mut self) -> Result<Amount, TxError> {
         let bytes = self.parse_bytes(8)?;
         self.hasher.add_digest(bytes);
-        Ok(LittleEndian::read_u64(bytes))
+        Ok(Amount::from_sat(LittleEndian::read_u64(bytes)))
     }
 
     fn parse_locktime(&mut self) -> Result<u32, TxError> {
@@ -206,3 +254,36 @@ impl<'a, 'b: 'a> TxParser<'a, 'b> {
         Ok(LittleEndian::read_u32(bytes))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_transaction, SerialTransaction};
+    use crate::amount::Amount;
+    use crate::blockchain::script::BitcoinScript;
+    use crate::blockchain::transactions::{Input, Output, Transaction, Utxo};
+    use crate::cursor::Cursor;
+    use crate::encode::Encodable;
+    use crate::types::Txid;
+
+    // 253 inputs take the three byte form of the count, the first byte of which doubles as the segwit marker
+    #[test]
+    fn txid_hashes_the_whole_input_count() {
+        let inputs = (0..253u32)
+            .map(|vout| {
+                Input::new(
+                    Utxo::new(Txid::new([1; 32]), vout),
+                    BitcoinScript::new(vec![0x51]),
+                    0,
+                    None,
+                )
+            })
+            .collect();
+        let outputs = vec![Output::new(Amount::from_sat(1000), BitcoinScript::new(vec![0x51]))];
+        let transaction = Transaction::new(1, inputs, outputs, 0);
+        let data = transaction.encoded();
+        assert_eq!(data[4..7], [0xfd, 0xfd, 0x00]);
+        let decoded: SerialTransaction = decode_transaction(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(*decoded.hash(), transaction.compute_txid());
+        assert_eq!(decoded.transaction().encoded(), data);
+    }
+}
@@ -3,7 +3,9 @@ use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "writer")]
 use super::serde_custom;
-use crate::types::{BitcoinHash as BHash, BlockTarget};
+use crate::encode::Encodable;
+use crate::types::{BitcoinHash as BHash, BlockHash, BlockTarget};
+use crate::BlockBuilder as BlockBuilderTrait;
 use crate::Transaction as TransactionTrait;
 use crate::TransactionBlock;
 
@@ -18,24 +20,78 @@ impl<T: TransactionTrait> Block<T> {
     pub(crate) fn new(header: BlockHeader, transactions: Vec<T>) -> Self {
         Self { header, transactions }
     }
+
+    pub fn header(&self) -> &BlockHeader {
+        &self.header
+    }
+
+    pub fn transactions(&self) -> &[T] {
+        &self.transactions
+    }
 }
 
+// the hash is not stored, so it is computed from the header on each call
 impl<T: TransactionTrait> TransactionBlock for Block<T> {
     type Transaction = T;
+    type Builder = BlockBuilder<T>;
+
+    fn hash(&self) -> BlockHash {
+        self.header.compute_hash()
+    }
+
+    fn previous(&self) -> BlockHash {
+        *self.header.previous()
+    }
+
+    fn time(&self) -> u32 {
+        self.header.time()
+    }
+
+    fn bits(&self) -> BlockTarget {
+        *self.header.bits()
+    }
+
+    fn transactions(&self) -> &[T] {
+        &self.transactions
+    }
+}
+
+pub struct BlockBuilder<T: TransactionTrait> {
+    header: BlockHeader,
+    transactions: Vec<T>,
+}
+
+impl<T: TransactionTrait> BlockBuilderTrait for BlockBuilder<T> {
+    type Block = Block<T>;
+
+    fn new(_size: u32, _hash: BlockHash, header: BlockHeader, _transactions: u64) -> Self {
+        Self {
+            header,
+            transactions: vec![],
+        }
+    }
+
+    fn transaction(&mut self, transaction: T) {
+        self.transactions.push(transaction);
+    }
+
+    fn build(self) -> Block<T> {
+        Block::new(self.header, self.transactions)
+    }
 }
 
 #[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
-pub(crate) struct BlockHeader {
+pub struct BlockHeader {
     #[cfg_attr(feature = "writer", serde(with = "serde_custom::version"))]
     version: u32,
-    previous: BHash,
+    previous: BlockHash,
     txroot: BHash,
     #[cfg_attr(feature = "writer", serde(flatten))]
     consensus: MiningInfo,
 }
 
 impl BlockHeader {
-    pub(crate) fn new(version: u32, previous: BHash, txroot: BHash, consensus: MiningInfo) -> Self {
+    pub(crate) fn new(version: u32, previous: BlockHash, txroot: BHash, consensus: MiningInfo) -> Self {
         Self {
             version,
             previous,
@@ -43,10 +99,42 @@ impl BlockHeader {
             consensus,
         }
     }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn previous(&self) -> &BlockHash {
+        &self.previous
+    }
+
+    pub fn txroot(&self) -> &BHash {
+        &self.txroot
+    }
+
+    pub fn mining_info(&self) -> &MiningInfo {
+        &self.consensus
+    }
+
+    pub fn time(&self) -> u32 {
+        self.consensus.time
+    }
+
+    pub fn bits(&self) -> &BlockTarget {
+        &self.consensus.bits
+    }
+
+    pub fn nonce(&self) -> u32 {
+        self.consensus.nonce
+    }
+
+    pub fn compute_hash(&self) -> BlockHash {
+        BlockHash::hash(&self.encoded())
+    }
 }
 
 #[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
-pub(crate) struct MiningInfo {
+pub struct MiningInfo {
     time: u32,
     bits: BlockTarget,
     nonce: u32,
@@ -56,4 +144,16 @@ impl MiningInfo {
     pub(crate) fn new(time: u32, bits: BlockTarget, nonce: u32) -> Self {
         Self { time, bits, nonce }
     }
+
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+
+    pub fn bits(&self) -> &BlockTarget {
+        &self.bits
+    }
+
+    pub fn nonce(&self) -> u32 {
+        self.nonce
+    }
 }
@@ -0,0 +1,307 @@
+#[cfg(feature = "writer")]
+use serde::{Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::script::opcodes::{OP_CHECKSIG, OP_CHECKSIGADD, OP_NUMEQUAL};
+use super::script::{decode_script_num, decode_small_int, BitcoinScript, Instruction, StandardScript};
+use super::spend::SpendType;
+use super::taproot::{TaprootSpend, TAPROOT_LEAF_TAPSCRIPT};
+use super::transactions::{Input, Output, Transaction};
+use crate::address::Address;
+use crate::network::Network;
+use crate::types::Txid;
+
+const INPUT_CHARSET: &[u8] =
+    b"0123456789()[],'/*abcdefgh@:$%{}IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+const CHECKSUM_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const CHECKSUM_GENERATOR: [u64; 5] = [0xf5dee51989, 0xa9fdca3312, 0x1bab10e32d, 0x3706b1677a, 0x644d626ffd];
+
+const MAX_BARE_MULTISIG_KEYS: usize = 3;
+const MAX_P2SH_MULTISIG_KEYS: usize = 15;
+const MAX_WITNESS_MULTISIG_KEYS: usize = 20;
+const MAX_TAPSCRIPT_MULTISIG_KEYS: usize = 999;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Context {
+    Top,
+    P2sh,
+    P2wsh,
+    Tapscript,
+}
+
+impl Context {
+    fn accepts_key(self, key: &[u8]) -> bool {
+        match (self, key.first()) {
+            (Context::Tapscript, _) => key.len() == 32,
+            (_, Some(0x02)) | (_, Some(0x03)) => key.len() == 33,
+            (Context::Top, Some(0x04)) | (Context::P2sh, Some(0x04)) => key.len() == 65,
+            _ => false,
+        }
+    }
+
+    fn max_multisig_keys(self) -> usize {
+        match self {
+            Context::Top => MAX_BARE_MULTISIG_KEYS,
+            Context::P2sh => MAX_P2SH_MULTISIG_KEYS,
+            Context::P2wsh => MAX_WITNESS_MULTISIG_KEYS,
+            Context::Tapscript => MAX_TAPSCRIPT_MULTISIG_KEYS,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Descriptor {
+    Pk(Vec<u8>),
+    Pkh(Vec<u8>),
+    Wpkh(Vec<u8>),
+    Sh(Box<Descriptor>),
+    Wsh(Box<Descriptor>),
+    Multi {
+        required: u8,
+        keys: Vec<Vec<u8>>,
+    },
+    MultiA {
+        required: u32,
+        keys: Vec<Vec<u8>>,
+    },
+    Tr {
+        internal_key: [u8; 32],
+        leaf: Option<Box<Descriptor>>,
+    },
+    RawTr([u8; 32]),
+    Addr(Address),
+    Raw(Vec<u8>),
+}
+
+impl Descriptor {
+    // an output script alone only reveals the keys of p2pk, bare multisig and taproot outputs
+    pub fn from_script(script: &BitcoinScript, network: Network) -> Self {
+        let standard = script.classify();
+        if let StandardScript::P2tr(key) = standard {
+            return Descriptor::RawTr(key);
+        }
+        if let Some(descriptor) = Self::infer_inner(&standard, Context::Top) {
+            return descriptor;
+        }
+        match Address::from_script(&standard, network) {
+            Some(address) => Descriptor::Addr(address),
+            None => Descriptor::Raw(script.as_bytes().to_owned()),
+        }
+    }
+
+    pub fn from_output(output: &Output, network: Network) -> Self {
+        Self::from_script(output.pubkey(), network)
+    }
+
+    // spends reveal the keys and scripts committed to by the output being spent
+    pub fn from_input(input: &Input) -> Option<Self> {
+        let info = input.spend_info();
+        let key = info.pubkeys().first();
+        match info.kind() {
+            SpendType::P2pkh => key
+                .filter(|key| Context::Top.accepts_key(key))
+                .map(|key| Descriptor::Pkh(key.clone())),
+            SpendType::P2wpkh => key
+                .filter(|key| Context::P2wsh.accepts_key(key))
+                .map(|key| Descriptor::Wpkh(key.clone())),
+            SpendType::P2shP2wpkh => key
+                .filter(|key| Context::P2wsh.accepts_key(key))
+                .map(|key| Descriptor::Sh(Box::new(Descriptor::Wpkh(key.clone())))),
+            SpendType::P2sh => Self::infer_inner(&info.redeem_script()?.classify(), Context::P2sh)
+                .map(|inner| Descriptor::Sh(Box::new(inner))),
+            SpendType::P2wsh => Self::infer_inner(&info.witness_script()?.classify(), Context::P2wsh)
+                .map(|inner| Descriptor::Wsh(Box::new(inner))),
+            SpendType::P2shP2wsh => Self::infer_inner(&info.witness_script()?.classify(), Context::P2wsh)
+                .map(|inner| Descriptor::Sh(Box::new(Descriptor::Wsh(Box::new(inner))))),
+            SpendType::P2trScriptPath => Self::infer_taproot(&input.witness()?.taproot().ok()?),
+            _ => None,
+        }
+    }
+
+    fn infer_inner(script: &StandardScript, context: Context) -> Option<Self> {
+        match *script {
+            StandardScript::P2pk(ref key) if context.accepts_key(key) => Some(Descriptor::Pk(key.clone())),
+            StandardScript::Multisig { required, ref keys }
+                if keys.len() <= context.max_multisig_keys() && keys.iter().all(|key| context.accepts_key(key)) =>
+            {
+                Some(Descriptor::Multi {
+                    required,
+                    keys: keys.clone(),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // the whole tree is only known when the spent leaf is the single leaf of the tree
+    fn infer_taproot(spend: &TaprootSpend<'_>) -> Option<Self> {
+        let control_block = spend.control_block()?;
+        if !control_block.merkle_path().is_empty() || control_block.leaf_version() != TAPROOT_LEAF_TAPSCRIPT {
+            return None;
+        }
+        let leaf = Self::infer_tapscript(&BitcoinScript::new(spend.script()?.to_owned()))?;
+        Some(Descriptor::Tr {
+            internal_key: *control_block.internal_key(),
+            leaf: Some(Box::new(leaf)),
+        })
+    }
+
+    fn infer_tapscript(script: &BitcoinScript) -> Option<Self> {
+        let instructions = script.instructions().collect::<Result<Vec<_>, _>>().ok()?;
+        match *instructions.as_slice() {
+            [Instruction::PushBytes(key), Instruction::Op(OP_CHECKSIG)] if Context::Tapscript.accepts_key(key) => {
+                Some(Descriptor::Pk(key.to_owned()))
+            }
+            [Instruction::PushBytes(first), Instruction::Op(OP_CHECKSIG), ref rest @ .., required, Instruction::Op(OP_NUMEQUAL)] =>
+            {
+                let mut keys = vec![first.to_owned()];
+                for pair in rest.chunks(2) {
+                    match *pair {
+                        [Instruction::PushBytes(key), Instruction::Op(OP_CHECKSIGADD)] => keys.push(key.to_owned()),
+                        _ => return None,
+                    }
+                }
+                let required = match required {
+                    Instruction::Op(opcode) => decode_small_int(opcode).map(u32::from),
+                    Instruction::PushBytes(bytes) => {
+                        decode_script_num(bytes).and_then(|number| u32::try_from(number).ok())
+                    }
+                }?;
+                let valid = required >= 1
+                    && required as usize <= keys.len()
+                    && keys.len() <= Context::Tapscript.max_multisig_keys()
+                    && keys.iter().all(|key| Context::Tapscript.accepts_key(key));
+                if valid {
+                    Some(Descriptor::MultiA { required, keys })
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_string_without_checksum(&self) -> String {
+        match *self {
+            Descriptor::Pk(ref key) => format!("pk({})", hex::encode(key)),
+            Descriptor::Pkh(ref key) => format!("pkh({})", hex::encode(key)),
+            Descriptor::Wpkh(ref key) => format!("wpkh({})", hex::encode(key)),
+            Descriptor::Sh(ref inner) => format!("sh({})", inner.to_string_without_checksum()),
+            Descriptor::Wsh(ref inner) => format!("wsh({})", inner.to_string_without_checksum()),
+            Descriptor::Multi { required, ref keys } => format!("multi({},{})", required, join_keys(keys)),
+            Descriptor::MultiA { required, ref keys } => format!("multi_a({},{})", required, join_keys(keys)),
+            Descriptor::Tr {
+                ref internal_key,
+                ref leaf,
+            } => match *leaf {
+                Some(ref leaf) => format!(
+                    "tr({},{})",
+                    hex::encode(internal_key),
+                    leaf.to_string_without_checksum()
+                ),
+                None => format!("tr({})", hex::encode(internal_key)),
+            },
+            Descriptor::RawTr(ref key) => format!("rawtr({})", hex::encode(key)),
+            Descriptor::Addr(ref address) => format!("addr({})", address),
+            Descriptor::Raw(ref script) => format!("raw({})", hex::encode(script)),
+        }
+    }
+}
+
+impl fmt::Display for Descriptor {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let descriptor = self.to_string_without_checksum();
+        let checksum = checksum(&descriptor).expect("inferred descriptors only use the input charset");
+        write!(formatter, "{}#{}", descriptor, checksum)
+    }
+}
+
+#[cfg(feature = "writer")]
+impl Serialize for Descriptor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg_attr(feature = "writer", derive(Serialize))]
+pub struct TransactionDescriptors {
+    txid: Txid,
+    inputs: Vec<Option<Descriptor>>,
+    outputs: Vec<Descriptor>,
+}
+
+impl TransactionDescriptors {
+    pub fn new(txid: Txid, transaction: &Transaction, network: Network) -> Self {
+        Self {
+            txid,
+            inputs: transaction.inputs().iter().map(Descriptor::from_input).collect(),
+            outputs: transaction
+                .outputs()
+                .iter()
+                .map(|output| Descriptor::from_output(output, network))
+                .collect(),
+        }
+    }
+
+    pub fn txid(&self) -> &Txid {
+        &self.txid
+    }
+
+    pub fn inputs(&self) -> &[Option<Descriptor>] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[Descriptor] {
+        &self.outputs
+    }
+}
+
+fn join_keys(keys: &[Vec<u8>]) -> String {
+    keys.iter().map(hex::encode).collect::<Vec<_>>().join(",")
+}
+
+fn polymod(symbols: impl Iterator<Item = u64>) -> u64 {
+    symbols.fold(1, |checksum, value| {
+        let top = checksum >> 35;
+        let checksum = (checksum & 0x7_ffff_ffff) << 5 ^ value;
+        CHECKSUM_GENERATOR
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| (top >> index) & 1 == 1)
+            .fold(checksum, |checksum, (_, generator)| checksum ^ generator)
+    })
+}
+
+// the checksum of BIP380, None when the descriptor has characters outside the input charset
+pub fn checksum(descriptor: &str) -> Option<String> {
+    let mut symbols = vec![];
+    let mut groups = vec![];
+    for character in descriptor.bytes() {
+        let position = INPUT_CHARSET.iter().position(|&symbol| symbol == character)? as u64;
+        symbols.push(position & 31);
+        groups.push(position >> 5);
+        if groups.len() == 3 {
+            symbols.push(groups[0] * 9 + groups[1] * 3 + groups[2]);
+            groups.clear();
+        }
+    }
+    match *groups.as_slice() {
+        [first] => symbols.push(first),
+        [first, second] => symbols.push(first * 3 + second),
+        _ => (),
+    }
+    let checksum = polymod(symbols.into_iter().chain(std::iter::repeat_n(0, 8))) ^ 1;
+    Some(
+        (0..8)
+            .map(|index| CHECKSUM_CHARSET[((checksum >> (5 * (7 - index))) & 31) as usize] as char)
+            .collect(),
+    )
+}
+
+pub fn verify_checksum(descriptor: &str) -> bool {
+    match descriptor.rsplit_once('#') {
+        Some((body, expected)) => checksum(body).map(|actual| actual == expected).unwrap_or(false),
+        None => false,
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use crate::types::{BlockHash, BlockTarget, Txid};
+use crate::Transaction as TransactionTrait;
+use crate::TransactionBlock;
+
+// positions of the blocks of a chain, built once from the links between them
+pub(crate) struct ChainIndex {
+    positions: HashMap<BlockHash, usize>,
+    heights: Vec<u32>,
+    // the blocks of the best chain, ordered by height starting at base
+    main: Vec<usize>,
+    base: u32,
+}
+
+impl ChainIndex {
+    pub(crate) fn build<T: TransactionBlock>(blocks: &[T], base: u32) -> Self {
+        let links = blocks
+            .iter()
+            .map(|block| (block.hash(), block.previous(), block.bits()))
+            .collect::<Vec<_>>();
+        Self::from_links(&links, base)
+    }
+
+    // takes the hash, previous hash and target of each block, blocks whose parent is not there start at the
+    // base height, or at zero when they are the genesis block
+    pub(crate) fn from_links(links: &[(BlockHash, BlockHash, BlockTarget)], base: u32) -> Self {
+        let positions = links
+            .iter()
+            .enumerate()
+            .map(|(position, &(hash, _, _))| (hash, position))
+            .collect::<HashMap<_, _>>();
+        let parents = links
+            .iter()
+            .map(|(_, previous, _)| positions.get(previous).copied())
+            .collect::<Vec<_>>();
+        let mut heights = vec![None; links.len()];
+        // the work of the blocks up to each one, counted from the first block loaded of its branch
+        let mut chainwork = vec![0u128; links.len()];
+        for start in 0..links.len() {
+            // walks back up to a block whose height is known, then assigns the heights on the way down
+            let mut path = vec![];
+            let mut position = start;
+            let mut height = loop {
+                if let Some(height) = heights[position] {
+                    break height;
+                }
+                path.push(position);
+                match parents[position] {
+                    // a longer walk than blocks loaded can only be a cycle of hashes
+                    Some(_) if path.len() > links.len() => break base,
+                    Some(parent) => position = parent,
+                    None if links[position].1.is_zero() => break 0,
+                    None => break base,
+                }
+            };
+            let mut work = chainwork[position];
+            if heights[position].is_none() {
+                let root = path.pop().expect("the walk visits at least one block");
+                heights[root] = Some(height);
+                work = links[root].2.work();
+                chainwork[root] = work;
+            }
+            while let Some(position) = path.pop() {
+                height += 1;
+                heights[position] = Some(height);
+                work = work.saturating_add(links[position].2.work());
+                chainwork[position] = work;
+            }
+        }
+        let heights = heights
+            .into_iter()
+            .map(|height| height.expect("every block gets a height"))
+            .collect::<Vec<u32>>();
+        // the tip is the block with the most work behind it, the first seen among the ones with the same work
+        // as the reference client does
+        let mut main = vec![];
+        let tip = (0..links.len()).fold(None, |tip: Option<usize>, position| match tip {
+            Some(tip) if chainwork[tip] >= chainwork[position] => Some(tip),
+            _ => Some(position),
+        });
+        let mut next = tip;
+        while let Some(position) = next {
+            main.push(position);
+            next = parents[position];
+        }
+        main.reverse();
+        let base = main.first().map(|&position| heights[position]).unwrap_or(base);
+        Self {
+            positions,
+            heights,
+            main,
+            base,
+        }
+    }
+
+    pub(crate) fn position(&self, hash: &BlockHash) -> Option<usize> {
+        self.positions.get(hash).copied()
+    }
+
+    pub(crate) fn height(&self, position: usize) -> u32 {
+        self.heights[position]
+    }
+
+    pub(crate) fn main(&self) -> &[usize] {
+        &self.main
+    }
+
+    pub(crate) fn base(&self) -> u32 {
+        self.base
+    }
+
+    pub(crate) fn main_position(&self, height: u32) -> Option<usize> {
+        let offset = height.checked_sub(self.base)? as usize;
+        self.main.get(offset).copied()
+    }
+
+    pub(crate) fn is_main(&self, position: usize) -> bool {
+        self.main_position(self.heights[position]) == Some(position)
+    }
+}
+
+// positions of the transactions by txid, the ones in the best chain prevail over the stale blocks
+pub(crate) struct TransactionIndex {
+    positions: HashMap<Txid, (usize, usize)>,
+}
+
+impl TransactionIndex {
+    pub(crate) fn build<T: TransactionBlock>(blocks: &[T], chain: &ChainIndex) -> Self {
+        let stale = (0..blocks.len()).filter(|&position| !chain.is_main(position));
+        let mut positions = HashMap::new();
+        for block in stale.chain(chain.main().iter().copied()) {
+            for (index, transaction) in blocks[block].transactions().iter().enumerate() {
+                positions.insert(transaction.txid(), (block, index));
+            }
+        }
+        Self { positions }
+    }
+
+    pub(crate) fn position(&self, txid: &Txid) -> Option<(usize, usize)> {
+        self.positions.get(txid).copied()
+    }
+}
@@ -1,44 +1,176 @@
 #[cfg(feature = "writer")]
 use serde::{Deserialize, Serialize};
 
-use std::iter::FromIterator;
+use std::iter::{self, FromIterator};
+use std::ops::{Bound, RangeBounds};
+use std::sync::OnceLock;
 
 pub mod block;
+pub mod descriptor;
+pub(crate) mod index;
 pub mod script;
 #[cfg(feature = "writer")]
-mod serde_custom;
+pub(crate) mod serde_custom;
+pub mod signature;
+pub mod spend;
+pub mod taproot;
 pub mod transactions;
 
+use crate::types::{BlockHash, Txid};
 use crate::TransactionBlock;
+use index::{ChainIndex, TransactionIndex};
 
+// the blocks are kept in the order they were loaded, the indexes over them are built on the first lookup
 #[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
 pub struct BlockChain<T: TransactionBlock> {
     blocks: Vec<T>,
+    #[cfg_attr(feature = "writer", serde(skip))]
+    base_height: u32,
+    #[cfg_attr(feature = "writer", serde(skip))]
+    index: OnceLock<ChainIndex>,
+    #[cfg_attr(feature = "writer", serde(skip))]
+    transactions: OnceLock<TransactionIndex>,
 }
 
 impl<T: TransactionBlock> BlockChain<T> {
     pub fn new() -> Self {
         Self::default()
     }
+
+    // the height of the first loaded block when the set does not start at the genesis block
+    pub fn with_base_height(mut self, height: u32) -> Self {
+        self.base_height = height;
+        self.invalidate();
+        self
+    }
+
+    pub fn blocks(&self) -> &[T] {
+        &self.blocks
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.blocks.iter()
+    }
+
+    pub fn get_by_hash(&self, hash: &BlockHash) -> Option<&T> {
+        self.index().position(hash).map(|position| &self.blocks[position])
+    }
+
+    // only the blocks of the best chain are reachable by height
+    pub fn get_by_height(&self, height: u32) -> Option<&T> {
+        self.index()
+            .main_position(height)
+            .map(|position| &self.blocks[position])
+    }
+
+    pub fn height(&self, hash: &BlockHash) -> Option<u32> {
+        let index = self.index();
+        index.position(hash).map(|position| index.height(position))
+    }
+
+    pub fn is_main_chain(&self, hash: &BlockHash) -> bool {
+        let index = self.index();
+        index.position(hash).is_some_and(|position| index.is_main(position))
+    }
+
+    // the block with the most cumulative work behind it, the first one loaded when there are several
+    pub fn tip(&self) -> Option<&T> {
+        self.index().main().last().map(|&position| &self.blocks[position])
+    }
+
+    pub fn tip_height(&self) -> Option<u32> {
+        let index = self.index();
+        index.main().last().map(|&position| index.height(position))
+    }
+
+    // the loaded blocks of the best chain, by increasing height
+    pub fn main_chain(&self) -> impl DoubleEndedIterator<Item = &T> + '_ {
+        self.index().main().iter().map(move |&position| &self.blocks[position])
+    }
+
+    // the parents of the block given, from the closest to the first one loaded, without the block itself
+    pub fn ancestors(&self, hash: &BlockHash) -> impl Iterator<Item = &T> + '_ {
+        let start = self.get_by_hash(hash);
+        iter::successors(start, move |block| self.get_by_hash(&block.previous())).skip(1)
+    }
+
+    pub fn range<R: RangeBounds<u32>>(&self, heights: R) -> impl Iterator<Item = &T> + '_ {
+        let index = self.index();
+        let offset = |height: u64| {
+            height
+                .saturating_sub(index.base() as u64)
+                .min(index.main().len() as u64) as usize
+        };
+        let start = match heights.start_bound() {
+            Bound::Included(&height) => offset(height as u64),
+            Bound::Excluded(&height) => offset(height as u64 + 1),
+            Bound::Unbounded => 0,
+        };
+        let end = match heights.end_bound() {
+            Bound::Included(&height) => offset(height as u64 + 1),
+            Bound::Excluded(&height) => offset(height as u64),
+            Bound::Unbounded => index.main().len(),
+        };
+        index.main()[start..end.max(start)]
+            .iter()
+            .map(move |&position| &self.blocks[position])
+    }
+
+    // block times are not monotonic, so this filters the whole best chain instead of stopping early
+    pub fn range_by_time<R: RangeBounds<u32>>(&self, times: R) -> impl Iterator<Item = &T> + '_ {
+        let times = (times.start_bound().cloned(), times.end_bound().cloned());
+        self.main_chain().filter(move |block| times.contains(&block.time()))
+    }
+
+    pub fn get_transaction(&self, txid: &Txid) -> Option<(&T, &T::Transaction)> {
+        let transactions = self
+            .transactions
+            .get_or_init(|| TransactionIndex::build(&self.blocks, self.index()));
+        transactions.position(txid).map(|(block, transaction)| {
+            let block = &self.blocks[block];
+            (block, &block.transactions()[transaction])
+        })
+    }
+
+    fn index(&self) -> &ChainIndex {
+        self.index
+            .get_or_init(|| ChainIndex::build(&self.blocks, self.base_height))
+    }
+
+    fn invalidate(&mut self) {
+        self.index = OnceLock::new();
+        self.transactions = OnceLock::new();
+    }
 }
 
 impl<T: TransactionBlock> Default for BlockChain<T> {
     fn default() -> Self {
-        Self { blocks: vec![] }
+        Self::from(vec![])
     }
 }
 
 impl<T: TransactionBlock> From<Vec<T>> for BlockChain<T> {
     fn from(blocks: Vec<T>) -> Self {
-        Self { blocks }
+        Self {
+            blocks,
+            base_height: 0,
+            index: OnceLock::new(),
+            transactions: OnceLock::new(),
+        }
     }
 }
 
 impl<T: TransactionBlock> FromIterator<T> for BlockChain<T> {
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
-        Self {
-            blocks: iter.into_iter().collect(),
-        }
+        Self::from(iter.into_iter().collect::<Vec<_>>())
     }
 }
 
@@ -62,6 +194,7 @@ impl<'a, T: TransactionBlock> IntoIterator for &'a mut BlockChain<T> {
     type Item = <&'a mut Vec<T> as IntoIterator>::Item;
     type IntoIter = <&'a mut Vec<T> as IntoIterator>::IntoIter;
     fn into_iter(self) -> <Self as std::iter::IntoIterator>::IntoIter {
+        self.invalidate();
         self.blocks.iter_mut()
     }
 }
@@ -0,0 +1,106 @@
+use std::convert::TryFrom;
+use thiserror::Error;
+
+use super::builder::{decode_script_num, ScriptBuilder};
+use super::instructions::{Instruction, Instructions};
+use super::opcodes::{self, OP_1, OP_16, OP_1NEGATE};
+use super::BitcoinScript;
+
+// pushes of up to 4 bytes are shown as numbers, following the reference client, while unassigned
+// opcodes and the tail of a truncated push are written as 0x prefixed raw bytes so from_asm can read them back
+pub fn to_asm(script: &[u8]) -> String {
+    let mut tokens = vec![];
+    let mut instructions = Instructions::new(script);
+    loop {
+        let start = instructions.position();
+        let instruction = match instructions.next() {
+            Some(instruction) => instruction,
+            None => break,
+        };
+        match instruction {
+            Ok(Instruction::PushBytes(bytes)) if bytes.len() <= 4 => {
+                tokens.push(decode_script_num(bytes).unwrap_or_default().to_string())
+            }
+            Ok(Instruction::PushBytes(bytes)) => tokens.push(hex::encode(bytes)),
+            Ok(Instruction::Op(OP_1NEGATE)) => tokens.push("-1".to_owned()),
+            Ok(Instruction::Op(opcode)) if (OP_1..=OP_16).contains(&opcode) => {
+                tokens.push((opcode - OP_1 + 1).to_string())
+            }
+            Ok(Instruction::Op(opcode)) => match opcodes::name(opcode) {
+                Some(name) => tokens.push(name.to_owned()),
+                None => tokens.push(format!("0x{:02x}", opcode)),
+            },
+            Err(_) => tokens.push(format!("0x{}", hex::encode(&script[start..]))),
+        }
+    }
+    tokens.join(" ")
+}
+
+// numbers and data are pushed minimally, so any text produced by to_asm assembles back to the same text
+pub fn from_asm(asm: &str) -> Result<BitcoinScript, AsmError> {
+    let mut builder = ScriptBuilder::new();
+    for token in asm.split_whitespace() {
+        builder = if let Some(number) = parse_number(token) {
+            builder.push_int(number)
+        } else if let Some(raw) = token.strip_prefix("0x") {
+            builder.push_raw(&decode_hex(raw).ok_or_else(|| AsmError::InvalidHex(token.to_owned()))?)
+        } else if let Some(opcode) = opcodes::from_name(token) {
+            builder.push_opcode(opcode)
+        } else if let Some(data) = decode_hex(token) {
+            builder.push_slice(&data)
+        } else {
+            return Err(AsmError::InvalidToken(token.to_owned()));
+        };
+    }
+    Ok(builder.into_script())
+}
+
+// only canonical decimals that fit in a 4 byte script number, anything else is data
+fn parse_number(token: &str) -> Option<i64> {
+    let digits = token.strip_prefix('-').unwrap_or(token);
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    if (digits.len() > 1 && digits.starts_with('0')) || token == "-0" {
+        return None;
+    }
+    let number = token.parse::<i64>().ok()?;
+    i32::try_from(number).ok().filter(|&number| number != i32::MIN)?;
+    Some(number)
+}
+
+fn decode_hex(token: &str) -> Option<Vec<u8>> {
+    hex::decode(token).ok().filter(|data| !data.is_empty())
+}
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum AsmError {
+    #[error("invalid hex data: {0}")]
+    InvalidHex(String),
+    #[error("unknown asm token: {0}")]
+    InvalidToken(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(script: &str, asm: &str) {
+        let script = hex::decode(script).unwrap();
+        assert_eq!(to_asm(&script), asm);
+        assert_eq!(from_asm(asm).unwrap().as_bytes(), &script[..]);
+    }
+
+    #[test]
+    fn writes_unassigned_opcodes_as_raw_bytes() {
+        round_trip("76a9bbac", "OP_DUP OP_HASH160 0xbb OP_CHECKSIG");
+        round_trip("fe", "0xfe");
+    }
+
+    #[test]
+    fn writes_the_tail_of_truncated_pushes_as_raw_bytes() {
+        round_trip("6a0501020304", "OP_RETURN 0x0501020304");
+        round_trip("514d01", "1 0x4d01");
+        round_trip("4c", "0x4c");
+    }
+}
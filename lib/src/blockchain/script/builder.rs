@@ -0,0 +1,102 @@
+use std::convert::TryFrom;
+
+use super::opcodes::{OP_0, OP_1, OP_1NEGATE, OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4};
+use super::BitcoinScript;
+
+#[derive(Clone, Debug, Default)]
+pub struct ScriptBuilder {
+    contents: Vec<u8>,
+}
+
+impl ScriptBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_opcode(mut self, opcode: u8) -> Self {
+        self.contents.push(opcode);
+        self
+    }
+
+    pub fn push_int(self, number: i64) -> Self {
+        match number {
+            -1 => self.push_opcode(OP_1NEGATE),
+            0 => self.push_opcode(OP_0),
+            1..=16 => self.push_opcode(OP_1 + number as u8 - 1),
+            _ => self.push_slice(&encode_script_num(number)),
+        }
+    }
+
+    // uses the smallest push, which is the one the minimal push policy of the reference client accepts
+    pub fn push_slice(mut self, data: &[u8]) -> Self {
+        match *data {
+            [] => return self.push_opcode(OP_0),
+            [number @ 1..=16] => return self.push_opcode(OP_1 + number - 1),
+            [0x81] => return self.push_opcode(OP_1NEGATE),
+            _ => (),
+        }
+        match data.len() {
+            length @ 0..=0x4b => self.contents.push(length as u8),
+            length @ 0x4c..=0xff => self.contents.extend_from_slice(&[OP_PUSHDATA1, length as u8]),
+            length @ 0x100..=0xffff => {
+                self.contents.push(OP_PUSHDATA2);
+                self.contents.extend_from_slice(&(length as u16).to_le_bytes());
+            }
+            length => {
+                self.contents.push(OP_PUSHDATA4);
+                self.contents.extend_from_slice(&(length as u32).to_le_bytes());
+            }
+        }
+        self.contents.extend_from_slice(data);
+        self
+    }
+
+    pub fn push_raw(mut self, data: &[u8]) -> Self {
+        self.contents.extend_from_slice(data);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.contents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
+    }
+
+    pub fn into_script(self) -> BitcoinScript {
+        BitcoinScript::new(self.contents)
+    }
+}
+
+// script numbers are little endian with the sign in the most significant bit
+pub fn encode_script_num(number: i64) -> Vec<u8> {
+    let mut absolute = number.unsigned_abs();
+    let mut bytes = vec![];
+    while absolute > 0 {
+        bytes.push((absolute & 0xff) as u8);
+        absolute >>= 8;
+    }
+    match bytes.last_mut() {
+        Some(last) if *last & 0x80 != 0 => bytes.push(if number < 0 { 0x80 } else { 0x00 }),
+        Some(last) if number < 0 => *last |= 0x80,
+        _ => (),
+    }
+    bytes
+}
+
+pub fn decode_script_num(bytes: &[u8]) -> Option<i64> {
+    if bytes.len() > 8 {
+        return None;
+    }
+    let (last, rest) = match bytes.split_last() {
+        Some(split) => split,
+        None => return Some(0),
+    };
+    let magnitude = rest
+        .iter()
+        .rev()
+        .fold(u64::from(last & 0x7f), |number, &byte| number << 8 | u64::from(byte));
+    let magnitude = i64::try_from(magnitude).ok()?;
+    Some(if last & 0x80 != 0 { -magnitude } else { magnitude })
+}
@@ -0,0 +1,101 @@
+use byteorder::{ByteOrder, LittleEndian};
+use thiserror::Error;
+
+use super::opcodes::{OP_PUSHDATA1, OP_PUSHDATA2, OP_PUSHDATA4};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Instruction<'a> {
+    PushBytes(&'a [u8]),
+    Op(u8),
+}
+
+impl<'a> Instruction<'a> {
+    pub fn push_bytes(&self) -> Option<&'a [u8]> {
+        match *self {
+            Instruction::PushBytes(bytes) => Some(bytes),
+            Instruction::Op(_) => None,
+        }
+    }
+
+    pub fn opcode(&self) -> Option<u8> {
+        match *self {
+            Instruction::PushBytes(_) => None,
+            Instruction::Op(opcode) => Some(opcode),
+        }
+    }
+}
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptError {
+    #[error("the script ended in the middle of a push length")]
+    TruncatedPushLength,
+    #[error("push of {expected} bytes exceeds the {remaining} bytes left in the script")]
+    TruncatedPush { expected: usize, remaining: usize },
+}
+
+#[derive(Clone)]
+pub struct Instructions<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Instructions<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    fn read_length(&mut self, size: usize) -> Result<usize, ScriptError> {
+        let remaining = &self.data[self.position..];
+        if remaining.len() < size {
+            return Err(ScriptError::TruncatedPushLength);
+        }
+        self.position += size;
+        Ok(match size {
+            1 => remaining[0] as usize,
+            2 => LittleEndian::read_u16(remaining) as usize,
+            _ => LittleEndian::read_u32(remaining) as usize,
+        })
+    }
+
+    fn read_push(&mut self, length: usize) -> Result<Instruction<'a>, ScriptError> {
+        let remaining = self.data.len() - self.position;
+        if remaining < length {
+            return Err(ScriptError::TruncatedPush {
+                expected: length,
+                remaining,
+            });
+        }
+        let bytes = &self.data[self.position..self.position + length];
+        self.position += length;
+        Ok(Instruction::PushBytes(bytes))
+    }
+
+    fn read_instruction(&mut self, opcode: u8) -> Result<Instruction<'a>, ScriptError> {
+        match opcode {
+            0x00..=0x4b => self.read_push(opcode as usize),
+            OP_PUSHDATA1 => self.read_length(1).and_then(|length| self.read_push(length)),
+            OP_PUSHDATA2 => self.read_length(2).and_then(|length| self.read_push(length)),
+            OP_PUSHDATA4 => self.read_length(4).and_then(|length| self.read_push(length)),
+            _ => Ok(Instruction::Op(opcode)),
+        }
+    }
+}
+
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Result<Instruction<'a>, ScriptError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let opcode = *self.data.get(self.position)?;
+        self.position += 1;
+        let instruction = self.read_instruction(opcode);
+        if instruction.is_err() {
+            // a malformed push consumes the rest of the script, as in the reference client
+            self.position = self.data.len();
+        }
+        Some(instruction)
+    }
+}
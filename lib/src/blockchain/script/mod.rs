@@ -0,0 +1,80 @@
+#[cfg(feature = "writer")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+mod asm;
+mod builder;
+mod instructions;
+pub mod opcodes;
+mod standard;
+
+pub use asm::AsmError;
+pub use builder::{decode_script_num, encode_script_num, ScriptBuilder};
+pub use instructions::{Instruction, Instructions, ScriptError};
+pub use standard::{decode_small_int, is_push_only, is_valid_key_size};
+pub use standard::{ScriptType, StandardScript, ANCHOR_PROGRAM};
+
+pub const MAX_SCRIPT_SIZE: usize = 10_000;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct BitcoinScript {
+    contents: Vec<u8>,
+}
+
+impl BitcoinScript {
+    pub fn new(contents: Vec<u8>) -> Self {
+        Self { contents }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.contents
+    }
+
+    pub fn len(&self) -> usize {
+        self.contents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
+    }
+
+    pub fn instructions(&self) -> Instructions<'_> {
+        Instructions::new(&self.contents)
+    }
+
+    pub fn classify(&self) -> StandardScript {
+        StandardScript::classify(&self.contents)
+    }
+
+    pub fn script_type(&self) -> ScriptType {
+        self.classify().script_type()
+    }
+
+    // scripts that can never be spent, which the reference client leaves out of its utxo set
+    pub fn is_unspendable(&self) -> bool {
+        self.contents.first() == Some(&opcodes::OP_RETURN) || self.contents.len() > MAX_SCRIPT_SIZE
+    }
+
+    pub fn asm(&self) -> String {
+        asm::to_asm(&self.contents)
+    }
+
+    pub fn from_asm(asm: &str) -> Result<Self, AsmError> {
+        asm::from_asm(asm)
+    }
+}
+
+#[cfg(feature = "writer")]
+impl Serialize for BitcoinScript {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        hex::encode(&self.contents).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "writer")]
+impl<'de> Deserialize<'de> for BitcoinScript {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex_string = <&str as Deserialize<'de>>::deserialize(deserializer)?;
+        let bytes = hex::decode(hex_string).map_err(de::Error::custom)?;
+        Ok(Self::new(bytes))
+    }
+}
@@ -0,0 +1,192 @@
+use arrayref::array_ref;
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+
+use super::instructions::{Instruction, Instructions};
+use super::opcodes::*;
+
+pub const ANCHOR_PROGRAM: [u8; 2] = [0x4e, 0x73];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StandardScript {
+    P2pk(Vec<u8>),
+    P2pkh([u8; 20]),
+    P2sh([u8; 20]),
+    P2wpkh([u8; 20]),
+    P2wsh([u8; 32]),
+    P2tr([u8; 32]),
+    Multisig { required: u8, keys: Vec<Vec<u8>> },
+    NullData,
+    Anchor,
+    WitnessUnknown { version: u8, program: Vec<u8> },
+    NonStandard,
+}
+
+impl StandardScript {
+    pub fn classify(script: &[u8]) -> Self {
+        if let Some(hash) = Self::match_p2sh(script) {
+            return StandardScript::P2sh(hash);
+        }
+        if let Some((version, program)) = Self::match_witness_program(script) {
+            return Self::classify_witness_program(version, program);
+        }
+        if Self::match_null_data(script) {
+            return StandardScript::NullData;
+        }
+        if let Some(key) = Self::match_p2pk(script) {
+            return StandardScript::P2pk(key.to_owned());
+        }
+        if let Some(hash) = Self::match_p2pkh(script) {
+            return StandardScript::P2pkh(hash);
+        }
+        if let Some((required, keys)) = Self::match_multisig(script) {
+            return StandardScript::Multisig { required, keys };
+        }
+        StandardScript::NonStandard
+    }
+
+    pub fn script_type(&self) -> ScriptType {
+        match *self {
+            StandardScript::P2pk(_) => ScriptType::P2pk,
+            StandardScript::P2pkh(_) => ScriptType::P2pkh,
+            StandardScript::P2sh(_) => ScriptType::P2sh,
+            StandardScript::P2wpkh(_) => ScriptType::P2wpkh,
+            StandardScript::P2wsh(_) => ScriptType::P2wsh,
+            StandardScript::P2tr(_) => ScriptType::P2tr,
+            StandardScript::Multisig { .. } => ScriptType::Multisig,
+            StandardScript::NullData => ScriptType::NullData,
+            StandardScript::Anchor => ScriptType::Anchor,
+            StandardScript::WitnessUnknown { .. } => ScriptType::WitnessUnknown,
+            StandardScript::NonStandard => ScriptType::NonStandard,
+        }
+    }
+
+    fn classify_witness_program(version: u8, program: &[u8]) -> Self {
+        match (version, program.len()) {
+            (0, 20) => StandardScript::P2wpkh(array_ref!(program, 0, 20).to_owned()),
+            (0, 32) => StandardScript::P2wsh(array_ref!(program, 0, 32).to_owned()),
+            (0, _) => StandardScript::NonStandard,
+            (1, 32) => StandardScript::P2tr(array_ref!(program, 0, 32).to_owned()),
+            (1, 2) if program == ANCHOR_PROGRAM => StandardScript::Anchor,
+            _ => StandardScript::WitnessUnknown {
+                version,
+                program: program.to_owned(),
+            },
+        }
+    }
+
+    fn match_p2sh(script: &[u8]) -> Option<[u8; 20]> {
+        match script {
+            [OP_HASH160, 0x14, hash @ .., OP_EQUAL] if hash.len() == 20 => Some(array_ref!(hash, 0, 20).to_owned()),
+            _ => None,
+        }
+    }
+
+    fn match_p2pkh(script: &[u8]) -> Option<[u8; 20]> {
+        match script {
+            [OP_DUP, OP_HASH160, 0x14, hash @ .., OP_EQUALVERIFY, OP_CHECKSIG] if hash.len() == 20 => {
+                Some(array_ref!(hash, 0, 20).to_owned())
+            }
+            _ => None,
+        }
+    }
+
+    fn match_p2pk(script: &[u8]) -> Option<&[u8]> {
+        match script {
+            [size, key @ .., OP_CHECKSIG] if *size as usize == key.len() && is_valid_key_size(key) => Some(key),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn match_witness_program(script: &[u8]) -> Option<(u8, &[u8])> {
+        if script.len() < 4 || script.len() > 42 {
+            return None;
+        }
+        let version = decode_small_int(script[0])?;
+        if script[1] as usize + 2 != script.len() {
+            return None;
+        }
+        Some((version, &script[2..]))
+    }
+
+    fn match_null_data(script: &[u8]) -> bool {
+        match script.split_first() {
+            Some((&OP_RETURN, rest)) => is_push_only(rest),
+            _ => false,
+        }
+    }
+
+    fn match_multisig(script: &[u8]) -> Option<(u8, Vec<Vec<u8>>)> {
+        let instructions = Instructions::new(script).collect::<Result<Vec<_>, _>>().ok()?;
+        let (first, rest) = instructions.split_first()?;
+        let (last, rest) = rest.split_last()?;
+        let (total, keys) = rest.split_last()?;
+        if *last != Instruction::Op(OP_CHECKMULTISIG) {
+            return None;
+        }
+        let required = first.opcode().and_then(decode_small_int).filter(|&m| m >= 1)?;
+        let total = total.opcode().and_then(decode_small_int).filter(|&n| n >= 1)?;
+        if total < required || total as usize != keys.len() {
+            return None;
+        }
+        keys.iter()
+            .map(|key| {
+                key.push_bytes()
+                    .filter(|bytes| is_valid_key_size(bytes))
+                    .map(ToOwned::to_owned)
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|keys| (required, keys))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub enum ScriptType {
+    #[cfg_attr(feature = "writer", serde(rename = "pubkey"))]
+    P2pk,
+    #[cfg_attr(feature = "writer", serde(rename = "pubkeyhash"))]
+    P2pkh,
+    #[cfg_attr(feature = "writer", serde(rename = "scripthash"))]
+    P2sh,
+    #[cfg_attr(feature = "writer", serde(rename = "witness_v0_keyhash"))]
+    P2wpkh,
+    #[cfg_attr(feature = "writer", serde(rename = "witness_v0_scripthash"))]
+    P2wsh,
+    #[cfg_attr(feature = "writer", serde(rename = "witness_v1_taproot"))]
+    P2tr,
+    #[cfg_attr(feature = "writer", serde(rename = "multisig"))]
+    Multisig,
+    #[cfg_attr(feature = "writer", serde(rename = "nulldata"))]
+    NullData,
+    #[cfg_attr(feature = "writer", serde(rename = "anchor"))]
+    Anchor,
+    #[cfg_attr(feature = "writer", serde(rename = "witness_unknown"))]
+    WitnessUnknown,
+    #[cfg_attr(feature = "writer", serde(rename = "nonstandard"))]
+    NonStandard,
+}
+
+pub fn decode_small_int(opcode: u8) -> Option<u8> {
+    match opcode {
+        OP_0 => Some(0),
+        OP_1..=OP_16 => Some(opcode - OP_1 + 1),
+        _ => None,
+    }
+}
+
+pub fn is_valid_key_size(key: &[u8]) -> bool {
+    match key.first() {
+        Some(0x02) | Some(0x03) => key.len() == 33,
+        Some(0x04) | Some(0x06) | Some(0x07) => key.len() == 65,
+        _ => false,
+    }
+}
+
+pub fn is_push_only(script: &[u8]) -> bool {
+    Instructions::new(script).all(|instruction| match instruction {
+        Ok(Instruction::PushBytes(_)) => true,
+        Ok(Instruction::Op(opcode)) => opcode <= OP_16,
+        Err(_) => false,
+    })
+}
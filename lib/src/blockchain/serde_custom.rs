@@ -13,3 +13,30 @@ pub mod version {
         Ok(u32::from_be_bytes(*data))
     }
 }
+
+pub mod hex_bytes {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        hex::encode(bytes).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex_string = <&str as Deserialize<'de>>::deserialize(deserializer)?;
+        hex::decode(hex_string).map_err(de::Error::custom)
+    }
+}
+
+pub mod hex_option {
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+        bytes.as_ref().map(hex::encode).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error> {
+        <Option<&str> as Deserialize<'de>>::deserialize(deserializer)?
+            .map(|hex_string| hex::decode(hex_string).map_err(de::Error::custom))
+            .transpose()
+    }
+}
@@ -0,0 +1,209 @@
+#[cfg(feature = "writer")]
+use serde::{Serialize, Serializer};
+use std::fmt;
+use thiserror::Error;
+
+pub const SIGHASH_ALL: u8 = 0x01;
+pub const SIGHASH_NONE: u8 = 0x02;
+pub const SIGHASH_SINGLE: u8 = 0x03;
+pub const SIGHASH_ANYONECANPAY: u8 = 0x80;
+
+// half of the order of the secp256k1 group, the upper bound of low-S values
+const HALF_ORDER: [u8; 32] = [
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x5d, 0x57, 0x6e,
+    0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SighashType(u8);
+
+impl SighashType {
+    pub fn new(flags: u8) -> Self {
+        Self(flags)
+    }
+
+    pub fn flags(self) -> u8 {
+        self.0
+    }
+
+    pub fn base(self) -> u8 {
+        self.0 & !SIGHASH_ANYONECANPAY
+    }
+
+    pub fn anyone_can_pay(self) -> bool {
+        self.0 & SIGHASH_ANYONECANPAY != 0
+    }
+
+    pub fn is_defined(self) -> bool {
+        (SIGHASH_ALL..=SIGHASH_SINGLE).contains(&self.base())
+    }
+}
+
+impl fmt::Display for SighashType {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let base = match self.base() {
+            SIGHASH_ALL => "ALL",
+            SIGHASH_NONE => "NONE",
+            SIGHASH_SINGLE => "SINGLE",
+            _ => return formatter.pad(&format!("0x{:02x}", self.0)),
+        };
+        if self.anyone_can_pay() {
+            formatter.pad(&format!("{}|ANYONECANPAY", base))
+        } else {
+            formatter.pad(base)
+        }
+    }
+}
+
+#[cfg(feature = "writer")]
+impl Serialize for SighashType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EcdsaSignature {
+    r: [u8; 32],
+    s: [u8; 32],
+    sighash: SighashType,
+    strict_der: bool,
+    size: usize,
+}
+
+impl EcdsaSignature {
+    // parses the DER encoding leniently, as the reference client did before BIP66, keeping track of strictness
+    pub fn parse(signature: &[u8]) -> Result<Self, SignatureError> {
+        let (&sighash, der) = signature.split_last().ok_or(SignatureError::Empty)?;
+        let (r, s) = parse_der_lax(der).ok_or(SignatureError::InvalidDer)?;
+        Ok(Self {
+            r: scalar(r).ok_or(SignatureError::Overflow)?,
+            s: scalar(s).ok_or(SignatureError::Overflow)?,
+            sighash: SighashType::new(sighash),
+            strict_der: is_strict_der(signature),
+            size: signature.len(),
+        })
+    }
+
+    pub fn r(&self) -> &[u8; 32] {
+        &self.r
+    }
+
+    pub fn s(&self) -> &[u8; 32] {
+        &self.s
+    }
+
+    pub fn sighash_type(&self) -> SighashType {
+        self.sighash
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_strict_der(&self) -> bool {
+        self.strict_der
+    }
+
+    pub fn is_low_s(&self) -> bool {
+        self.s <= HALF_ORDER
+    }
+
+    // a low R fits in 32 bytes without a sign padding byte, wallets grind nonces to always get one
+    pub fn is_low_r(&self) -> bool {
+        self.r[0] < 0x80
+    }
+
+    // any of these allow a third party to change the signature, and so the txid, without invalidating it
+    pub fn is_canonical(&self) -> bool {
+        self.strict_der && self.is_low_s() && self.sighash.is_defined()
+    }
+}
+
+// the BIP66 check, over the signature including the trailing sighash byte
+pub fn is_strict_der(signature: &[u8]) -> bool {
+    let length = signature.len();
+    if !(9..=73).contains(&length) || signature[0] != 0x30 || signature[1] as usize != length - 3 {
+        return false;
+    }
+    let r_length = signature[3] as usize;
+    if 5 + r_length >= length {
+        return false;
+    }
+    let s_length = signature[5 + r_length] as usize;
+    if r_length + s_length + 7 != length {
+        return false;
+    }
+    let r = &signature[4..4 + r_length];
+    let s = &signature[6 + r_length..6 + r_length + s_length];
+    signature[2] == 0x02 && signature[4 + r_length] == 0x02 && is_strict_integer(r) && is_strict_integer(s)
+}
+
+fn is_strict_integer(integer: &[u8]) -> bool {
+    match *integer {
+        [] => false,
+        [first, ..] if first & 0x80 != 0 => false,
+        [0x00, second, ..] => second & 0x80 != 0,
+        _ => true,
+    }
+}
+
+fn parse_der_lax(der: &[u8]) -> Option<(&[u8], &[u8])> {
+    let mut position = 0;
+    if *der.get(position)? != 0x30 {
+        return None;
+    }
+    position += 1;
+    // the sequence length is not checked by the lax parser
+    read_length(der, &mut position)?;
+    let r = read_integer(der, &mut position)?;
+    let s = read_integer(der, &mut position)?;
+    Some((r, s))
+}
+
+fn read_integer<'a>(der: &'a [u8], position: &mut usize) -> Option<&'a [u8]> {
+    if *der.get(*position)? != 0x02 {
+        return None;
+    }
+    *position += 1;
+    let length = read_length(der, position)?;
+    let integer = der.get(*position..position.checked_add(length)?)?;
+    *position += length;
+    Some(integer)
+}
+
+fn read_length(der: &[u8], position: &mut usize) -> Option<usize> {
+    let first = *der.get(*position)?;
+    *position += 1;
+    if first & 0x80 == 0 {
+        return Some(first as usize);
+    }
+    let bytes = der.get(*position..*position + (first & 0x7f) as usize)?;
+    *position += bytes.len();
+    let significant = bytes.iter().skip_while(|&&byte| byte == 0).collect::<Vec<_>>();
+    if significant.len() > std::mem::size_of::<usize>() {
+        return None;
+    }
+    Some(significant.iter().fold(0, |length, &&byte| length << 8 | byte as usize))
+}
+
+fn scalar(integer: &[u8]) -> Option<[u8; 32]> {
+    let start = integer.iter().position(|&byte| byte != 0).unwrap_or(integer.len());
+    let integer = &integer[start..];
+    if integer.len() > 32 {
+        return None;
+    }
+    let mut scalar = [0; 32];
+    scalar[32 - integer.len()..].copy_from_slice(integer);
+    Some(scalar)
+}
+
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureError {
+    #[error("the signature is empty")]
+    Empty,
+    #[error("the signature is not DER encoded")]
+    InvalidDer,
+    #[error("the signature values overflow 32 bytes")]
+    Overflow,
+}
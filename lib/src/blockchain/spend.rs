@@ -0,0 +1,268 @@
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+
+use super::script::opcodes::{OP_CHECKSIG, OP_CHECKSIGADD, OP_CHECKSIGVERIFY};
+use super::script::{is_valid_key_size, BitcoinScript, Instruction, Instructions, StandardScript};
+use super::taproot::{TaprootSpend, TAPROOT_LEAF_TAPSCRIPT};
+use super::transactions::Witness;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "writer", serde(rename_all = "snake_case"))]
+pub enum SpendType {
+    Coinbase,
+    P2pk,
+    P2pkh,
+    P2sh,
+    P2shP2wpkh,
+    P2shP2wsh,
+    P2wpkh,
+    P2wsh,
+    P2trKeyPath,
+    P2trScriptPath,
+    Multisig,
+    NonStandard,
+}
+
+impl SpendType {
+    pub fn is_segwit(self) -> bool {
+        matches!(
+            self,
+            SpendType::P2shP2wpkh
+                | SpendType::P2shP2wsh
+                | SpendType::P2wpkh
+                | SpendType::P2wsh
+                | SpendType::P2trKeyPath
+                | SpendType::P2trScriptPath
+        )
+    }
+
+    pub fn is_taproot(self) -> bool {
+        matches!(self, SpendType::P2trKeyPath | SpendType::P2trScriptPath)
+    }
+}
+
+pub struct SpendInfo {
+    kind: SpendType,
+    signatures: Vec<Vec<u8>>,
+    pubkeys: Vec<Vec<u8>>,
+    redeem_script: Option<BitcoinScript>,
+    witness_script: Option<BitcoinScript>,
+}
+
+impl SpendInfo {
+    fn new(kind: SpendType) -> Self {
+        Self {
+            kind,
+            signatures: vec![],
+            pubkeys: vec![],
+            redeem_script: None,
+            witness_script: None,
+        }
+    }
+
+    pub fn infer(script_sig: &BitcoinScript, witness: Option<&Witness>, coinbase: bool) -> Self {
+        if coinbase {
+            return Self::new(SpendType::Coinbase);
+        }
+        let pushes = match Self::collect_pushes(script_sig.as_bytes()) {
+            Some(pushes) => pushes,
+            None => return Self::new(SpendType::NonStandard),
+        };
+        match witness.filter(|witness| witness.count() > 0) {
+            Some(witness) if pushes.is_empty() => Self::infer_native_witness(witness.items()),
+            Some(witness) => Self::infer_nested_witness(&pushes, witness.items()),
+            None => Self::infer_legacy(&pushes),
+        }
+    }
+
+    pub fn kind(&self) -> SpendType {
+        self.kind
+    }
+
+    pub fn signatures(&self) -> &[Vec<u8>] {
+        &self.signatures
+    }
+
+    pub fn pubkeys(&self) -> &[Vec<u8>] {
+        &self.pubkeys
+    }
+
+    pub fn redeem_script(&self) -> Option<&BitcoinScript> {
+        self.redeem_script.as_ref()
+    }
+
+    pub fn witness_script(&self) -> Option<&BitcoinScript> {
+        self.witness_script.as_ref()
+    }
+
+    fn collect_pushes(script: &[u8]) -> Option<Vec<&[u8]>> {
+        Instructions::new(script)
+            .map(|instruction| instruction.ok().and_then(|instruction| instruction.push_bytes()))
+            .collect()
+    }
+
+    fn infer_legacy(pushes: &[&[u8]]) -> Self {
+        match pushes {
+            [signature] if is_ecdsa_signature(signature) => {
+                Self::new(SpendType::P2pk).with_signatures(pushes.iter().copied())
+            }
+            [signature, key] if is_ecdsa_signature(signature) && is_valid_key_size(key) => Self::new(SpendType::P2pkh)
+                .with_signatures(pushes.iter().copied())
+                .with_keys(pushes.iter().copied()),
+            [dummy, signatures @ ..]
+                if dummy.is_empty()
+                    && !signatures.is_empty()
+                    && signatures.iter().all(|sig| is_ecdsa_signature(sig)) =>
+            {
+                Self::new(SpendType::Multisig).with_signatures(signatures.iter().copied())
+            }
+            [arguments @ .., redeem] if !redeem.is_empty() && Self::looks_like_redeem_script(redeem) => {
+                let redeem_script = BitcoinScript::new(redeem.to_vec());
+                Self::new(SpendType::P2sh)
+                    .with_signatures(arguments.iter().copied())
+                    .with_keys(arguments.iter().copied())
+                    .with_script_keys(&redeem_script)
+                    .with_redeem_script(redeem_script)
+            }
+            _ => Self::new(SpendType::NonStandard),
+        }
+    }
+
+    fn infer_nested_witness(pushes: &[&[u8]], items: &[Vec<u8>]) -> Self {
+        let redeem = match pushes {
+            [redeem] => *redeem,
+            _ => return Self::new(SpendType::NonStandard),
+        };
+        let nested = match StandardScript::match_witness_program(redeem) {
+            Some((0, program)) if program.len() == 20 => {
+                Self::infer_p2wpkh(items).map(|info| (SpendType::P2shP2wpkh, info))
+            }
+            Some((0, program)) if program.len() == 32 => Some((SpendType::P2shP2wsh, Self::infer_p2wsh(items))),
+            _ => None,
+        };
+        match nested {
+            Some((kind, mut info)) => {
+                info.kind = kind;
+                info.with_redeem_script(BitcoinScript::new(redeem.to_vec()))
+            }
+            None => Self::new(SpendType::NonStandard),
+        }
+    }
+
+    fn infer_native_witness(items: &[Vec<u8>]) -> Self {
+        if let Some(info) = Self::infer_p2wpkh(items) {
+            return info;
+        }
+        match TaprootSpend::parse(items) {
+            Ok(TaprootSpend::KeyPath { signature, .. }) if is_schnorr_signature(signature) => {
+                Self::new(SpendType::P2trKeyPath).with_schnorr_signatures(std::iter::once(signature))
+            }
+            Ok(TaprootSpend::ScriptPath {
+                arguments,
+                script,
+                control_block,
+                ..
+            }) if control_block.leaf_version() == TAPROOT_LEAF_TAPSCRIPT => {
+                let tapscript = BitcoinScript::new(script.to_owned());
+                Self::new(SpendType::P2trScriptPath)
+                    .with_schnorr_signatures(arguments.iter().map(Vec::as_slice))
+                    .with_tapscript_keys(&tapscript)
+                    .with_witness_script(tapscript)
+            }
+            _ => Self::infer_p2wsh(items),
+        }
+    }
+
+    fn infer_p2wpkh(items: &[Vec<u8>]) -> Option<Self> {
+        match items {
+            [signature, key] if is_ecdsa_signature(signature) && key.len() == 33 && is_valid_key_size(key) => Some(
+                Self::new(SpendType::P2wpkh)
+                    .with_signatures(std::iter::once(&signature[..]))
+                    .with_keys(std::iter::once(&key[..])),
+            ),
+            _ => None,
+        }
+    }
+
+    fn infer_p2wsh(items: &[Vec<u8>]) -> Self {
+        match items.split_last() {
+            Some((script, arguments)) => {
+                let witness_script = BitcoinScript::new(script.clone());
+                Self::new(SpendType::P2wsh)
+                    .with_signatures(arguments.iter().map(Vec::as_slice))
+                    .with_keys(arguments.iter().map(Vec::as_slice))
+                    .with_script_keys(&witness_script)
+                    .with_witness_script(witness_script)
+            }
+            None => Self::new(SpendType::NonStandard),
+        }
+    }
+
+    fn looks_like_redeem_script(script: &[u8]) -> bool {
+        if is_ecdsa_signature(script) || is_valid_key_size(script) {
+            return false;
+        }
+        Instructions::new(script).all(|instruction| instruction.is_ok())
+    }
+
+    fn with_signatures<'a>(mut self, items: impl Iterator<Item = &'a [u8]>) -> Self {
+        self.signatures
+            .extend(items.filter(|item| is_ecdsa_signature(item)).map(ToOwned::to_owned));
+        self
+    }
+
+    fn with_schnorr_signatures<'a>(mut self, items: impl Iterator<Item = &'a [u8]>) -> Self {
+        self.signatures
+            .extend(items.filter(|item| is_schnorr_signature(item)).map(ToOwned::to_owned));
+        self
+    }
+
+    fn with_keys<'a>(mut self, items: impl Iterator<Item = &'a [u8]>) -> Self {
+        self.pubkeys
+            .extend(items.filter(|item| is_valid_key_size(item)).map(ToOwned::to_owned));
+        self
+    }
+
+    fn with_script_keys(mut self, script: &BitcoinScript) -> Self {
+        match script.classify() {
+            StandardScript::P2pk(key) => self.pubkeys.push(key),
+            StandardScript::Multisig { keys, .. } => self.pubkeys.extend(keys),
+            _ => (),
+        }
+        self
+    }
+
+    fn with_tapscript_keys(mut self, script: &BitcoinScript) -> Self {
+        let instructions = script.instructions().filter_map(Result::ok).collect::<Vec<_>>();
+        for pair in instructions.windows(2) {
+            if let [Instruction::PushBytes(key), Instruction::Op(OP_CHECKSIG)]
+            | [Instruction::PushBytes(key), Instruction::Op(OP_CHECKSIGVERIFY)]
+            | [Instruction::PushBytes(key), Instruction::Op(OP_CHECKSIGADD)] = *pair
+            {
+                if key.len() == 32 {
+                    self.pubkeys.push(key.to_owned());
+                }
+            }
+        }
+        self
+    }
+
+    fn with_redeem_script(mut self, script: BitcoinScript) -> Self {
+        self.redeem_script = Some(script);
+        self
+    }
+
+    fn with_witness_script(mut self, script: BitcoinScript) -> Self {
+        self.witness_script = Some(script);
+        self
+    }
+}
+
+pub fn is_ecdsa_signature(item: &[u8]) -> bool {
+    item.len() >= 9 && item.len() <= 73 && item[0] == 0x30 && item[1] as usize == item.len() - 3
+}
+
+pub fn is_schnorr_signature(item: &[u8]) -> bool {
+    item.len() == 64 || item.len() == 65
+}
@@ -0,0 +1,240 @@
+use arrayref::array_ref;
+use k256::elliptic_curve::point::DecompressPoint;
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use k256::elliptic_curve::subtle::Choice;
+use k256::elliptic_curve::PrimeField;
+use k256::{AffinePoint, FieldBytes, ProjectivePoint, Scalar};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::types::VarInt;
+
+pub const TAPROOT_ANNEX_TAG: u8 = 0x50;
+pub const TAPROOT_LEAF_MASK: u8 = 0xfe;
+pub const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+pub const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+pub const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+pub const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+pub enum TaprootSpend<'a> {
+    KeyPath {
+        signature: &'a [u8],
+        annex: Option<&'a [u8]>,
+    },
+    ScriptPath {
+        arguments: &'a [Vec<u8>],
+        script: &'a [u8],
+        control_block: ControlBlock,
+        annex: Option<&'a [u8]>,
+    },
+}
+
+impl<'a> TaprootSpend<'a> {
+    pub fn parse(items: &'a [Vec<u8>]) -> Result<Self, TaprootError> {
+        let annex = annex(items);
+        let stack = match annex {
+            Some(_) => &items[..items.len() - 1],
+            None => items,
+        };
+        match stack {
+            [] => Err(TaprootError::EmptyWitness),
+            [signature] => Ok(TaprootSpend::KeyPath { signature, annex }),
+            [arguments @ .., script, control] => Ok(TaprootSpend::ScriptPath {
+                arguments,
+                script,
+                control_block: ControlBlock::parse(control)?,
+                annex,
+            }),
+        }
+    }
+
+    pub fn annex(&self) -> Option<&'a [u8]> {
+        match *self {
+            TaprootSpend::KeyPath { annex, .. } | TaprootSpend::ScriptPath { annex, .. } => annex,
+        }
+    }
+
+    pub fn is_key_path(&self) -> bool {
+        matches!(*self, TaprootSpend::KeyPath { .. })
+    }
+
+    pub fn script(&self) -> Option<&'a [u8]> {
+        match *self {
+            TaprootSpend::KeyPath { .. } => None,
+            TaprootSpend::ScriptPath { script, .. } => Some(script),
+        }
+    }
+
+    pub fn control_block(&self) -> Option<&ControlBlock> {
+        match *self {
+            TaprootSpend::KeyPath { .. } => None,
+            TaprootSpend::ScriptPath { ref control_block, .. } => Some(control_block),
+        }
+    }
+
+    pub fn tapleaf_hash(&self) -> Option<[u8; 32]> {
+        match *self {
+            TaprootSpend::KeyPath { .. } => None,
+            TaprootSpend::ScriptPath {
+                script,
+                ref control_block,
+                ..
+            } => Some(tapleaf_hash(control_block.leaf_version(), script)),
+        }
+    }
+
+    pub fn output_key(&self) -> Option<Result<OutputKey, TaprootError>> {
+        match *self {
+            TaprootSpend::KeyPath { .. } => None,
+            TaprootSpend::ScriptPath {
+                script,
+                ref control_block,
+                ..
+            } => Some(control_block.output_key(script)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ControlBlock {
+    leaf_version: u8,
+    parity: u8,
+    internal_key: [u8; 32],
+    merkle_path: Vec<[u8; 32]>,
+}
+
+impl ControlBlock {
+    pub fn parse(data: &[u8]) -> Result<Self, TaprootError> {
+        if data.len() < TAPROOT_CONTROL_BASE_SIZE
+            || !(data.len() - TAPROOT_CONTROL_BASE_SIZE).is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+            || (data.len() - TAPROOT_CONTROL_BASE_SIZE) / TAPROOT_CONTROL_NODE_SIZE > TAPROOT_CONTROL_MAX_NODE_COUNT
+        {
+            return Err(TaprootError::InvalidControlBlockSize(data.len()));
+        }
+        Ok(Self {
+            leaf_version: data[0] & TAPROOT_LEAF_MASK,
+            parity: data[0] & !TAPROOT_LEAF_MASK,
+            internal_key: array_ref!(data, 1, 32).to_owned(),
+            merkle_path: data[TAPROOT_CONTROL_BASE_SIZE..]
+                .chunks(TAPROOT_CONTROL_NODE_SIZE)
+                .map(|node| array_ref!(node, 0, 32).to_owned())
+                .collect(),
+        })
+    }
+
+    pub fn leaf_version(&self) -> u8 {
+        self.leaf_version
+    }
+
+    pub fn parity(&self) -> u8 {
+        self.parity
+    }
+
+    pub fn internal_key(&self) -> &[u8; 32] {
+        &self.internal_key
+    }
+
+    pub fn merkle_path(&self) -> &[[u8; 32]] {
+        &self.merkle_path
+    }
+
+    pub fn merkle_root(&self, script: &[u8]) -> [u8; 32] {
+        self.merkle_path
+            .iter()
+            .fold(tapleaf_hash(self.leaf_version, script), |node, sibling| {
+                tapbranch_hash(&node, sibling)
+            })
+    }
+
+    pub fn output_key(&self, script: &[u8]) -> Result<OutputKey, TaprootError> {
+        tweak_key(&self.internal_key, Some(&self.merkle_root(script)))
+    }
+
+    pub fn verify(&self, script: &[u8], output_key: &[u8; 32]) -> bool {
+        match self.output_key(script) {
+            Ok(key) => key.parity == self.parity && key.key == *output_key,
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct OutputKey {
+    key: [u8; 32],
+    parity: u8,
+}
+
+impl OutputKey {
+    pub fn key(&self) -> &[u8; 32] {
+        &self.key
+    }
+
+    pub fn parity(&self) -> u8 {
+        self.parity
+    }
+}
+
+pub fn annex(items: &[Vec<u8>]) -> Option<&[u8]> {
+    match items {
+        [_, .., last] if last.first() == Some(&TAPROOT_ANNEX_TAG) => Some(last),
+        _ => None,
+    }
+}
+
+pub fn tagged_hash(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    data.iter().for_each(|chunk| hasher.update(chunk));
+    let digest = hasher.finalize();
+    array_ref!(&digest[..], 0, 32).to_owned()
+}
+
+pub fn tapleaf_hash(leaf_version: u8, script: &[u8]) -> [u8; 32] {
+    let size = VarInt::new(script.len() as u64).to_bytes();
+    tagged_hash("TapLeaf", &[&[leaf_version], &size, script])
+}
+
+pub fn tapbranch_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    if left <= right {
+        tagged_hash("TapBranch", &[left, right])
+    } else {
+        tagged_hash("TapBranch", &[right, left])
+    }
+}
+
+pub fn tweak_key(internal_key: &[u8; 32], merkle_root: Option<&[u8; 32]>) -> Result<OutputKey, TaprootError> {
+    let tweak = match merkle_root {
+        Some(root) => tagged_hash("TapTweak", &[internal_key, root]),
+        None => tagged_hash("TapTweak", &[internal_key]),
+    };
+    let point = Option::<AffinePoint>::from(AffinePoint::decompress(
+        &FieldBytes::from(*internal_key),
+        Choice::from(0),
+    ))
+    .ok_or(TaprootError::InvalidInternalKey)?;
+    let scalar =
+        Option::<Scalar>::from(Scalar::from_repr(FieldBytes::from(tweak))).ok_or(TaprootError::InvalidTweak)?;
+    let tweaked = (ProjectivePoint::from(point) + ProjectivePoint::GENERATOR * scalar).to_affine();
+    let encoded = tweaked.to_encoded_point(true);
+    match encoded.as_bytes() {
+        [prefix, key @ ..] if key.len() == 32 => Ok(OutputKey {
+            key: array_ref!(key, 0, 32).to_owned(),
+            parity: prefix & 1,
+        }),
+        _ => Err(TaprootError::InvalidTweak),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TaprootError {
+    #[error("the witness stack is empty")]
+    EmptyWitness,
+    #[error("invalid control block size: {0}")]
+    InvalidControlBlockSize(usize),
+    #[error("the internal key is not a valid x-only public key")]
+    InvalidInternalKey,
+    #[error("the tweak does not produce a valid output key")]
+    InvalidTweak,
+}
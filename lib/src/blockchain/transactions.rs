@@ -1,9 +1,20 @@
 #[cfg(feature = "writer")]
-use serde::{de, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
+use serde::ser::{SerializeMap, SerializeSeq, SerializeStruct};
+#[cfg(feature = "writer")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::OnceLock;
 
 use super::script::BitcoinScript as BScript;
-use crate::types::BitcoinHash;
+use super::script::{ScriptType, StandardScript};
+use super::spend::{SpendInfo, SpendType};
+use super::taproot::{self, TaprootError, TaprootSpend};
+use crate::address::Address;
+use crate::amount::Amount;
+use crate::encode::Encodable;
+use crate::network::Network;
+use crate::types::{Txid, Wtxid};
 use crate::Transaction as TransactionTrait;
+use crate::TransactionBuilder as TransactionBuilderTrait;
 
 #[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
 pub struct Transaction {
@@ -26,20 +37,119 @@ impl Transaction {
         }
     }
 
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[Output] {
+        &self.outputs
+    }
+
+    pub fn locktime(&self) -> u32 {
+        self.locktime
+    }
+
+    pub fn is_segwit(&self) -> bool {
+        self.segwit
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        matches!(self.inputs.as_slice(), [input] if input.is_coinbase())
+    }
+
+    // none when the sum leaves the money range, which only happens in invalid transactions
+    pub fn output_value(&self) -> Option<Amount> {
+        Amount::checked_sum(self.outputs.iter().map(Output::value))
+    }
+
+    // the fee given the value of the outputs spent, which the transaction itself does not include
+    pub fn fee(&self, input_value: Amount) -> Option<Amount> {
+        input_value.checked_sub(self.output_value()?)
+    }
+
+    pub fn compute_txid(&self) -> Txid {
+        Txid::hash(&self.encoded_without_witness())
+    }
+
+    // equals the txid for transactions without witness data
+    pub fn compute_wtxid(&self) -> Wtxid {
+        Wtxid::hash(&self.encoded())
+    }
+
     fn look_for_witness(inputs: &[Input]) -> bool {
         inputs.iter().any(|item| item.witness.is_some())
     }
 }
 
-impl TransactionTrait for Transaction {}
+impl TransactionTrait for Transaction {
+    type Builder = TransactionBuilder;
 
-#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+    fn txid(&self) -> Txid {
+        self.compute_txid()
+    }
+
+    fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
+    fn outputs(&self) -> &[Output] {
+        &self.outputs
+    }
+}
+
+pub struct TransactionBuilder {
+    version: u32,
+    inputs: Vec<Input>,
+    outputs: Vec<Output>,
+}
+
+impl TransactionBuilderTrait for TransactionBuilder {
+    type Transaction = Transaction;
+
+    fn new(version: u32, _segwit: bool) -> Self {
+        Self {
+            version,
+            inputs: vec![],
+            outputs: vec![],
+        }
+    }
+
+    fn input(&mut self, txid: Txid, vout: u32, script: &[u8], sequence: u32) {
+        let utxo = Utxo::new(txid, vout);
+        self.inputs
+            .push(Input::new(utxo, BScript::new(script.to_owned()), sequence, None));
+    }
+
+    fn output(&mut self, value: Amount, script: &[u8]) {
+        self.outputs.push(Output::new(value, BScript::new(script.to_owned())));
+    }
+
+    // an empty witness is the same as none, it is only there because another input has one
+    fn witness(&mut self, input: usize, items: &[&[u8]]) {
+        if let (Some(input), false) = (self.inputs.get_mut(input), items.is_empty()) {
+            input.assign_witness(Witness::new(items.iter().map(|&item| item.to_owned()).collect()));
+        }
+    }
+
+    fn build(self, _txid: Txid, locktime: u32) -> Transaction {
+        Transaction::new(self.version, self.inputs, self.outputs, locktime)
+    }
+}
+
+// the kind of spend is only inferred when asked for, most inputs are read without needing it
+#[cfg_attr(feature = "writer", derive(Deserialize))]
 pub struct Input {
     #[cfg_attr(feature = "writer", serde(flatten))]
     utxo: Utxo,
     signature: BScript,
     sequence: u32,
     witness: Option<Witness>,
+    #[cfg_attr(feature = "writer", serde(skip))]
+    kind: OnceLock<SpendType>,
 }
 
 impl Input {
@@ -49,38 +159,124 @@ impl Input {
             signature,
             sequence,
             witness,
+            kind: OnceLock::new(),
         }
     }
 
     pub fn assign_witness(&mut self, witness: Witness) {
         self.witness = Some(witness);
+        self.kind = OnceLock::new();
+    }
+
+    pub fn utxo(&self) -> &Utxo {
+        &self.utxo
+    }
+
+    pub fn signature(&self) -> &BScript {
+        &self.signature
+    }
+
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.utxo.is_coinbase()
+    }
+
+    pub fn witness(&self) -> Option<&Witness> {
+        self.witness.as_ref()
+    }
+
+    pub fn spend_type(&self) -> SpendType {
+        *self.kind.get_or_init(|| self.spend_info().kind())
+    }
+
+    pub fn spend_info(&self) -> SpendInfo {
+        SpendInfo::infer(&self.signature, self.witness.as_ref(), self.utxo.is_coinbase())
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 #[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
 pub struct Utxo {
-    txid: BitcoinHash,
+    txid: Txid,
     vout: u32,
 }
 
 impl Utxo {
-    pub fn new(txid: BitcoinHash, vout: u32) -> Self {
+    pub fn new(txid: Txid, vout: u32) -> Self {
         Self { txid, vout }
     }
+
+    pub fn txid(&self) -> &Txid {
+        &self.txid
+    }
+
+    pub fn vout(&self) -> u32 {
+        self.vout
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.vout == u32::MAX && self.txid.is_zero()
+    }
 }
 
-#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+// the type and address only depend on the script, they are worked out the first time they are asked for
+#[derive(Clone)]
+#[cfg_attr(feature = "writer", derive(Deserialize))]
 pub struct Output {
-    value: u64,
+    value: Amount,
     pubkey: BScript,
+    #[cfg_attr(feature = "writer", serde(skip))]
+    kind: OnceLock<ScriptType>,
+    #[cfg_attr(feature = "writer", serde(skip))]
+    address: OnceLock<Option<Address>>,
 }
 
 impl Output {
-    pub fn new(value: u64, pubkey: BScript) -> Self {
-        Self { value, pubkey }
+    pub fn new(value: Amount, pubkey: BScript) -> Self {
+        Self {
+            value,
+            pubkey,
+            kind: OnceLock::new(),
+            address: OnceLock::new(),
+        }
+    }
+
+    pub fn value(&self) -> Amount {
+        self.value
+    }
+
+    pub fn pubkey(&self) -> &BScript {
+        &self.pubkey
+    }
+
+    pub fn address(&self) -> Option<&Address> {
+        self.address.get_or_init(|| self.address_for(Network::Bitcoin)).as_ref()
+    }
+
+    pub fn address_for(&self, network: Network) -> Option<Address> {
+        Address::from_script(&self.classify(), network)
+    }
+
+    pub fn script_type(&self) -> ScriptType {
+        *self.kind.get_or_init(|| self.classify().script_type())
+    }
+
+    pub fn classify(&self) -> StandardScript {
+        self.pubkey.classify()
     }
 }
 
+impl PartialEq for Output {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.pubkey == other.pubkey
+    }
+}
+
+impl Eq for Output {}
+
 pub struct Witness {
     items: Vec<Vec<u8>>,
 }
@@ -93,6 +289,26 @@ impl Witness {
     pub fn count(&self) -> usize {
         self.items.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn items(&self) -> &[Vec<u8>] {
+        &self.items
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Vec<u8>> {
+        self.items.iter()
+    }
+
+    pub fn annex(&self) -> Option<&[u8]> {
+        taproot::annex(&self.items)
+    }
+
+    pub fn taproot(&self) -> Result<TaprootSpend<'_>, TaprootError> {
+        TaprootSpend::parse(&self.items)
+    }
 }
 
 impl IntoIterator for Witness {
@@ -119,6 +335,32 @@ impl<'a> IntoIterator for &'a mut Witness {
     }
 }
 
+#[cfg(feature = "writer")]
+impl Serialize for Input {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("txid", self.utxo.txid())?;
+        map.serialize_entry("vout", &self.utxo.vout())?;
+        map.serialize_entry("signature", &self.signature)?;
+        map.serialize_entry("sequence", &self.sequence)?;
+        map.serialize_entry("witness", &self.witness)?;
+        map.serialize_entry("type", &self.spend_type())?;
+        map.end()
+    }
+}
+
+#[cfg(feature = "writer")]
+impl Serialize for Output {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Output", 4)?;
+        state.serialize_field("value", &self.value)?;
+        state.serialize_field("pubkey", &self.pubkey)?;
+        state.serialize_field("type", &self.script_type())?;
+        state.serialize_field("address", &self.address())?;
+        state.end()
+    }
+}
+
 #[cfg(feature = "writer")]
 impl Serialize for Witness {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
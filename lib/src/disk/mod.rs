@@ -4,6 +4,9 @@ use std::io;
 use std::io::Write;
 use std::path::Path;
 
+#[cfg(feature = "json")]
+use crate::amount::{AmountFormat, WithAmounts};
+
 #[cfg(feature = "json")]
 pub const JSON_BLK_BUFFER: usize = 400 * 1024 * 1024;
 
@@ -15,12 +18,23 @@ pub trait Writer {
 pub struct JsonWriter<'a> {
     path: &'a Path,
     buf: Vec<u8>,
+    amounts: AmountFormat,
 }
 
 #[cfg(feature = "json")]
 impl<'a> JsonWriter<'a> {
     pub fn new(path: &'a Path) -> Self {
-        Self { path, buf: vec![] }
+        Self {
+            path,
+            buf: vec![],
+            amounts: AmountFormat::Satoshis,
+        }
+    }
+
+    // bitcoins are written as decimals with eight places, as the reference client RPC does
+    pub fn with_amounts(mut self, amounts: AmountFormat) -> Self {
+        self.amounts = amounts;
+        self
     }
 
     fn alloc_buffer(&mut self) {
@@ -37,8 +51,53 @@ impl<'a> JsonWriter<'a> {
 impl<'a> Writer for JsonWriter<'a> {
     fn save<S: Serialize, P: AsRef<Path>>(&mut self, blockchain: S, file: P) -> io::Result<()> {
         self.alloc_buffer();
-        json::to_writer(&mut self.buf, &blockchain).expect("Write to in memory buffer cannot fail");
+        json::to_writer(&mut self.buf, &WithAmounts::new(&blockchain, self.amounts))
+            .expect("Write to in memory buffer cannot fail");
         File::create(self.path.join(file))?.write_all(&self.buf)?;
         Ok(())
     }
 }
+
+// the records of the blocks visited, written every few blocks so the ones of a long chain are never all
+// kept in memory. Each file is named after the first height of its range
+#[cfg(feature = "json")]
+pub struct RecordBatches<'a, R> {
+    writer: JsonWriter<'a>,
+    name: &'static str,
+    blocks: u32,
+    batch: Option<u32>,
+    records: Vec<R>,
+}
+
+#[cfg(feature = "json")]
+impl<'a, R: Serialize> RecordBatches<'a, R> {
+    pub fn new(writer: JsonWriter<'a>, name: &'static str, blocks: u32) -> Self {
+        Self {
+            writer,
+            name,
+            blocks: blocks.max(1),
+            batch: None,
+            records: vec![],
+        }
+    }
+
+    // the heights come in order, so a record past the range of the current batch writes it
+    pub fn push(&mut self, height: u32, record: R) -> io::Result<()> {
+        let batch = height / self.blocks;
+        if self.batch.is_some_and(|current| current != batch) {
+            self.flush()?;
+        }
+        self.batch = Some(batch);
+        self.records.push(record);
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(batch) = self.batch.take() {
+            let file = format!("{}.{:08}.json", self.name, batch * self.blocks);
+            self.writer.save(&self.records, file)?;
+            self.records.clear();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+use crate::blockchain::block::{Block, BlockHeader, MiningInfo};
+use crate::blockchain::script::BitcoinScript;
+use crate::blockchain::transactions::{Input, Output, Transaction, Utxo, Witness};
+use crate::types::{BitcoinHash, BlockHash, BlockTarget, Txid, VarInt, Wtxid};
+use crate::Transaction as TransactionTrait;
+
+pub const SEGWIT_MARKER: u8 = 0x00;
+pub const SEGWIT_FLAG: u8 = 0x01;
+
+// the consensus serialization, the exact bytes that are hashed and relayed on the network
+pub trait Encodable {
+    fn encode(&self, buffer: &mut Vec<u8>);
+
+    // the legacy serialization, which is the one committed to by the txid
+    fn encode_without_witness(&self, buffer: &mut Vec<u8>) {
+        self.encode(buffer)
+    }
+
+    fn encoded(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+        self.encode(&mut buffer);
+        buffer
+    }
+
+    fn encoded_without_witness(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+        self.encode_without_witness(&mut buffer);
+        buffer
+    }
+}
+
+impl Encodable for VarInt {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.to_bytes());
+    }
+}
+
+impl Encodable for BitcoinHash {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(self.as_bytes());
+    }
+}
+
+macro_rules! encode_hash_type {
+    ($($name:ident),*) => {
+        $(impl Encodable for $name {
+            fn encode(&self, buffer: &mut Vec<u8>) {
+                buffer.extend_from_slice(self.as_bytes());
+            }
+        })*
+    };
+}
+
+encode_hash_type!(Txid, Wtxid, BlockHash);
+
+impl Encodable for BlockTarget {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Encodable for [u8] {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        VarInt::new(self.len() as u64).encode(buffer);
+        buffer.extend_from_slice(self);
+    }
+}
+
+impl Encodable for BitcoinScript {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.as_bytes().encode(buffer);
+    }
+}
+
+impl Encodable for Witness {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        VarInt::new(self.count() as u64).encode(buffer);
+        self.iter().for_each(|item| item.as_slice().encode(buffer));
+    }
+}
+
+impl Encodable for Utxo {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.txid().encode(buffer);
+        buffer.extend_from_slice(&self.vout().to_le_bytes());
+    }
+}
+
+// the witness of an input is serialized apart, after all the outputs of the transaction
+impl Encodable for Input {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.utxo().encode(buffer);
+        self.signature().encode(buffer);
+        buffer.extend_from_slice(&self.sequence().to_le_bytes());
+    }
+}
+
+impl Encodable for Output {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.value().to_sat().to_le_bytes());
+        self.pubkey().encode(buffer);
+    }
+}
+
+impl<T: Encodable> Encodable for [T] {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        VarInt::new(self.len() as u64).encode(buffer);
+        self.iter().for_each(|item| item.encode(buffer));
+    }
+
+    fn encode_without_witness(&self, buffer: &mut Vec<u8>) {
+        VarInt::new(self.len() as u64).encode(buffer);
+        self.iter().for_each(|item| item.encode_without_witness(buffer));
+    }
+}
+
+impl Encodable for Transaction {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        if !self.is_segwit() {
+            return self.encode_without_witness(buffer);
+        }
+        buffer.extend_from_slice(&self.version().to_le_bytes());
+        buffer.extend_from_slice(&[SEGWIT_MARKER, SEGWIT_FLAG]);
+        self.inputs().encode(buffer);
+        self.outputs().encode(buffer);
+        for input in self.inputs() {
+            match input.witness() {
+                Some(witness) => witness.encode(buffer),
+                None => VarInt::new(0).encode(buffer),
+            }
+        }
+        buffer.extend_from_slice(&self.locktime().to_le_bytes());
+    }
+
+    fn encode_without_witness(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.version().to_le_bytes());
+        self.inputs().encode(buffer);
+        self.outputs().encode(buffer);
+        buffer.extend_from_slice(&self.locktime().to_le_bytes());
+    }
+}
+
+impl Encodable for MiningInfo {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.time().to_le_bytes());
+        self.bits().encode(buffer);
+        buffer.extend_from_slice(&self.nonce().to_le_bytes());
+    }
+}
+
+impl Encodable for BlockHeader {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        buffer.extend_from_slice(&self.version().to_le_bytes());
+        self.previous().encode(buffer);
+        self.txroot().encode(buffer);
+        self.mining_info().encode(buffer);
+    }
+}
+
+impl<T: TransactionTrait + Encodable> Encodable for Block<T> {
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        self.header().encode(buffer);
+        self.transactions().encode(buffer);
+    }
+
+    fn encode_without_witness(&self, buffer: &mut Vec<u8>) {
+        self.header().encode(buffer);
+        self.transactions().encode_without_witness(buffer);
+    }
+}
+
+#[cfg(feature = "parser")]
+mod serial {
+    use super::Encodable;
+    use crate::blkparser::{SerialBlock, SerialTransaction, MAGIC_BYTES};
+
+    impl Encodable for SerialTransaction {
+        fn encode(&self, buffer: &mut Vec<u8>) {
+            self.transaction().encode(buffer);
+        }
+
+        fn encode_without_witness(&self, buffer: &mut Vec<u8>) {
+            self.transaction().encode_without_witness(buffer);
+        }
+    }
+
+    impl Encodable for SerialBlock {
+        fn encode(&self, buffer: &mut Vec<u8>) {
+            self.block().encode(buffer);
+        }
+
+        fn encode_without_witness(&self, buffer: &mut Vec<u8>) {
+            self.block().encode_without_witness(buffer);
+        }
+    }
+
+    impl SerialBlock {
+        // the block framed as it is stored in the blk files of the reference client
+        pub fn encode_record(&self, buffer: &mut Vec<u8>) {
+            let block = self.encoded();
+            buffer.extend_from_slice(&MAGIC_BYTES.to_be_bytes());
+            buffer.extend_from_slice(&(block.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(&block);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "parser"))]
+mod tests {
+    use super::Encodable;
+    use crate::blkparser::{decode_block, decode_transaction};
+    use crate::blockchain::block::Block;
+    use crate::blockchain::transactions::Transaction;
+    use crate::cursor::Cursor;
+    use crate::types::{BlockHash, Txid, Wtxid};
+
+    const GENESIS_BLOCK: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+    const GENESIS_HASH: &str = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+    const GENESIS_TXID: &str = "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b";
+
+    // spends a legacy input and a native witness one, so it has an empty witness next to a filled one
+    const SEGWIT_TX: &str = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0eba9b9b4f6ac3f3c6a4e2f3a0c6a7ee6201eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000";
+    const SEGWIT_TX_STRIPPED: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0eba9b9b4f6ac3f3c6a4e2f3a0c6a7ee6201eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+    const SEGWIT_TXID: &str = "e6d904c24fda7d6fd3e3b175e6c4b50d1afa0acbb8e01e9560193421142b2761";
+    const SEGWIT_WTXID: &str = "00bca5586e346b777cbe4eb5211e2fd6dc78911df12dbc3518be82bf71cb99b1";
+
+    // a header on top of the genesis block committing to its coinbase and the segwit transaction
+    const SEGWIT_HEADER: &str = "000000206fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d61900000000008909d334c77db827bcf2db0536bcbdb44846de6b9dfd641f9c2ebe0f7508babe002f6859ffff001d00000000";
+    const SEGWIT_HASH: &str = "09689f847cc17602554f09d5450cfbbad8f99dec1e5079fa553f01916af0f736";
+
+    fn bytes(data: &str) -> Vec<u8> {
+        hex::decode(data).unwrap()
+    }
+
+    fn segwit_block(transaction: &str) -> Vec<u8> {
+        let genesis = bytes(GENESIS_BLOCK);
+        [
+            bytes(SEGWIT_HEADER),
+            vec![0x02],
+            genesis[81..].to_vec(),
+            bytes(transaction),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn legacy_transaction_round_trip() {
+        let genesis = bytes(GENESIS_BLOCK);
+        let data = &genesis[81..];
+        let transaction: Transaction = decode_transaction(&mut Cursor::new(data)).unwrap();
+        assert_eq!(transaction.encoded(), data);
+        assert_eq!(transaction.encoded_without_witness(), data);
+        assert_eq!(transaction.compute_txid(), GENESIS_TXID.parse::<Txid>().unwrap());
+        assert_eq!(transaction.compute_wtxid(), GENESIS_TXID.parse::<Wtxid>().unwrap());
+    }
+
+    #[test]
+    fn segwit_transaction_round_trip() {
+        let data = bytes(SEGWIT_TX);
+        let transaction: Transaction = decode_transaction(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(transaction.encoded(), data);
+        assert_eq!(transaction.encoded_without_witness(), bytes(SEGWIT_TX_STRIPPED));
+        assert_eq!(transaction.compute_txid(), SEGWIT_TXID.parse::<Txid>().unwrap());
+        assert_eq!(transaction.compute_wtxid(), SEGWIT_WTXID.parse::<Wtxid>().unwrap());
+    }
+
+    #[test]
+    fn legacy_block_round_trip() {
+        let data = bytes(GENESIS_BLOCK);
+        let block: Block<Transaction> = decode_block(Cursor::new(&data)).unwrap();
+        assert_eq!(block.encoded(), data);
+        assert_eq!(block.encoded_without_witness(), data);
+        assert_eq!(
+            block.header().compute_hash(),
+            GENESIS_HASH.parse::<BlockHash>().unwrap()
+        );
+    }
+
+    #[test]
+    fn segwit_block_round_trip() {
+        let data = segwit_block(SEGWIT_TX);
+        let block: Block<Transaction> = decode_block(Cursor::new(&data)).unwrap();
+        assert_eq!(block.encoded(), data);
+        assert_eq!(block.encoded_without_witness(), segwit_block(SEGWIT_TX_STRIPPED));
+        assert_eq!(block.header().compute_hash(), SEGWIT_HASH.parse::<BlockHash>().unwrap());
+        let txids = block
+            .transactions()
+            .iter()
+            .map(Transaction::compute_txid)
+            .collect::<Vec<_>>();
+        assert_eq!(txids, [GENESIS_TXID.parse().unwrap(), SEGWIT_TXID.parse().unwrap()]);
+    }
+}
@@ -0,0 +1,497 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read, Write};
+
+use super::chain::ResolvedVisitor;
+use super::change::ChangeVerdict;
+use super::clusters::Exclusions;
+use super::scripts::{ResolvedTransaction, ScriptId, ScriptIndex};
+use super::{Direction, GraphError};
+use crate::amount::Amount;
+use crate::network::Network;
+use crate::types::Txid;
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+// how the edges between the same scripts are merged
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub enum Aggregation {
+    // an edge per transaction
+    Transaction,
+    // an edge per day, by the time of the blocks
+    Day,
+}
+
+// the value sent from one script to another in a transaction or a day. The value of each output is split
+// between the scripts of the inputs in proportion to what they spend
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct AddressEdge {
+    from: ScriptId,
+    to: ScriptId,
+    // the number of the transaction in the graph or the day since the epoch
+    period: u32,
+    value: Amount,
+    transactions: u32,
+}
+
+impl AddressEdge {
+    pub fn from(&self) -> ScriptId {
+        self.from
+    }
+
+    pub fn to(&self) -> ScriptId {
+        self.to
+    }
+
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    pub fn value(&self) -> Amount {
+        self.value
+    }
+
+    pub fn transactions(&self) -> u32 {
+        self.transactions
+    }
+}
+
+// an edge with the addresses of its scripts and its transaction, as it is exported
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct LabeledEdge {
+    from: String,
+    to: String,
+    period: u32,
+    // none when the edges are merged by day
+    txid: Option<Txid>,
+    value: Amount,
+    transactions: u32,
+}
+
+impl LabeledEdge {
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+
+    pub fn to(&self) -> &str {
+        &self.to
+    }
+
+    pub fn period(&self) -> u32 {
+        self.period
+    }
+
+    pub fn txid(&self) -> Option<&Txid> {
+        self.txid.as_ref()
+    }
+
+    pub fn value(&self) -> Amount {
+        self.value
+    }
+
+    pub fn transactions(&self) -> u32 {
+        self.transactions
+    }
+}
+
+// when a script was first and last used, and the value it received and spent
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct AddressStats {
+    first_seen: Seen,
+    last_seen: Seen,
+    received: Amount,
+    sent: Amount,
+    outputs: u32,
+    spent: u32,
+}
+
+impl AddressStats {
+    fn new(seen: Seen) -> Self {
+        Self {
+            first_seen: seen,
+            last_seen: seen,
+            received: Amount::from_sat(0),
+            sent: Amount::from_sat(0),
+            outputs: 0,
+            spent: 0,
+        }
+    }
+
+    // the stats are indexed by script, a script seen for the first time is numbered after the ones before it
+    pub(crate) fn record(stats: &mut Vec<Self>, transaction: &ResolvedTransaction, seen: Seen) {
+        for output in transaction.outputs() {
+            if let Some(script) = output.script() {
+                if output.is_fresh() {
+                    debug_assert_eq!(script.index(), stats.len());
+                    stats.push(Self::new(seen));
+                }
+                stats[script.index()].receive(seen, output.value());
+            }
+        }
+        for input in transaction.resolved_inputs() {
+            stats[input.script().index()].send(seen, input.value());
+        }
+    }
+
+    fn receive(&mut self, seen: Seen, value: Amount) {
+        self.last_seen = seen;
+        self.received = self.received.saturating_add(value);
+        self.outputs += 1;
+    }
+
+    fn send(&mut self, seen: Seen, value: Amount) {
+        self.last_seen = seen;
+        self.sent = self.sent.saturating_add(value);
+        self.spent += 1;
+    }
+
+    pub fn first_seen(&self) -> &Seen {
+        &self.first_seen
+    }
+
+    pub fn last_seen(&self) -> &Seen {
+        &self.last_seen
+    }
+
+    pub fn received(&self) -> Amount {
+        self.received
+    }
+
+    pub fn sent(&self) -> Amount {
+        self.sent
+    }
+
+    pub fn balance(&self) -> Amount {
+        self.received.saturating_sub(self.sent)
+    }
+
+    pub fn outputs(&self) -> u32 {
+        self.outputs
+    }
+
+    pub fn spent(&self) -> u32 {
+        self.spent
+    }
+
+    pub(crate) fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for seen in [self.first_seen, self.last_seen] {
+            writer.write_u32::<LittleEndian>(seen.height)?;
+            writer.write_u32::<LittleEndian>(seen.time)?;
+        }
+        writer.write_u64::<LittleEndian>(self.received.to_sat())?;
+        writer.write_u64::<LittleEndian>(self.sent.to_sat())?;
+        writer.write_u32::<LittleEndian>(self.outputs)?;
+        writer.write_u32::<LittleEndian>(self.spent)
+    }
+
+    pub(crate) fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let first_seen = Seen::new(reader.read_u32::<LittleEndian>()?, reader.read_u32::<LittleEndian>()?);
+        let last_seen = Seen::new(reader.read_u32::<LittleEndian>()?, reader.read_u32::<LittleEndian>()?);
+        Ok(Self {
+            first_seen,
+            last_seen,
+            received: Amount::from_sat(reader.read_u64::<LittleEndian>()?),
+            sent: Amount::from_sat(reader.read_u64::<LittleEndian>()?),
+            outputs: reader.read_u32::<LittleEndian>()?,
+            spent: reader.read_u32::<LittleEndian>()?,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct Seen {
+    height: u32,
+    time: u32,
+}
+
+impl Seen {
+    pub(crate) fn new(height: u32, time: u32) -> Self {
+        Self { height, time }
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+}
+
+// a script linked to another one, with the edges between them merged
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct Neighbour {
+    script: ScriptId,
+    value: Amount,
+    transactions: u32,
+}
+
+impl Neighbour {
+    pub fn script(&self) -> ScriptId {
+        self.script
+    }
+
+    pub fn value(&self) -> Amount {
+        self.value
+    }
+
+    pub fn transactions(&self) -> u32 {
+        self.transactions
+    }
+}
+
+// the edges of each script sorted by its number, built on the first query after a change
+struct Adjacency {
+    outgoing: Vec<usize>,
+    outgoing_offsets: Vec<usize>,
+    incoming: Vec<usize>,
+    incoming_offsets: Vec<usize>,
+}
+
+// the scripts of the outputs linked from the ones of the inputs of each transaction. The transactions left
+// apart by the exclusions add no edges, as each pair of scripts they link would take one
+pub struct AddressGraph {
+    aggregation: Aggregation,
+    exclusions: Exclusions,
+    excluded: u64,
+    stats: Vec<AddressStats>,
+    edges: Vec<AddressEdge>,
+    // the transactions of the edges when they are not merged by day
+    txids: Vec<Txid>,
+    // the edge of each pair of scripts and day
+    days: HashMap<(ScriptId, ScriptId, u32), usize>,
+    adjacency: OnceCell<Adjacency>,
+}
+
+impl AddressGraph {
+    pub fn new(aggregation: Aggregation, exclusions: Exclusions) -> Self {
+        Self {
+            aggregation,
+            exclusions,
+            excluded: 0,
+            stats: Vec::new(),
+            edges: Vec::new(),
+            txids: Vec::new(),
+            days: HashMap::new(),
+            adjacency: OnceCell::new(),
+        }
+    }
+
+    pub fn aggregation(&self) -> Aggregation {
+        self.aggregation
+    }
+
+    pub fn exclusions(&self) -> &Exclusions {
+        &self.exclusions
+    }
+
+    // the transactions left apart by the exclusions
+    pub fn excluded(&self) -> u64 {
+        self.excluded
+    }
+
+    pub fn len(&self) -> usize {
+        self.stats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stats.is_empty()
+    }
+
+    pub fn edges(&self) -> &[AddressEdge] {
+        &self.edges
+    }
+
+    // the transaction of an edge, only known when the edges are not merged by day
+    pub fn transaction(&self, edge: &AddressEdge) -> Option<&Txid> {
+        match self.aggregation {
+            Aggregation::Transaction => self.txids.get(edge.period as usize),
+            Aggregation::Day => None,
+        }
+    }
+
+    pub fn labeled(&self, edge: &AddressEdge, scripts: &ScriptIndex, network: Network) -> LabeledEdge {
+        LabeledEdge {
+            from: scripts.label(edge.from, network),
+            to: scripts.label(edge.to, network),
+            period: edge.period,
+            txid: self.transaction(edge).copied(),
+            value: edge.value,
+            transactions: edge.transactions,
+        }
+    }
+
+    pub fn stats(&self, script: ScriptId) -> &AddressStats {
+        &self.stats[script.index()]
+    }
+
+    pub fn first_seen(&self, script: ScriptId) -> &Seen {
+        self.stats(script).first_seen()
+    }
+
+    pub fn last_seen(&self, script: ScriptId) -> &Seen {
+        self.stats(script).last_seen()
+    }
+
+    // the value of the outputs paying to the script
+    pub fn inflow(&self, script: ScriptId) -> Amount {
+        self.stats(script).received()
+    }
+
+    // the value of the outputs of the script spent
+    pub fn outflow(&self, script: ScriptId) -> Amount {
+        self.stats(script).sent()
+    }
+
+    // the edges leaving the script, or reaching it when going backward
+    pub fn edges_of(&self, script: ScriptId, direction: Direction) -> impl Iterator<Item = &AddressEdge> {
+        let adjacency = self.adjacency();
+        let (edges, offsets) = match direction {
+            Direction::Forward => (&adjacency.outgoing, &adjacency.outgoing_offsets),
+            Direction::Backward => (&adjacency.incoming, &adjacency.incoming_offsets),
+        };
+        edges[offsets[script.index()]..offsets[script.index() + 1]]
+            .iter()
+            .map(move |&edge| &self.edges[edge])
+    }
+
+    // the scripts linked to the given one, the ones with the most value first
+    pub fn neighbours(&self, script: ScriptId, direction: Direction) -> Vec<Neighbour> {
+        let mut neighbours = BTreeMap::<ScriptId, Neighbour>::new();
+        for edge in self.edges_of(script, direction) {
+            let other = match direction {
+                Direction::Forward => edge.to,
+                Direction::Backward => edge.from,
+            };
+            let neighbour = neighbours.entry(other).or_insert(Neighbour {
+                script: other,
+                value: Amount::from_sat(0),
+                transactions: 0,
+            });
+            neighbour.value = neighbour.value.saturating_add(edge.value);
+            neighbour.transactions += edge.transactions;
+        }
+        let mut neighbours = neighbours.into_values().collect::<Vec<_>>();
+        neighbours.sort_by_key(|neighbour| Reverse(neighbour.value));
+        neighbours
+    }
+
+    fn adjacency(&self) -> &Adjacency {
+        self.adjacency.get_or_init(|| {
+            let (outgoing, outgoing_offsets) = self.sort_edges(|edge| edge.from);
+            let (incoming, incoming_offsets) = self.sort_edges(|edge| edge.to);
+            Adjacency {
+                outgoing,
+                outgoing_offsets,
+                incoming,
+                incoming_offsets,
+            }
+        })
+    }
+
+    // the edges ordered by one of their scripts, with the offset of the edges of each script
+    fn sort_edges(&self, key: impl Fn(&AddressEdge) -> ScriptId) -> (Vec<usize>, Vec<usize>) {
+        let mut offsets = vec![0; self.stats.len() + 1];
+        for edge in &self.edges {
+            offsets[key(edge).index() + 1] += 1;
+        }
+        for script in 0..self.stats.len() {
+            offsets[script + 1] += offsets[script];
+        }
+        let mut positions = offsets.clone();
+        let mut sorted = vec![0; self.edges.len()];
+        for (index, edge) in self.edges.iter().enumerate() {
+            let position = &mut positions[key(edge).index()];
+            sorted[*position] = index;
+            *position += 1;
+        }
+        (sorted, offsets)
+    }
+
+    fn add_resolved(&mut self, transaction: &ResolvedTransaction, seen: Seen) {
+        AddressStats::record(&mut self.stats, transaction, seen);
+        if self.exclusions.excludes(transaction) {
+            self.excluded += 1;
+            return;
+        }
+        let weights = Self::split_value(transaction);
+        if weights.is_empty() {
+            return;
+        }
+        let period = match self.aggregation {
+            Aggregation::Transaction => {
+                self.txids.push(*transaction.txid());
+                self.txids.len() as u32 - 1
+            }
+            Aggregation::Day => seen.time / SECONDS_PER_DAY,
+        };
+        for ((from, to), value) in weights {
+            let edge = AddressEdge {
+                from,
+                to,
+                period,
+                value: Amount::from_sat(value),
+                transactions: 1,
+            };
+            match self.aggregation {
+                Aggregation::Transaction => self.edges.push(edge),
+                Aggregation::Day => match self.days.get(&(from, to, period)) {
+                    Some(&index) => {
+                        let merged = &mut self.edges[index];
+                        merged.value = merged.value.saturating_add(edge.value);
+                        merged.transactions += 1;
+                    }
+                    None => {
+                        self.days.insert((from, to, period), self.edges.len());
+                        self.edges.push(edge);
+                    }
+                },
+            }
+        }
+    }
+
+    // the value each pair of scripts moves in the transaction, the inputs that could not be resolved are
+    // left out
+    fn split_value(transaction: &ResolvedTransaction) -> BTreeMap<(ScriptId, ScriptId), u64> {
+        let mut weights = BTreeMap::new();
+        let total = transaction
+            .resolved_inputs()
+            .map(|input| input.value().to_sat() as u128)
+            .sum::<u128>();
+        for input in transaction.resolved_inputs() {
+            for output in transaction.outputs() {
+                if let Some(to) = output.script() {
+                    let value = match total {
+                        0 => 0,
+                        total => output.value().to_sat() as u128 * input.value().to_sat() as u128 / total,
+                    };
+                    *weights.entry((input.script(), to)).or_insert(0) += value as u64;
+                }
+            }
+        }
+        weights
+    }
+}
+
+impl ResolvedVisitor for AddressGraph {
+    fn on_transaction(
+        &mut self,
+        seen: Seen,
+        transaction: &ResolvedTransaction,
+        _verdict: Option<&ChangeVerdict>,
+    ) -> Result<(), GraphError> {
+        self.add_resolved(transaction, seen);
+        self.adjacency.take();
+        Ok(())
+    }
+}
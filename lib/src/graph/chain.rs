@@ -0,0 +1,208 @@
+use std::io::{Read, Write};
+
+use super::addresses::Seen;
+use super::change::{ChangeDetector, ChangeVerdict};
+use super::scripts::{ResolvedTransaction, ScriptIndex, ScriptResolver};
+use super::{GraphError, HeightWindow};
+#[cfg(feature = "parser")]
+use crate::blkparser::SerialBlock;
+use crate::blockchain::transactions::Transaction;
+use crate::types::{BlockHash, Txid};
+use crate::utxo::{MemoryStore, UtxoStore};
+#[cfg(feature = "parser")]
+use crate::visitor::ChainVisitor;
+
+// what is built from the transactions of the chain once the outputs their inputs spend are known. The
+// scripts are numbered by the chain, so a graph must take part in every pass from the first one
+pub trait ResolvedVisitor {
+    // the verdict is only given when the chain detects the change and the transaction gets one
+    fn on_transaction(
+        &mut self,
+        seen: Seen,
+        transaction: &ResolvedTransaction,
+        verdict: Option<&ChangeVerdict>,
+    ) -> Result<(), GraphError>;
+}
+
+impl<V: ResolvedVisitor + ?Sized> ResolvedVisitor for &mut V {
+    fn on_transaction(
+        &mut self,
+        seen: Seen,
+        transaction: &ResolvedTransaction,
+        verdict: Option<&ChangeVerdict>,
+    ) -> Result<(), GraphError> {
+        (**self).on_transaction(seen, transaction, verdict)
+    }
+}
+
+// resolves the transactions of the blocks within the window, and detects their change when asked. Each
+// transaction is resolved once and handed to every graph of the pass
+pub struct ResolvedChain<S: UtxoStore = MemoryStore> {
+    resolver: ScriptResolver<S>,
+    detector: Option<ChangeDetector>,
+    window: HeightWindow,
+    error: Option<GraphError>,
+}
+
+impl ResolvedChain<MemoryStore> {
+    pub fn new() -> Self {
+        Self::with_resolver(ScriptResolver::new())
+    }
+}
+
+impl<S: UtxoStore> ResolvedChain<S> {
+    // resumes after the last block the resolver was given
+    pub fn with_resolver(resolver: ScriptResolver<S>) -> Self {
+        Self {
+            window: HeightWindow::resume(resolver.tip()),
+            resolver,
+            detector: None,
+            error: None,
+        }
+    }
+
+    // the store must be the one the chain written was resolved with
+    pub fn read<R: Read>(reader: R, unspent: S) -> Result<Self, GraphError> {
+        let (scripts, tip) = ScriptIndex::read(reader)?;
+        let resolver = ScriptResolver::with_store(scripts, unspent);
+        if resolver.tip() != tip {
+            return Err(GraphError::Unspent);
+        }
+        Ok(Self::with_resolver(resolver))
+    }
+
+    // the numbers of the scripts and the last block, the unspent outputs are kept by the store
+    pub fn write<W: Write>(&self, writer: W) -> Result<W, GraphError> {
+        self.resolver.scripts().write(self.tip(), writer)
+    }
+
+    pub fn start_at(mut self, height: u32) -> Self {
+        self.window = self.window.start_at(height);
+        self
+    }
+
+    pub fn stop_at(mut self, height: u32) -> Self {
+        self.window = self.window.stop_at(height);
+        self
+    }
+
+    pub fn detect_change(mut self, detector: ChangeDetector) -> Self {
+        self.detector = Some(detector);
+        self
+    }
+
+    pub fn scripts(&self) -> &ScriptIndex {
+        self.resolver.scripts()
+    }
+
+    pub fn resolver(&self) -> &ScriptResolver<S> {
+        &self.resolver
+    }
+
+    pub fn detector(&self) -> Option<&ChangeDetector> {
+        self.detector.as_ref()
+    }
+
+    // the height of the last block added
+    pub fn tip(&self) -> Option<u32> {
+        self.window.tip()
+    }
+
+    // the graphs visiting the chain along with it
+    pub fn pass<'v>(&mut self) -> ResolvedPass<'_, 'v, S> {
+        ResolvedPass {
+            chain: self,
+            visitors: Vec::new(),
+        }
+    }
+
+    pub fn add_transactions<'a>(
+        &mut self,
+        height: u32,
+        hash: BlockHash,
+        time: u32,
+        transactions: impl IntoIterator<Item = (Txid, &'a Transaction)>,
+        visitors: &mut [&mut dyn ResolvedVisitor],
+    ) -> Result<(), GraphError> {
+        self.window.check(height)?;
+        let seen = Seen::new(height, time);
+        for (txid, transaction) in transactions {
+            let resolved = self.resolver.resolve(height, txid, transaction)?;
+            let verdict = self
+                .detector
+                .as_ref()
+                .and_then(|detector| detector.detect(&resolved, self.resolver.scripts()));
+            for visitor in visitors.iter_mut() {
+                visitor.on_transaction(seen, &resolved, verdict.as_ref())?;
+            }
+        }
+        self.resolver.finish_block(height, hash)?;
+        self.window.advance(height);
+        Ok(())
+    }
+
+    #[cfg(feature = "parser")]
+    pub fn add_block(
+        &mut self,
+        height: u32,
+        block: &SerialBlock,
+        visitors: &mut [&mut dyn ResolvedVisitor],
+    ) -> Result<(), GraphError> {
+        let transactions = block
+            .transactions()
+            .iter()
+            .map(|transaction| (*transaction.hash(), transaction.transaction()));
+        self.add_transactions(height, *block.hash(), block.header().time(), transactions, visitors)
+    }
+
+    // writes the unspent outputs the store still holds in memory
+    pub fn flush(&mut self) -> Result<(), GraphError> {
+        self.resolver.flush()
+    }
+
+    // the error that stopped the chain while visiting it, if any
+    pub fn take_error(&mut self) -> Option<GraphError> {
+        self.error.take()
+    }
+}
+
+impl Default for ResolvedChain<MemoryStore> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// the graphs built in a single pass over the chain
+pub struct ResolvedPass<'c, 'v, S: UtxoStore = MemoryStore> {
+    chain: &'c mut ResolvedChain<S>,
+    visitors: Vec<&'v mut dyn ResolvedVisitor>,
+}
+
+impl<'v, S: UtxoStore> ResolvedPass<'_, 'v, S> {
+    pub fn with<V: ResolvedVisitor>(mut self, visitor: &'v mut V) -> Self {
+        self.visitors.push(visitor);
+        self
+    }
+}
+
+#[cfg(feature = "parser")]
+impl<S: UtxoStore> ChainVisitor for ResolvedPass<'_, '_, S> {
+    fn anchor(&self) -> Option<(u32, BlockHash)> {
+        self.chain.resolver.tip_block()
+    }
+
+    fn on_block(&mut self, height: u32, block: &SerialBlock) {
+        if self.chain.error.is_some() || self.chain.window.skips(height) {
+            return;
+        }
+        if let Err(err) = self.chain.add_block(height, block, &mut self.visitors) {
+            self.chain.error = Some(err);
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Err(err) = self.chain.flush() {
+            self.chain.error.get_or_insert(err);
+        }
+    }
+}
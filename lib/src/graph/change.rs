@@ -0,0 +1,408 @@
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use super::addresses::Seen;
+use super::chain::ResolvedVisitor;
+use super::scripts::{ResolvedOutput, ResolvedTransaction, ScriptIndex};
+use super::GraphError;
+use crate::amount::Amount;
+use crate::types::Txid;
+
+// the value the payments made by hand are usually a multiple of
+const ROUND_UNIT: Amount = Amount::from_sat(100_000);
+
+// a way of telling which output of a transaction returns the rest of the inputs to the sender
+pub trait ChangeHeuristic {
+    fn name(&self) -> &'static str;
+
+    // the confidence, from zero to one, of each output being the change, none when the heuristic tells
+    // nothing about the transaction
+    fn scores(&self, transaction: &ResolvedTransaction, scripts: &ScriptIndex) -> Option<Vec<f64>>;
+}
+
+// the change goes back to the script of one of the inputs
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AddressReuse;
+
+impl ChangeHeuristic for AddressReuse {
+    fn name(&self) -> &'static str {
+        "address-reuse"
+    }
+
+    fn scores(&self, transaction: &ResolvedTransaction, _: &ScriptIndex) -> Option<Vec<f64>> {
+        candidates(transaction, |output| {
+            transaction
+                .resolved_inputs()
+                .any(|input| Some(input.script()) == output.script())
+        })
+    }
+}
+
+// the change has the type of script of the inputs when all of them share it
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ScriptTypeMatch;
+
+impl ChangeHeuristic for ScriptTypeMatch {
+    fn name(&self) -> &'static str {
+        "script-type"
+    }
+
+    fn scores(&self, transaction: &ResolvedTransaction, scripts: &ScriptIndex) -> Option<Vec<f64>> {
+        let mut types = transaction
+            .resolved_inputs()
+            .map(|input| scripts.script_type(input.script()));
+        let first = types.next()?;
+        if !types.all(|script_type| script_type == first) {
+            return None;
+        }
+        candidates(transaction, |output| {
+            output.script().map(|script| scripts.script_type(script)) == Some(first)
+        })
+    }
+}
+
+// the payments are round values, the change is what is left
+#[derive(Copy, Clone, Debug)]
+pub struct RoundNumber {
+    unit: Amount,
+}
+
+impl RoundNumber {
+    // the payments are taken as the outputs whose value is a multiple of the unit
+    pub fn new(unit: Amount) -> Self {
+        Self { unit }
+    }
+
+    pub fn unit(&self) -> Amount {
+        self.unit
+    }
+}
+
+impl Default for RoundNumber {
+    fn default() -> Self {
+        Self::new(ROUND_UNIT)
+    }
+}
+
+impl ChangeHeuristic for RoundNumber {
+    fn name(&self) -> &'static str {
+        "round-number"
+    }
+
+    fn scores(&self, transaction: &ResolvedTransaction, _: &ScriptIndex) -> Option<Vec<f64>> {
+        let unit = self.unit.to_sat().max(1);
+        candidates(transaction, |output| output.value().to_sat() % unit != 0)
+    }
+}
+
+// a wallet does not spend more inputs than the payment needs, so an output that could be paid without the
+// smallest input is not the payment
+#[derive(Copy, Clone, Debug, Default)]
+pub struct UnnecessaryInput;
+
+impl ChangeHeuristic for UnnecessaryInput {
+    fn name(&self) -> &'static str {
+        "unnecessary-input"
+    }
+
+    fn scores(&self, transaction: &ResolvedTransaction, _: &ScriptIndex) -> Option<Vec<f64>> {
+        if transaction.inputs().len() < 2 {
+            return None;
+        }
+        let smallest = transaction.resolved_inputs().map(|input| input.value()).min()?;
+        let fee = transaction.input_value()?.checked_sub(transaction.output_value()?)?;
+        let available = transaction.input_value()?.saturating_sub(smallest).saturating_sub(fee);
+        candidates(transaction, |output| output.value() <= available)
+    }
+}
+
+// the change goes to a script never seen before, while the payments go to known ones
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FreshAddress;
+
+impl ChangeHeuristic for FreshAddress {
+    fn name(&self) -> &'static str {
+        "fresh-address"
+    }
+
+    fn scores(&self, transaction: &ResolvedTransaction, _: &ScriptIndex) -> Option<Vec<f64>> {
+        candidates(transaction, ResolvedOutput::is_fresh)
+    }
+}
+
+// the change is smaller than every input, otherwise one of them would not have been needed
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OptimalChange;
+
+impl ChangeHeuristic for OptimalChange {
+    fn name(&self) -> &'static str {
+        "optimal-change"
+    }
+
+    fn scores(&self, transaction: &ResolvedTransaction, _: &ScriptIndex) -> Option<Vec<f64>> {
+        let smallest = transaction.resolved_inputs().map(|input| input.value()).min()?;
+        candidates(transaction, |output| output.value() < smallest)
+    }
+}
+
+// splits the confidence between the spendable outputs matching the predicate, none when all or none of them
+// match
+fn candidates(transaction: &ResolvedTransaction, matches: impl Fn(&ResolvedOutput) -> bool) -> Option<Vec<f64>> {
+    let spendable = transaction
+        .outputs()
+        .iter()
+        .filter(|output| output.script().is_some())
+        .count();
+    let matching = transaction
+        .outputs()
+        .iter()
+        .map(|output| output.script().is_some() && matches(output))
+        .collect::<Vec<_>>();
+    let count = matching.iter().filter(|&&matching| matching).count();
+    if count == 0 || count == spendable {
+        return None;
+    }
+    Some(
+        matching
+            .into_iter()
+            .map(|matching| if matching { 1.0 / count as f64 } else { 0.0 })
+            .collect(),
+    )
+}
+
+// the scores given by one of the heuristics to the outputs of a transaction
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct HeuristicScores {
+    heuristic: Cow<'static, str>,
+    scores: Vec<f64>,
+}
+
+impl HeuristicScores {
+    pub fn heuristic(&self) -> &str {
+        &self.heuristic
+    }
+
+    pub fn scores(&self) -> &[f64] {
+        &self.scores
+    }
+}
+
+// the output taken as the change of a transaction, if any, with the scores it was chosen by
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct ChangeVerdict {
+    txid: Txid,
+    change: Option<u32>,
+    // the weighted mean of the scores of the heuristics telling something about the transaction
+    scores: Vec<f64>,
+    heuristics: Vec<HeuristicScores>,
+}
+
+impl ChangeVerdict {
+    pub fn txid(&self) -> &Txid {
+        &self.txid
+    }
+
+    pub fn change(&self) -> Option<u32> {
+        self.change
+    }
+
+    pub fn scores(&self) -> &[f64] {
+        &self.scores
+    }
+
+    pub fn heuristics(&self) -> &[HeuristicScores] {
+        &self.heuristics
+    }
+}
+
+// combines the scores of a set of weighted heuristics, the output with the highest combined score being the
+// change when it reaches the threshold
+pub struct ChangeDetector {
+    heuristics: Vec<(Box<dyn ChangeHeuristic>, f64)>,
+    threshold: f64,
+}
+
+impl ChangeDetector {
+    // a detector without heuristics, which never finds the change until some are added
+    pub fn new() -> Self {
+        Self {
+            heuristics: Vec::new(),
+            threshold: 0.5,
+        }
+    }
+
+    pub fn with(mut self, heuristic: impl ChangeHeuristic + 'static, weight: f64) -> Self {
+        self.heuristics.push((Box::new(heuristic), weight));
+        self
+    }
+
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.heuristics.iter().map(|(heuristic, _)| heuristic.name())
+    }
+
+    // the verdict on a transaction spending only known outputs and paying to several spendable ones, none for
+    // the rest
+    pub fn detect(&self, transaction: &ResolvedTransaction, scripts: &ScriptIndex) -> Option<ChangeVerdict> {
+        let spendable = transaction
+            .outputs()
+            .iter()
+            .filter(|output| output.script().is_some())
+            .count();
+        if transaction.is_coinbase() || !transaction.is_fully_resolved() || spendable < 2 {
+            return None;
+        }
+        let mut combined = vec![0.0; transaction.outputs().len()];
+        let mut weights = 0.0;
+        let mut heuristics = Vec::new();
+        for (heuristic, weight) in &self.heuristics {
+            if let Some(scores) = heuristic.scores(transaction, scripts) {
+                for (combined, score) in combined.iter_mut().zip(&scores) {
+                    *combined += weight * score;
+                }
+                weights += weight;
+                heuristics.push(HeuristicScores {
+                    heuristic: Cow::Borrowed(heuristic.name()),
+                    scores,
+                });
+            }
+        }
+        if weights > 0.0 {
+            combined.iter_mut().for_each(|score| *score /= weights);
+        }
+        // the best output must reach the threshold and beat every other one
+        let mut best: Option<usize> = None;
+        let mut tied = false;
+        for (vout, &score) in combined.iter().enumerate() {
+            match best {
+                Some(index) if score == combined[index] => tied = true,
+                Some(index) if score < combined[index] => (),
+                _ => {
+                    best = Some(vout);
+                    tied = false;
+                }
+            }
+        }
+        let change = best
+            .filter(|&vout| !tied && combined[vout] > 0.0 && combined[vout] >= self.threshold)
+            .map(|vout| vout as u32);
+        Some(ChangeVerdict {
+            txid: *transaction.txid(),
+            change,
+            scores: combined,
+            heuristics,
+        })
+    }
+}
+
+// every heuristic with the same weight
+impl Default for ChangeDetector {
+    fn default() -> Self {
+        Self::new()
+            .with(AddressReuse, 1.0)
+            .with(ScriptTypeMatch, 1.0)
+            .with(RoundNumber::default(), 1.0)
+            .with(UnnecessaryInput, 1.0)
+            .with(FreshAddress, 1.0)
+            .with(OptimalChange, 1.0)
+    }
+}
+
+// the transactions a heuristic tells something about, and how many times its best output is the change
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct HeuristicCounts {
+    applied: u64,
+    agreeing: u64,
+}
+
+impl HeuristicCounts {
+    pub fn applied(&self) -> u64 {
+        self.applied
+    }
+
+    pub fn agreeing(&self) -> u64 {
+        self.agreeing
+    }
+}
+
+// counts the verdicts of the change detected by the chain as they come, handing each one to the sink
+// instead of keeping them
+pub struct ChangeAnalysis<F> {
+    sink: F,
+    transactions: u64,
+    changes: u64,
+    heuristics: BTreeMap<Cow<'static, str>, HeuristicCounts>,
+}
+
+impl<F: FnMut(Seen, &ChangeVerdict) -> Result<(), GraphError>> ChangeAnalysis<F> {
+    pub fn new(sink: F) -> Self {
+        Self {
+            sink,
+            transactions: 0,
+            changes: 0,
+            heuristics: BTreeMap::new(),
+        }
+    }
+
+    // the transactions given a verdict
+    pub fn transactions(&self) -> u64 {
+        self.transactions
+    }
+
+    pub fn changes(&self) -> u64 {
+        self.changes
+    }
+
+    // the heuristics that told something about any transaction, by name
+    pub fn heuristics(&self) -> impl Iterator<Item = (&str, &HeuristicCounts)> {
+        self.heuristics
+            .iter()
+            .map(|(heuristic, counts)| (heuristic.as_ref(), counts))
+    }
+
+    pub fn counts(&self, heuristic: &str) -> HeuristicCounts {
+        self.heuristics.get(heuristic).copied().unwrap_or_default()
+    }
+
+    fn add_verdict(&mut self, verdict: &ChangeVerdict) {
+        self.transactions += 1;
+        self.changes += verdict.change.is_some() as u64;
+        for heuristic in &verdict.heuristics {
+            let counts = self.heuristics.entry(heuristic.heuristic.clone()).or_default();
+            counts.applied += 1;
+            let scores = &heuristic.scores;
+            if verdict
+                .change
+                .is_some_and(|vout| scores.iter().all(|&score| score <= scores[vout as usize]))
+            {
+                counts.agreeing += 1;
+            }
+        }
+    }
+}
+
+impl<F: FnMut(Seen, &ChangeVerdict) -> Result<(), GraphError>> ResolvedVisitor for ChangeAnalysis<F> {
+    fn on_transaction(
+        &mut self,
+        seen: Seen,
+        _transaction: &ResolvedTransaction,
+        verdict: Option<&ChangeVerdict>,
+    ) -> Result<(), GraphError> {
+        match verdict {
+            Some(verdict) => {
+                self.add_verdict(verdict);
+                (self.sink)(seen, verdict)
+            }
+            None => Ok(()),
+        }
+    }
+}
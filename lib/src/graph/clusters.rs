@@ -0,0 +1,454 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+use super::addresses::{AddressStats, Seen};
+use super::chain::ResolvedVisitor;
+use super::change::ChangeVerdict;
+use super::scripts::{ResolvedTransaction, ScriptId};
+use super::GraphError;
+use crate::amount::Amount;
+
+const SETS_MAGIC: [u8; 8] = *b"unionfnd";
+const SETS_VERSION: u16 = 1;
+const CLUSTERS_MAGIC: [u8; 8] = *b"clusters";
+const CLUSTERS_VERSION: u16 = 1;
+
+// the disjoint sets of the scripts, kept in two flat arrays indexed by the script number so they take five
+// bytes a script and are written to disk as they are
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnionFind {
+    parents: Vec<u32>,
+    ranks: Vec<u8>,
+}
+
+impl UnionFind {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    // adds the scripts up to the given number, each one alone in its set
+    pub fn grow(&mut self, len: usize) -> Result<(), GraphError> {
+        u32::try_from(len).map_err(|_| GraphError::Full)?;
+        for script in self.parents.len()..len {
+            self.parents.push(script as u32);
+            self.ranks.push(0);
+        }
+        Ok(())
+    }
+
+    // the script representing the set, halving the path to it on the way
+    pub fn find(&mut self, script: ScriptId) -> ScriptId {
+        let mut current = script.index();
+        while self.parents[current] as usize != current {
+            let parent = self.parents[current] as usize;
+            self.parents[current] = self.parents[parent];
+            current = self.parents[current] as usize;
+        }
+        ScriptId::new(current as u32)
+    }
+
+    // the script representing the set, without changing the paths
+    pub fn root(&self, script: ScriptId) -> ScriptId {
+        let mut current = script.index();
+        while self.parents[current] as usize != current {
+            current = self.parents[current] as usize;
+        }
+        ScriptId::new(current as u32)
+    }
+
+    // joins the sets of both scripts, returns whether they were apart
+    pub fn union(&mut self, first: ScriptId, second: ScriptId) -> bool {
+        let (first, second) = (self.find(first).index(), self.find(second).index());
+        if first == second {
+            return false;
+        }
+        let (parent, child) = match self.ranks[first] < self.ranks[second] {
+            true => (second, first),
+            false => (first, second),
+        };
+        self.parents[child] = parent as u32;
+        if self.ranks[parent] == self.ranks[child] {
+            self.ranks[parent] += 1;
+        }
+        true
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<W, GraphError> {
+        writer.write_all(&SETS_MAGIC)?;
+        writer.write_u16::<LittleEndian>(SETS_VERSION)?;
+        writer.write_u64::<LittleEndian>(self.parents.len() as u64)?;
+        for &parent in &self.parents {
+            writer.write_u32::<LittleEndian>(parent)?;
+        }
+        writer.write_all(&self.ranks)?;
+        writer.flush()?;
+        Ok(writer)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, GraphError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != SETS_MAGIC {
+            return Err(GraphError::Magic);
+        }
+        let version = reader.read_u16::<LittleEndian>()?;
+        if version != SETS_VERSION {
+            return Err(GraphError::Version(version));
+        }
+        let len = u32::try_from(reader.read_u64::<LittleEndian>()?).map_err(|_| GraphError::Corrupted)? as usize;
+        let mut parents = Vec::with_capacity(len);
+        for _ in 0..len {
+            let parent = reader.read_u32::<LittleEndian>()?;
+            if parent as usize >= len {
+                return Err(GraphError::Corrupted);
+            }
+            parents.push(parent);
+        }
+        let mut ranks = vec![0; len];
+        reader.read_exact(&mut ranks)?;
+        Ok(Self { parents, ranks })
+    }
+}
+
+// the transactions whose inputs are not assumed to belong to the same owner
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct Exclusions {
+    coinjoin_outputs: Option<usize>,
+    max_inputs: Option<usize>,
+    max_pairs: Option<usize>,
+}
+
+impl Exclusions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // skips the transactions looking like a coinjoin, the ones paying the same value to at least the given
+    // number of outputs and spending at least as many inputs
+    pub fn skip_coinjoins(mut self, outputs: usize) -> Self {
+        self.coinjoin_outputs = Some(outputs.max(2));
+        self
+    }
+
+    // skips the transactions spending more inputs than the given number
+    pub fn max_inputs(mut self, inputs: usize) -> Self {
+        self.max_inputs = Some(inputs);
+        self
+    }
+
+    // skips the transactions linking more pairs of input and output scripts than the given number, the
+    // consolidations and coinjoins that would add an edge for each of them
+    pub fn max_pairs(mut self, pairs: usize) -> Self {
+        self.max_pairs = Some(pairs);
+        self
+    }
+
+    pub fn excludes(&self, transaction: &ResolvedTransaction) -> bool {
+        let inputs = transaction.inputs().len();
+        let coinjoin = self
+            .coinjoin_outputs
+            .is_some_and(|outputs| inputs >= outputs && equal_outputs(transaction) >= outputs);
+        coinjoin
+            || self.max_inputs.is_some_and(|max| inputs > max)
+            || self.max_pairs.is_some_and(|max| script_pairs(transaction) > max)
+    }
+}
+
+// the number of pairs of distinct scripts of the resolved inputs and the outputs of the transaction
+pub fn script_pairs(transaction: &ResolvedTransaction) -> usize {
+    let inputs = transaction
+        .resolved_inputs()
+        .map(|input| input.script())
+        .collect::<HashSet<_>>();
+    let outputs = transaction
+        .outputs()
+        .iter()
+        .filter_map(|output| output.script())
+        .collect::<HashSet<_>>();
+    inputs.len().saturating_mul(outputs.len())
+}
+
+// the largest number of spendable outputs of the transaction paying the same value
+pub fn equal_outputs(transaction: &ResolvedTransaction) -> usize {
+    let mut counts = HashMap::<Amount, usize>::new();
+    for output in transaction.outputs().iter().filter(|output| output.script().is_some()) {
+        *counts.entry(output.value()).or_insert(0) += 1;
+    }
+    counts.into_values().max().unwrap_or(0)
+}
+
+// the number of a cluster, given in the order their first script appears in the chain
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct ClusterId(u32);
+
+impl ClusterId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct ClusterStats {
+    size: u32,
+    first_seen: Seen,
+    received: Amount,
+    sent: Amount,
+}
+
+impl ClusterStats {
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn first_seen(&self) -> &Seen {
+        &self.first_seen
+    }
+
+    pub fn received(&self) -> Amount {
+        self.received
+    }
+
+    pub fn sent(&self) -> Amount {
+        self.sent
+    }
+
+    pub fn balance(&self) -> Amount {
+        self.received.saturating_sub(self.sent)
+    }
+}
+
+// the cluster of each script and the stats of each cluster at the time they are taken
+pub struct Clusters {
+    membership: Vec<ClusterId>,
+    stats: Vec<ClusterStats>,
+}
+
+impl Clusters {
+    pub fn len(&self) -> usize {
+        self.stats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stats.is_empty()
+    }
+
+    pub fn cluster_of(&self, script: ScriptId) -> ClusterId {
+        self.membership[script.index()]
+    }
+
+    pub fn stats(&self, cluster: ClusterId) -> &ClusterStats {
+        &self.stats[cluster.index()]
+    }
+
+    pub fn membership(&self) -> &[ClusterId] {
+        &self.membership
+    }
+
+    pub fn members(&self, cluster: ClusterId) -> impl Iterator<Item = ScriptId> + '_ {
+        self.membership
+            .iter()
+            .enumerate()
+            .filter(move |&(_, &member)| member == cluster)
+            .map(|(script, _)| ScriptId::new(script as u32))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ClusterId, &ClusterStats)> {
+        self.stats
+            .iter()
+            .enumerate()
+            .map(|(cluster, stats)| (ClusterId(cluster as u32), stats))
+    }
+}
+
+// groups the scripts assuming the inputs of a transaction belong to the same owner
+pub struct AddressClusters {
+    exclusions: Exclusions,
+    join_change: bool,
+    sets: UnionFind,
+    stats: Vec<AddressStats>,
+    excluded: u64,
+    changes: u64,
+}
+
+impl AddressClusters {
+    pub fn new(exclusions: Exclusions) -> Self {
+        Self {
+            exclusions,
+            join_change: false,
+            sets: UnionFind::new(),
+            stats: Vec::new(),
+            excluded: 0,
+            changes: 0,
+        }
+    }
+
+    // also joins the change found by the chain to the inputs of the transactions not excluded
+    pub fn join_change(mut self) -> Self {
+        self.join_change = true;
+        self
+    }
+
+    pub fn exclusions(&self) -> &Exclusions {
+        &self.exclusions
+    }
+
+    pub fn sets(&self) -> &UnionFind {
+        &self.sets
+    }
+
+    pub fn len(&self) -> usize {
+        self.stats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stats.is_empty()
+    }
+
+    pub fn stats(&self, script: ScriptId) -> &AddressStats {
+        &self.stats[script.index()]
+    }
+
+    // the transactions left apart by the exclusions
+    pub fn excluded(&self) -> u64 {
+        self.excluded
+    }
+
+    // the change outputs joined to the inputs of their transaction
+    pub fn changes(&self) -> u64 {
+        self.changes
+    }
+
+    // joins the clusters of both scripts, returns whether they were apart
+    pub fn join(&mut self, first: ScriptId, second: ScriptId) -> bool {
+        self.sets.union(first, second)
+    }
+
+    // the stats of the scripts and their sets, so the clusters can be extended after they are read. The
+    // exclusions are not written, they are given again when reading them
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<W, GraphError> {
+        writer.write_all(&CLUSTERS_MAGIC)?;
+        writer.write_u16::<LittleEndian>(CLUSTERS_VERSION)?;
+        writer.write_u64::<LittleEndian>(self.excluded)?;
+        writer.write_u64::<LittleEndian>(self.changes)?;
+        writer.write_u64::<LittleEndian>(self.stats.len() as u64)?;
+        for stats in &self.stats {
+            stats.write(&mut writer)?;
+        }
+        self.sets.write(writer)
+    }
+
+    pub fn read<R: Read>(exclusions: Exclusions, mut reader: R) -> Result<Self, GraphError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != CLUSTERS_MAGIC {
+            return Err(GraphError::Magic);
+        }
+        let version = reader.read_u16::<LittleEndian>()?;
+        if version != CLUSTERS_VERSION {
+            return Err(GraphError::Version(version));
+        }
+        let excluded = reader.read_u64::<LittleEndian>()?;
+        let changes = reader.read_u64::<LittleEndian>()?;
+        let len = u32::try_from(reader.read_u64::<LittleEndian>()?).map_err(|_| GraphError::Corrupted)?;
+        let stats = (0..len)
+            .map(|_| AddressStats::read(&mut reader))
+            .collect::<Result<Vec<_>, _>>()?;
+        let sets = UnionFind::read(reader)?;
+        if sets.len() != stats.len() {
+            return Err(GraphError::Corrupted);
+        }
+        Ok(Self {
+            exclusions,
+            join_change: false,
+            sets,
+            stats,
+            excluded,
+            changes,
+        })
+    }
+
+    // numbers the clusters by their first script and sums the stats of their scripts
+    pub fn clusters(&self) -> Clusters {
+        let mut numbers = vec![u32::MAX; self.stats.len()];
+        let mut membership = Vec::with_capacity(self.stats.len());
+        let mut stats = Vec::<ClusterStats>::new();
+        for (script, address) in self.stats.iter().enumerate() {
+            let root = self.sets.root(ScriptId::new(script as u32)).index();
+            if numbers[root] == u32::MAX {
+                numbers[root] = stats.len() as u32;
+                stats.push(ClusterStats {
+                    size: 0,
+                    first_seen: *address.first_seen(),
+                    received: Amount::from_sat(0),
+                    sent: Amount::from_sat(0),
+                });
+            }
+            let cluster = &mut stats[numbers[root] as usize];
+            cluster.size += 1;
+            cluster.received = cluster.received.saturating_add(address.received());
+            cluster.sent = cluster.sent.saturating_add(address.sent());
+            membership.push(ClusterId(numbers[root]));
+        }
+        Clusters { membership, stats }
+    }
+
+    fn add_resolved(
+        &mut self,
+        transaction: &ResolvedTransaction,
+        seen: Seen,
+        verdict: Option<&ChangeVerdict>,
+    ) -> Result<(), GraphError> {
+        AddressStats::record(&mut self.stats, transaction, seen);
+        self.sets.grow(self.stats.len())?;
+        if transaction.is_coinbase() {
+            return Ok(());
+        }
+        if self.exclusions.excludes(transaction) {
+            self.excluded += 1;
+            return Ok(());
+        }
+        let mut inputs = transaction.resolved_inputs();
+        let first = match inputs.next() {
+            Some(first) => first.script(),
+            None => return Ok(()),
+        };
+        for input in inputs {
+            self.sets.union(first, input.script());
+        }
+        let change = verdict
+            .filter(|_| self.join_change)
+            .and_then(|verdict| verdict.change())
+            .and_then(|vout| transaction.outputs()[vout as usize].script());
+        if let Some(change) = change {
+            self.sets.union(first, change);
+            self.changes += 1;
+        }
+        Ok(())
+    }
+}
+
+impl ResolvedVisitor for AddressClusters {
+    fn on_transaction(
+        &mut self,
+        seen: Seen,
+        transaction: &ResolvedTransaction,
+        verdict: Option<&ChangeVerdict>,
+    ) -> Result<(), GraphError> {
+        self.add_resolved(transaction, seen, verdict)
+    }
+}
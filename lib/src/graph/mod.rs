@@ -0,0 +1,100 @@
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+use std::io;
+use thiserror::Error;
+
+use crate::utxo::UtxoError;
+
+pub mod addresses;
+pub mod chain;
+pub mod change;
+pub mod clusters;
+mod records;
+pub mod scripts;
+pub mod transactions;
+
+// the way the edges are followed when walking a graph, forward goes from the funds to where they are spent
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+// the blocks a graph is built from when visiting the chain: the ones between the start and stop heights
+// that follow the last one added
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct HeightWindow {
+    tip: Option<u32>,
+    start: Option<u32>,
+    stop: Option<u32>,
+}
+
+impl HeightWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // a window after the last block of a graph read from disk
+    pub fn resume(tip: Option<u32>) -> Self {
+        Self { tip, ..Self::default() }
+    }
+
+    // blocks below the height are ignored, the inputs spending their outputs are left out of the graph
+    pub fn start_at(mut self, height: u32) -> Self {
+        self.start = Some(height);
+        self
+    }
+
+    // blocks above the height are ignored
+    pub fn stop_at(mut self, height: u32) -> Self {
+        self.stop = Some(height);
+        self
+    }
+
+    // the height of the last block added
+    pub fn tip(&self) -> Option<u32> {
+        self.tip
+    }
+
+    // the blocks already added are skipped as well, so a graph read from disk resumes after its tip
+    pub fn skips(&self, height: u32) -> bool {
+        let added = self.tip.is_some_and(|tip| height <= tip);
+        added || self.start.is_some_and(|start| height < start) || self.stop.is_some_and(|stop| height > stop)
+    }
+
+    // the heights must follow each other, the first one being any
+    pub fn check(&self, height: u32) -> Result<(), GraphError> {
+        match self.tip.map(|tip| tip + 1).filter(|&expected| expected != height) {
+            Some(expected) => Err(GraphError::Height {
+                expected,
+                found: height,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    pub fn advance(&mut self, height: u32) {
+        self.tip = Some(height);
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GraphError {
+    #[error("expected the block at height {expected} but found the one at height {found}")]
+    Height { expected: u32, found: u32 },
+    #[error("the graph can not hold more than {} nodes", u32::MAX)]
+    Full,
+    #[error("unable to read or write the graph")]
+    Io(#[from] io::Error),
+    #[error("the file does not hold a graph of this kind")]
+    Magic,
+    #[error("unsupported graph version {0}")]
+    Version(u16),
+    #[error("the graph file is corrupted")]
+    Corrupted,
+    #[error("unable to access the unspent outputs")]
+    Utxo(#[from] UtxoError),
+    #[error("the unspent outputs were not stored along with the graph")]
+    Unspent,
+}
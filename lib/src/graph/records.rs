@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::GraphError;
+
+// an array of fixed size records kept in a file after a header. The records added or changed since the last
+// flush are kept in memory, so a block is written at once and the ones before it are only read back
+pub(crate) struct RecordFile {
+    file: File,
+    size: usize,
+    header: u64,
+    // the records on disk
+    stored: u64,
+    pending: Vec<u8>,
+    changes: BTreeMap<u64, Vec<u8>>,
+}
+
+impl RecordFile {
+    // creates the file with the header when it does not exist, checking the header otherwise
+    pub fn open<P: AsRef<Path>>(path: P, size: usize, header: &[u8]) -> Result<Self, GraphError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let length = file.metadata()?.len();
+        if length == 0 {
+            file.write_all(header)?;
+        } else {
+            let mut found = vec![0; header.len()];
+            file.read_exact(&mut found).map_err(|_| GraphError::Magic)?;
+            if found[..8] != header[..8] {
+                return Err(GraphError::Magic);
+            }
+            if found[8..] != header[8..] {
+                return Err(GraphError::Version(u16::from_le_bytes([found[8], found[9]])));
+            }
+        }
+        let header = header.len() as u64;
+        let body = length.saturating_sub(header);
+        // a record cut short by a write that did not finish
+        if body % size as u64 != 0 {
+            return Err(GraphError::Corrupted);
+        }
+        Ok(Self {
+            file,
+            size,
+            header,
+            stored: body / size as u64,
+            pending: Vec::new(),
+            changes: BTreeMap::new(),
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.stored + (self.pending.len() / self.size) as u64
+    }
+
+    pub fn push(&mut self, record: &[u8]) {
+        debug_assert_eq!(record.len(), self.size);
+        self.pending.extend_from_slice(record);
+    }
+
+    pub fn read(&self, index: u64, record: &mut [u8]) -> Result<(), GraphError> {
+        if index >= self.len() {
+            return Err(GraphError::Corrupted);
+        }
+        if index >= self.stored {
+            let start = (index - self.stored) as usize * self.size;
+            record.copy_from_slice(&self.pending[start..start + self.size]);
+        } else if let Some(changed) = self.changes.get(&index) {
+            record.copy_from_slice(changed);
+        } else {
+            let mut file = &self.file;
+            file.seek(SeekFrom::Start(self.offset(index)))?;
+            file.read_exact(record)?;
+        }
+        Ok(())
+    }
+
+    pub fn write(&mut self, index: u64, record: &[u8]) -> Result<(), GraphError> {
+        if index >= self.len() {
+            return Err(GraphError::Corrupted);
+        }
+        if index >= self.stored {
+            let start = (index - self.stored) as usize * self.size;
+            self.pending[start..start + self.size].copy_from_slice(record);
+        } else {
+            self.changes.insert(index, record.to_vec());
+        }
+        Ok(())
+    }
+
+    // writes the changed records in file order before the new ones
+    pub fn flush(&mut self) -> Result<(), GraphError> {
+        for (index, record) in std::mem::take(&mut self.changes) {
+            self.file.seek(SeekFrom::Start(self.offset(index)))?;
+            self.file.write_all(&record)?;
+        }
+        if !self.pending.is_empty() {
+            self.file.seek(SeekFrom::Start(self.offset(self.stored)))?;
+            self.file.write_all(&self.pending)?;
+            self.stored = self.len();
+            self.pending.clear();
+        }
+        Ok(())
+    }
+
+    fn offset(&self, index: u64) -> u64 {
+        self.header + index * self.size as u64
+    }
+}
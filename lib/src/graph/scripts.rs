@@ -0,0 +1,330 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use super::GraphError;
+use crate::address::Address;
+use crate::amount::Amount;
+use crate::blockchain::script::{ScriptType, StandardScript};
+use crate::blockchain::transactions::{Transaction, Utxo};
+use crate::network::Network;
+use crate::types::{BlockHash, Txid};
+use crate::utxo::{Coin, MemoryStore, UtxoStore};
+
+const SCRIPTS_MAGIC: [u8; 8] = *b"scripts\0";
+const SCRIPTS_VERSION: u16 = 1;
+
+// an output script, numbered in the order they first appear in the chain
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct ScriptId(u32);
+
+impl ScriptId {
+    pub fn new(index: u32) -> Self {
+        Self(index)
+    }
+
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// the numbers given to the scripts, each script is stored once and shared with its entry in the map
+#[derive(Default)]
+pub struct ScriptIndex {
+    scripts: Vec<Arc<[u8]>>,
+    ids: HashMap<Arc<[u8]>, ScriptId>,
+}
+
+impl ScriptIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scripts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scripts.is_empty()
+    }
+
+    pub fn id(&self, script: &[u8]) -> Option<ScriptId> {
+        self.ids.get(script).copied()
+    }
+
+    pub fn script(&self, id: ScriptId) -> &[u8] {
+        &self.scripts[id.index()]
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = ScriptId> {
+        (0..self.scripts.len() as u32).map(ScriptId)
+    }
+
+    pub fn script_type(&self, id: ScriptId) -> ScriptType {
+        StandardScript::classify(self.script(id)).script_type()
+    }
+
+    // the address of the script, or its hex for the ones without an address
+    pub fn label(&self, id: ScriptId, network: Network) -> String {
+        let script = self.script(id);
+        Address::from_script(&StandardScript::classify(script), network)
+            .map(|address| address.to_string())
+            .unwrap_or_else(|| hex::encode(script))
+    }
+
+    // the number of the script, and whether it is the first time it is seen
+    pub fn insert(&mut self, script: &[u8]) -> Result<(ScriptId, bool), GraphError> {
+        if let Some(&id) = self.ids.get(script) {
+            return Ok((id, false));
+        }
+        let id = ScriptId(u32::try_from(self.scripts.len()).map_err(|_| GraphError::Full)?);
+        let script = Arc::<[u8]>::from(script);
+        self.scripts.push(script.clone());
+        self.ids.insert(script, id);
+        Ok((id, true))
+    }
+
+    // the scripts in the order of their numbers, along with the height of the last block they were taken from
+    pub fn write<W: Write>(&self, tip: Option<u32>, mut writer: W) -> Result<W, GraphError> {
+        writer.write_all(&SCRIPTS_MAGIC)?;
+        writer.write_u16::<LittleEndian>(SCRIPTS_VERSION)?;
+        match tip {
+            Some(tip) => {
+                writer.write_u8(1)?;
+                writer.write_u32::<LittleEndian>(tip)?;
+            }
+            None => writer.write_u8(0)?,
+        }
+        writer.write_u64::<LittleEndian>(self.scripts.len() as u64)?;
+        for script in &self.scripts {
+            writer.write_u32::<LittleEndian>(script.len() as u32)?;
+            writer.write_all(script)?;
+        }
+        writer.flush()?;
+        Ok(writer)
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<(Self, Option<u32>), GraphError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != SCRIPTS_MAGIC {
+            return Err(GraphError::Magic);
+        }
+        let version = reader.read_u16::<LittleEndian>()?;
+        if version != SCRIPTS_VERSION {
+            return Err(GraphError::Version(version));
+        }
+        let tip = match reader.read_u8()? {
+            0 => None,
+            1 => Some(reader.read_u32::<LittleEndian>()?),
+            _ => return Err(GraphError::Corrupted),
+        };
+        let mut index = Self::new();
+        for _ in 0..reader.read_u64::<LittleEndian>()? {
+            let mut script = vec![0; reader.read_u32::<LittleEndian>()? as usize];
+            reader.read_exact(&mut script)?;
+            // each script is numbered once
+            if !index.insert(&script)?.1 {
+                return Err(GraphError::Corrupted);
+            }
+        }
+        Ok((index, tip))
+    }
+}
+
+// an input with the script and value of the output it spends
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct ResolvedInput {
+    script: ScriptId,
+    value: Amount,
+}
+
+impl ResolvedInput {
+    pub fn script(&self) -> ScriptId {
+        self.script
+    }
+
+    pub fn value(&self) -> Amount {
+        self.value
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct ResolvedOutput {
+    // none for the unspendable outputs, which are left out of the index
+    script: Option<ScriptId>,
+    value: Amount,
+    fresh: bool,
+}
+
+impl ResolvedOutput {
+    pub fn script(&self) -> Option<ScriptId> {
+        self.script
+    }
+
+    pub fn value(&self) -> Amount {
+        self.value
+    }
+
+    // whether the script is seen for the first time in this output
+    pub fn is_fresh(&self) -> bool {
+        self.fresh
+    }
+}
+
+// a transaction with the scripts of both sides. The inputs spending outputs created before the first block
+// resolved are none
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct ResolvedTransaction {
+    txid: Txid,
+    coinbase: bool,
+    inputs: Vec<Option<ResolvedInput>>,
+    outputs: Vec<ResolvedOutput>,
+}
+
+impl ResolvedTransaction {
+    pub fn txid(&self) -> &Txid {
+        &self.txid
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.coinbase
+    }
+
+    pub fn inputs(&self) -> &[Option<ResolvedInput>] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[ResolvedOutput] {
+        &self.outputs
+    }
+
+    pub fn resolved_inputs(&self) -> impl Iterator<Item = &ResolvedInput> {
+        self.inputs.iter().flatten()
+    }
+
+    pub fn is_fully_resolved(&self) -> bool {
+        self.inputs.iter().all(Option::is_some)
+    }
+
+    pub fn input_value(&self) -> Option<Amount> {
+        Amount::checked_sum(self.resolved_inputs().map(ResolvedInput::value))
+    }
+
+    pub fn output_value(&self) -> Option<Amount> {
+        Amount::checked_sum(self.outputs.iter().map(ResolvedOutput::value))
+    }
+}
+
+// numbers the scripts of the transactions and finds the script and value of the outputs their inputs spend,
+// keeping the unspent ones in a utxo store. The transactions must be given in the order of the chain
+pub struct ScriptResolver<S: UtxoStore = MemoryStore> {
+    scripts: ScriptIndex,
+    unspent: S,
+}
+
+impl ScriptResolver<MemoryStore> {
+    pub fn new() -> Self {
+        Self::with_store(ScriptIndex::new(), MemoryStore::new())
+    }
+}
+
+impl Default for ScriptResolver<MemoryStore> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: UtxoStore> ScriptResolver<S> {
+    // the store must hold the unspent outputs of the transactions the scripts were numbered from
+    pub fn with_store(scripts: ScriptIndex, unspent: S) -> Self {
+        Self { scripts, unspent }
+    }
+
+    pub fn scripts(&self) -> &ScriptIndex {
+        &self.scripts
+    }
+
+    pub fn unspent_count(&self) -> u64 {
+        self.unspent.len()
+    }
+
+    // the height of the last block resolved
+    pub fn tip(&self) -> Option<u32> {
+        self.unspent.tip().map(|(height, _)| height)
+    }
+
+    // the height and hash of the last block given
+    pub fn tip_block(&self) -> Option<(u32, BlockHash)> {
+        self.unspent.tip()
+    }
+
+    pub fn resolve(
+        &mut self,
+        height: u32,
+        txid: Txid,
+        transaction: &Transaction,
+    ) -> Result<ResolvedTransaction, GraphError> {
+        let coinbase = transaction.is_coinbase();
+        let mut inputs = Vec::with_capacity(transaction.inputs().len());
+        if !coinbase {
+            for input in transaction.inputs() {
+                let resolved = match self.unspent.remove(input.utxo())? {
+                    Some(coin) => Some(ResolvedInput {
+                        script: self
+                            .scripts
+                            .id(coin.output().pubkey().as_bytes())
+                            .ok_or(GraphError::Unspent)?,
+                        value: coin.output().value(),
+                    }),
+                    None => None,
+                };
+                inputs.push(resolved);
+            }
+        }
+        let mut outputs = Vec::with_capacity(transaction.outputs().len());
+        for (vout, output) in transaction.outputs().iter().enumerate() {
+            let (script, fresh) = match output.pubkey().is_unspendable() {
+                true => (None, false),
+                false => {
+                    let (script, fresh) = self.scripts.insert(output.pubkey().as_bytes())?;
+                    self.unspent.insert(
+                        Utxo::new(txid, vout as u32),
+                        Coin::new(output.clone(), height, coinbase),
+                    )?;
+                    (Some(script), fresh)
+                }
+            };
+            outputs.push(ResolvedOutput {
+                script,
+                value: output.value(),
+                fresh,
+            });
+        }
+        Ok(ResolvedTransaction {
+            txid,
+            coinbase,
+            inputs,
+            outputs,
+        })
+    }
+
+    // called once every transaction of the block is resolved, the store may write its changes at this point
+    pub fn finish_block(&mut self, height: u32, hash: BlockHash) -> Result<(), GraphError> {
+        self.unspent.set_tip(height, hash)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), GraphError> {
+        self.unspent.flush()?;
+        Ok(())
+    }
+}
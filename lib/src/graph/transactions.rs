@@ -0,0 +1,584 @@
+use arrayref::array_ref;
+use byteorder::{ByteOrder, LittleEndian};
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+use super::records::RecordFile;
+use super::{Direction, GraphError, HeightWindow};
+use crate::amount::Amount;
+#[cfg(feature = "parser")]
+use crate::blkparser::SerialBlock;
+use crate::blockchain::transactions::{Transaction, Utxo};
+use crate::types::{BlockHash, Txid};
+use crate::utxo::{Coin, MemoryStore, UtxoStore};
+#[cfg(feature = "parser")]
+use crate::visitor::ChainVisitor;
+
+pub const GRAPH_MAGIC: [u8; 8] = *b"txgraph\0";
+pub const GRAPH_VERSION: u16 = 4;
+
+// the end of the lists of spends
+const NO_EDGE: u64 = u64::MAX;
+// the end of the lists of nodes in a bucket of the txid index
+const NO_NODE: u32 = u32::MAX;
+// the nodes per bucket of the txid index before one is split
+const LOAD: u64 = 2;
+
+// the files of the graph directory and the size of their records
+const NODES: (&str, usize) = ("nodes", 44);
+const SPENDS: (&str, usize) = ("spends", 8);
+const EDGES: (&str, usize) = ("edges", 28);
+const BLOCKS: (&str, usize) = ("blocks", 28);
+const BUCKETS: (&str, usize) = ("buckets", 4);
+const LINKS: (&str, usize) = ("links", 12);
+
+// a transaction of the graph, numbered in the order they were added
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct TxNode(u32);
+
+impl TxNode {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+// an output of one transaction spent by another
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct Spend {
+    from: TxNode,
+    to: TxNode,
+    vout: u32,
+    value: Amount,
+}
+
+impl Spend {
+    pub fn from(&self) -> TxNode {
+        self.from
+    }
+
+    pub fn to(&self) -> TxNode {
+        self.to
+    }
+
+    pub fn vout(&self) -> u32 {
+        self.vout
+    }
+
+    pub fn value(&self) -> Amount {
+        self.value
+    }
+}
+
+// transactions linked by the outputs they spend from each other. The nodes and edges are kept in files of
+// fixed size records indexed by their number, only the block being added is held in memory: the spends of a
+// transaction are stored together when it is added, and the ones of its outputs are linked through the edges
+// as they are found. The nodes are found by txid through a hash table kept in the files as well, and the
+// outputs not spent yet are kept in a utxo store
+pub struct TransactionGraph<S: UtxoStore = MemoryStore> {
+    // the txid, height and first input edge of each node
+    nodes: RecordFile,
+    // the last spend of the outputs of each node
+    spends: RecordFile,
+    // the spend and the previous one of the outputs of the same node
+    edges: RecordFile,
+    // the height of each block and the nodes, edges and buckets of the graph once it was added
+    blocks: RecordFile,
+    // the last node added to each bucket of the txid index
+    buckets: RecordFile,
+    // the key in the txid index and the previous node of the same bucket of each node
+    links: RecordFile,
+    unspent: S,
+    window: HeightWindow,
+    error: Option<GraphError>,
+}
+
+impl<S: UtxoStore> TransactionGraph<S> {
+    // creates the graph in the directory, or resumes the one in it after its last block. The store must
+    // hold the unspent outputs of the graph, so it has to be the one the graph was built with
+    pub fn open<P: AsRef<Path>>(dir: P, unspent: S) -> Result<Self, GraphError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let mut header = GRAPH_MAGIC.to_vec();
+        header.extend_from_slice(&GRAPH_VERSION.to_le_bytes());
+        let open = |(name, size)| RecordFile::open(dir.join(name), size, &header);
+        let mut graph = Self {
+            nodes: open(NODES)?,
+            spends: open(SPENDS)?,
+            edges: open(EDGES)?,
+            blocks: open(BLOCKS)?,
+            buckets: open(BUCKETS)?,
+            links: open(LINKS)?,
+            unspent,
+            window: HeightWindow::new(),
+            error: None,
+        };
+        let nodes = graph.nodes.len();
+        if graph.spends.len() != nodes || graph.links.len() != nodes {
+            return Err(GraphError::Corrupted);
+        }
+        let (tip, expected) = match graph.blocks.len() {
+            0 => (None, (0, 0, 0)),
+            blocks => {
+                let (height, nodes, edges, buckets) = graph.block_record(blocks - 1)?;
+                (Some(height), (nodes, edges, buckets))
+            }
+        };
+        // the files written before the blocks when the last block was not finished
+        if (nodes, graph.edges.len(), graph.buckets.len()) != expected {
+            return Err(GraphError::Corrupted);
+        }
+        if graph.buckets.len() == 0 {
+            graph.buckets.push(&NO_NODE.to_le_bytes());
+        }
+        if graph.unspent.tip().map(|(height, _)| height) != tip {
+            return Err(GraphError::Unspent);
+        }
+        graph.window = HeightWindow::resume(tip);
+        Ok(graph)
+    }
+
+    pub fn start_at(mut self, height: u32) -> Self {
+        self.window = self.window.start_at(height);
+        self
+    }
+
+    pub fn stop_at(mut self, height: u32) -> Self {
+        self.window = self.window.stop_at(height);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn spend_count(&self) -> u64 {
+        self.edges.len()
+    }
+
+    pub fn unspent_count(&self) -> u64 {
+        self.unspent.len()
+    }
+
+    // the height of the last block added
+    pub fn tip(&self) -> Option<u32> {
+        self.window.tip()
+    }
+
+    pub fn txid(&self, node: TxNode) -> Result<Txid, GraphError> {
+        Ok(self.node_record(node)?.0)
+    }
+
+    pub fn height(&self, node: TxNode) -> Result<u32, GraphError> {
+        Ok(self.node_record(node)?.1)
+    }
+
+    // the latest node with the txid, the few transactions repeated by the early coinbases shadow the first one
+    pub fn node(&self, txid: &Txid) -> Result<Option<TxNode>, GraphError> {
+        let key = index_key(txid);
+        let mut next = self.bucket_record(index_bucket(key, self.buckets.len()))?;
+        while next != NO_NODE {
+            let (found, previous) = self.link_record(next)?;
+            if found == key && self.txid(TxNode(next))? == *txid {
+                return Ok(Some(TxNode(next)));
+            }
+            next = previous;
+        }
+        Ok(None)
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = TxNode> {
+        (0..self.len() as u32).map(TxNode)
+    }
+
+    // the outputs the transaction spends, in the order of its inputs
+    pub fn inputs(&self, node: TxNode) -> Result<Vec<Spend>, GraphError> {
+        let start = self.node_record(node)?.2;
+        let end = match node.index() + 1 < self.len() {
+            true => self.node_record(TxNode(node.0 + 1))?.2,
+            false => self.edges.len(),
+        };
+        (start..end)
+            .map(|edge| self.edge_record(edge).map(|(spend, _)| spend))
+            .collect()
+    }
+
+    // the spends of the outputs of the transaction, the latest first
+    pub fn spends(&self, node: TxNode) -> Result<Spends<'_, S>, GraphError> {
+        Ok(Spends {
+            graph: self,
+            edge: self.spend_record(node)?,
+        })
+    }
+
+    pub fn traverse(&self, node: TxNode, direction: Direction) -> Traversal<'_, S> {
+        Traversal {
+            graph: self,
+            direction,
+            max_depth: None,
+            min_value: Amount::from_sat(0),
+            queue: vec![Step {
+                node,
+                depth: 0,
+                spend: None,
+            }]
+            .into(),
+            visited: vec![node].into_iter().collect(),
+        }
+    }
+
+    // the block is written to the files once all its transactions are added
+    pub fn add_transactions<'a>(
+        &mut self,
+        height: u32,
+        hash: BlockHash,
+        transactions: impl IntoIterator<Item = (Txid, &'a Transaction)>,
+    ) -> Result<(), GraphError> {
+        self.window.check(height)?;
+        for (txid, transaction) in transactions {
+            self.add_transaction(height, txid, transaction)?;
+        }
+        let mut record = [0; BLOCKS.1];
+        LittleEndian::write_u32(&mut record[..4], height);
+        LittleEndian::write_u64(&mut record[4..12], self.nodes.len());
+        LittleEndian::write_u64(&mut record[12..20], self.edges.len());
+        LittleEndian::write_u64(&mut record[20..], self.buckets.len());
+        self.blocks.push(&record);
+        self.flush_files()?;
+        self.unspent.set_tip(height, hash)?;
+        self.window.advance(height);
+        Ok(())
+    }
+
+    #[cfg(feature = "parser")]
+    pub fn add_block(&mut self, height: u32, block: &SerialBlock) -> Result<(), GraphError> {
+        let transactions = block
+            .transactions()
+            .iter()
+            .map(|transaction| (*transaction.hash(), transaction.transaction()));
+        self.add_transactions(height, *block.hash(), transactions)
+    }
+
+    fn add_transaction(&mut self, height: u32, txid: Txid, transaction: &Transaction) -> Result<(), GraphError> {
+        let node = TxNode(u32::try_from(self.nodes.len()).map_err(|_| GraphError::Full)?);
+        let coinbase = transaction.is_coinbase();
+        let first = self.edges.len();
+        if !coinbase {
+            for input in transaction.inputs() {
+                let coin = match self.unspent.remove(input.utxo())? {
+                    Some(coin) => coin,
+                    None => continue,
+                };
+                // the outputs created before the first block of the graph are not in it
+                if let Some(from) = self.node(input.utxo().txid())? {
+                    self.link(Spend {
+                        from,
+                        to: node,
+                        vout: input.utxo().vout(),
+                        value: coin.output().value(),
+                    })?;
+                }
+            }
+        }
+        let mut record = [0; NODES.1];
+        record[..32].copy_from_slice(txid.as_bytes());
+        LittleEndian::write_u32(&mut record[32..36], height);
+        LittleEndian::write_u64(&mut record[36..], first);
+        self.nodes.push(&record);
+        self.spends.push(&NO_EDGE.to_le_bytes());
+        self.index(&txid, node)?;
+        for (vout, output) in transaction.outputs().iter().enumerate() {
+            if !output.pubkey().is_unspendable() {
+                self.unspent.insert(
+                    Utxo::new(txid, vout as u32),
+                    Coin::new(output.clone(), height, coinbase),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn link(&mut self, spend: Spend) -> Result<(), GraphError> {
+        let previous = self.spend_record(spend.from)?;
+        let mut record = [0; EDGES.1];
+        LittleEndian::write_u32(&mut record[..4], spend.from.0);
+        LittleEndian::write_u32(&mut record[4..8], spend.to.0);
+        LittleEndian::write_u32(&mut record[8..12], spend.vout);
+        LittleEndian::write_u64(&mut record[12..20], spend.value.to_sat());
+        LittleEndian::write_u64(&mut record[20..], previous);
+        self.spends
+            .write(spend.from.0 as u64, &self.edges.len().to_le_bytes())?;
+        self.edges.push(&record);
+        Ok(())
+    }
+
+    fn index(&mut self, txid: &Txid, node: TxNode) -> Result<(), GraphError> {
+        let key = index_key(txid);
+        let bucket = index_bucket(key, self.buckets.len());
+        let previous = self.bucket_record(bucket)?;
+        self.links.push(&link(key, previous));
+        self.buckets.write(bucket, &node.0.to_le_bytes())?;
+        if self.links.len() > LOAD * self.buckets.len() {
+            self.split()?;
+        }
+        Ok(())
+    }
+
+    // adds a bucket by moving the nodes of the one it splits from that no longer belong there
+    fn split(&mut self) -> Result<(), GraphError> {
+        let buckets = self.buckets.len();
+        let split = buckets - (1 << (63 - buckets.leading_zeros()));
+        let (mut kept, mut moved) = (NO_NODE, NO_NODE);
+        let mut next = self.bucket_record(split)?;
+        while next != NO_NODE {
+            let (key, previous) = self.link_record(next)?;
+            let head = match index_bucket(key, buckets + 1) == split {
+                true => &mut kept,
+                false => &mut moved,
+            };
+            self.links.write(next as u64, &link(key, *head))?;
+            *head = next;
+            next = previous;
+        }
+        self.buckets.write(split, &kept.to_le_bytes())?;
+        self.buckets.push(&moved.to_le_bytes());
+        Ok(())
+    }
+
+    fn node_record(&self, node: TxNode) -> Result<(Txid, u32, u64), GraphError> {
+        let mut record = [0; NODES.1];
+        self.nodes.read(node.0 as u64, &mut record)?;
+        Ok((
+            Txid::new(*array_ref!(record, 0, 32)),
+            LittleEndian::read_u32(&record[32..36]),
+            LittleEndian::read_u64(&record[36..]),
+        ))
+    }
+
+    fn spend_record(&self, node: TxNode) -> Result<u64, GraphError> {
+        let mut record = [0; SPENDS.1];
+        self.spends.read(node.0 as u64, &mut record)?;
+        Ok(LittleEndian::read_u64(&record))
+    }
+
+    // the spend and the previous one of the outputs of the same node
+    fn edge_record(&self, edge: u64) -> Result<(Spend, u64), GraphError> {
+        let mut record = [0; EDGES.1];
+        self.edges.read(edge, &mut record)?;
+        let spend = Spend {
+            from: TxNode(LittleEndian::read_u32(&record[..4])),
+            to: TxNode(LittleEndian::read_u32(&record[4..8])),
+            vout: LittleEndian::read_u32(&record[8..12]),
+            value: Amount::from_sat(LittleEndian::read_u64(&record[12..20])),
+        };
+        Ok((spend, LittleEndian::read_u64(&record[20..])))
+    }
+
+    fn bucket_record(&self, bucket: u64) -> Result<u32, GraphError> {
+        let mut record = [0; BUCKETS.1];
+        self.buckets.read(bucket, &mut record)?;
+        Ok(LittleEndian::read_u32(&record))
+    }
+
+    fn link_record(&self, node: u32) -> Result<(u64, u32), GraphError> {
+        let mut record = [0; LINKS.1];
+        self.links.read(node as u64, &mut record)?;
+        Ok((
+            LittleEndian::read_u64(&record[..8]),
+            LittleEndian::read_u32(&record[8..]),
+        ))
+    }
+
+    fn block_record(&self, block: u64) -> Result<(u32, u64, u64, u64), GraphError> {
+        let mut record = [0; BLOCKS.1];
+        self.blocks.read(block, &mut record)?;
+        Ok((
+            LittleEndian::read_u32(&record[..4]),
+            LittleEndian::read_u64(&record[4..12]),
+            LittleEndian::read_u64(&record[12..20]),
+            LittleEndian::read_u64(&record[20..]),
+        ))
+    }
+
+    fn flush_files(&mut self) -> Result<(), GraphError> {
+        self.nodes.flush()?;
+        self.spends.flush()?;
+        self.edges.flush()?;
+        self.buckets.flush()?;
+        self.links.flush()?;
+        // the block goes last, a graph is only resumed when the other files hold what its record counts
+        self.blocks.flush()
+    }
+
+    // writes the unspent outputs the store still holds in memory, the files are written after each block
+    pub fn flush(&mut self) -> Result<(), GraphError> {
+        self.unspent.flush()?;
+        Ok(())
+    }
+
+    // the error that stopped the graph while visiting the chain, if any
+    pub fn take_error(&mut self) -> Option<GraphError> {
+        self.error.take()
+    }
+}
+
+// the txids are hashes already, their first bytes are spread evenly enough to key the index
+fn index_key(txid: &Txid) -> u64 {
+    LittleEndian::read_u64(&txid.as_bytes()[..8])
+}
+
+// the index grows by linear hashing: the buckets are split one at a time in order, and the count doubles once
+// they all are. The bucket of a key only depends on the number of buckets, which is never zero
+fn index_bucket(key: u64, buckets: u64) -> u64 {
+    let level = 63 - buckets.leading_zeros();
+    let bucket = key & ((2 << level) - 1);
+    match bucket < buckets {
+        true => bucket,
+        false => bucket - (1 << level),
+    }
+}
+
+fn link(key: u64, previous: u32) -> [u8; LINKS.1] {
+    let mut record = [0; LINKS.1];
+    LittleEndian::write_u64(&mut record[..8], key);
+    LittleEndian::write_u32(&mut record[8..], previous);
+    record
+}
+
+#[cfg(feature = "parser")]
+impl<S: UtxoStore> ChainVisitor for TransactionGraph<S> {
+    fn anchor(&self) -> Option<(u32, BlockHash)> {
+        self.unspent.tip()
+    }
+
+    fn on_block(&mut self, height: u32, block: &SerialBlock) {
+        if self.error.is_some() || self.window.skips(height) {
+            return;
+        }
+        if let Err(err) = self.add_block(height, block) {
+            self.error = Some(err);
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Err(err) = self.flush() {
+            self.error.get_or_insert(err);
+        }
+    }
+}
+
+pub struct Spends<'a, S: UtxoStore> {
+    graph: &'a TransactionGraph<S>,
+    edge: u64,
+}
+
+impl<S: UtxoStore> Iterator for Spends<'_, S> {
+    type Item = Result<Spend, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.edge == NO_EDGE {
+            return None;
+        }
+        let record = self.graph.edge_record(self.edge);
+        // a list that can not be read is not followed any further
+        self.edge = record.as_ref().map_or(NO_EDGE, |&(_, previous)| previous);
+        Some(record.map(|(spend, _)| spend))
+    }
+}
+
+// a transaction reached by a traversal, with the spend followed to reach it
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct Step {
+    node: TxNode,
+    depth: u32,
+    spend: Option<Spend>,
+}
+
+impl Step {
+    pub fn node(&self) -> TxNode {
+        self.node
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    // none for the transaction the traversal starts at
+    pub fn spend(&self) -> Option<&Spend> {
+        self.spend.as_ref()
+    }
+}
+
+// a breadth first walk from a transaction, visiting each reachable one once and the closest ones first
+pub struct Traversal<'a, S: UtxoStore> {
+    graph: &'a TransactionGraph<S>,
+    direction: Direction,
+    max_depth: Option<u32>,
+    min_value: Amount,
+    queue: VecDeque<Step>,
+    visited: HashSet<TxNode>,
+}
+
+impl<S: UtxoStore> Traversal<'_, S> {
+    pub fn max_depth(mut self, depth: u32) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    // the spends of smaller outputs are not followed
+    pub fn min_value(mut self, value: Amount) -> Self {
+        self.min_value = value;
+        self
+    }
+}
+
+// the walk ends at the first error reading the graph
+impl<S: UtxoStore> Iterator for Traversal<'_, S> {
+    type Item = Result<Step, GraphError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let step = self.queue.pop_front()?;
+        if self.max_depth.is_some_and(|depth| step.depth >= depth) {
+            return Some(Ok(step));
+        }
+        let spends = match self.direction {
+            Direction::Forward => self
+                .graph
+                .spends(step.node)
+                .and_then(|spends| spends.collect::<Result<Vec<_>, _>>()),
+            Direction::Backward => self.graph.inputs(step.node),
+        };
+        let spends = match spends {
+            Ok(spends) => spends,
+            Err(err) => {
+                self.queue.clear();
+                return Some(Err(err));
+            }
+        };
+        let min_value = self.min_value;
+        for spend in spends.into_iter().filter(|spend| spend.value >= min_value) {
+            let node = match self.direction {
+                Direction::Forward => spend.to,
+                Direction::Backward => spend.from,
+            };
+            if self.visited.insert(node) {
+                self.queue.push_back(Step {
+                    node,
+                    depth: step.depth + 1,
+                    spend: Some(spend),
+                });
+            }
+        }
+        Some(Ok(step))
+    }
+}
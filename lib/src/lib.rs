@@ -1,14 +1,24 @@
 #![allow(dead_code)]
 
+pub mod address;
+pub mod amount;
+pub mod analysis;
 #[cfg(feature = "parser")]
 pub mod blkparser;
 pub mod blockchain;
 pub mod cursor;
 #[cfg(feature = "writer")]
 pub mod disk;
+pub mod encode;
+pub mod graph;
+pub mod network;
 #[cfg(feature = "parser")]
 pub mod parser;
+pub mod protocols;
 pub mod types;
+pub mod utxo;
+#[cfg(feature = "parser")]
+pub mod visitor;
 
 pub mod byteorder {
     pub use byteorder::BigEndian;
@@ -16,8 +26,69 @@ pub mod byteorder {
     pub use byteorder::LittleEndian;
 }
 
-pub trait TransactionBlock {
+use amount::Amount;
+use blockchain::block::BlockHeader;
+use blockchain::transactions::{Input, Output};
+use types::{BlockHash, BlockTarget, Txid};
+
+// what the chain needs from a block model to link and index it, the parser fills it through its builder
+pub trait TransactionBlock: Sized {
     type Transaction: Transaction;
+    type Builder: BlockBuilder<Block = Self>;
+
+    fn hash(&self) -> BlockHash;
+
+    fn previous(&self) -> BlockHash;
+
+    fn time(&self) -> u32;
+
+    fn bits(&self) -> BlockTarget;
+
+    fn transactions(&self) -> &[Self::Transaction];
+}
+
+pub trait Transaction: Sized {
+    type Builder: TransactionBuilder<Transaction = Self>;
+
+    fn txid(&self) -> Txid;
+
+    // the models that do not keep the inputs or the outputs have none to show
+    fn inputs(&self) -> &[Input] {
+        &[]
+    }
+
+    fn outputs(&self) -> &[Output] {
+        &[]
+    }
+}
+
+// the parser creates one builder per block once the header is read, and hands it each transaction in order
+pub trait BlockBuilder {
+    type Block: TransactionBlock;
+
+    fn new(size: u32, hash: BlockHash, header: BlockHeader, transactions: u64) -> Self;
+
+    fn transaction(&mut self, transaction: <Self::Block as TransactionBlock>::Transaction);
+
+    fn build(self) -> Self::Block;
 }
 
-pub trait Transaction {}
+// the parser calls these in the order the fields are serialized: the inputs, the outputs, the witness of
+// each input when the transaction has them, and then the locktime. Only the parts of interest need to be kept
+pub trait TransactionBuilder {
+    type Transaction: Transaction;
+
+    fn new(version: u32, segwit: bool) -> Self;
+
+    fn inputs(&mut self, _count: u64) {}
+
+    fn input(&mut self, _txid: Txid, _vout: u32, _script: &[u8], _sequence: u32) {}
+
+    fn outputs(&mut self, _count: u64) {}
+
+    fn output(&mut self, _value: Amount, _script: &[u8]) {}
+
+    fn witness(&mut self, _input: usize, _items: &[&[u8]]) {}
+
+    fn build(self, txid: Txid, locktime: u32) -> Self::Transaction;
+}
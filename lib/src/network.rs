@@ -0,0 +1,80 @@
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Network {
+    Bitcoin,
+    Testnet,
+    Signet,
+    Regtest,
+}
+
+impl Network {
+    pub fn magic(self) -> u32 {
+        match self {
+            Network::Bitcoin => 0xf9beb4d9,
+            Network::Testnet => 0x0b110907,
+            Network::Signet => 0x0a03cf40,
+            Network::Regtest => 0xfabfb5da,
+        }
+    }
+
+    pub fn from_magic(magic: u32) -> Option<Self> {
+        [Network::Bitcoin, Network::Testnet, Network::Signet, Network::Regtest]
+            .iter()
+            .copied()
+            .find(|network| network.magic() == magic)
+    }
+
+    pub fn p2pkh_prefix(self) -> u8 {
+        match self {
+            Network::Bitcoin => 0x00,
+            _ => 0x6f,
+        }
+    }
+
+    pub fn p2sh_prefix(self) -> u8 {
+        match self {
+            Network::Bitcoin => 0x05,
+            _ => 0xc4,
+        }
+    }
+
+    pub fn bech32_hrp(self) -> &'static str {
+        match self {
+            Network::Bitcoin => "bc",
+            Network::Testnet | Network::Signet => "tb",
+            Network::Regtest => "bcrt",
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Network::Bitcoin => "bitcoin",
+            Network::Testnet => "testnet",
+            Network::Signet => "signet",
+            Network::Regtest => "regtest",
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("unknown network: {0}")]
+pub struct UnknownNetwork(String);
+
+impl FromStr for Network {
+    type Err = UnknownNetwork;
+
+    fn from_str(network: &str) -> Result<Self, Self::Err> {
+        match network {
+            "bitcoin" | "mainnet" | "main" => Ok(Network::Bitcoin),
+            "testnet" | "test" => Ok(Network::Testnet),
+            "signet" => Ok(Network::Signet),
+            "regtest" => Ok(Network::Regtest),
+            _ => Err(UnknownNetwork(network.to_owned())),
+        }
+    }
+}
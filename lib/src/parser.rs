@@ -58,6 +58,8 @@ pub enum ParseErrorKind {
     ReadError,
     #[error("unable to deserialize block from data")]
     BlockError,
+    #[error("unable to tell the height of the blocks")]
+    HeightError,
 }
 
 #[derive(Debug, Error)]
@@ -0,0 +1,331 @@
+use arrayref::array_ref;
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+
+use crate::blockchain::script::opcodes::{OP_0, OP_1NEGATE, OP_ENDIF, OP_IF};
+use crate::blockchain::script::{decode_small_int, Instruction, Instructions};
+#[cfg(feature = "writer")]
+use crate::blockchain::serde_custom;
+use crate::blockchain::transactions::{Transaction, Witness};
+use crate::types::Txid;
+
+pub const PROTOCOL_ID: &[u8] = b"ord";
+
+pub const BODY_TAG: u8 = 0;
+pub const CONTENT_TYPE_TAG: u8 = 1;
+pub const POINTER_TAG: u8 = 2;
+pub const PARENT_TAG: u8 = 3;
+pub const METADATA_TAG: u8 = 5;
+pub const METAPROTOCOL_TAG: u8 = 7;
+pub const CONTENT_ENCODING_TAG: u8 = 9;
+pub const DELEGATE_TAG: u8 = 11;
+pub const RUNE_TAG: u8 = 13;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct Inscription {
+    index: u32,
+    input: u32,
+    offset: u32,
+    content_type: Option<String>,
+    #[cfg_attr(feature = "writer", serde(with = "serde_custom::hex_option"))]
+    content_encoding: Option<Vec<u8>>,
+    pointer: Option<u64>,
+    parents: Vec<String>,
+    delegate: Option<String>,
+    #[cfg_attr(feature = "writer", serde(with = "serde_custom::hex_option"))]
+    metadata: Option<Vec<u8>>,
+    metaprotocol: Option<String>,
+    #[cfg_attr(feature = "writer", serde(with = "serde_custom::hex_option"))]
+    rune: Option<Vec<u8>>,
+    #[cfg_attr(feature = "writer", serde(with = "serde_custom::hex_option"))]
+    body: Option<Vec<u8>>,
+    pushnum: bool,
+    duplicate_field: bool,
+    incomplete_field: bool,
+    unrecognized_even_field: bool,
+}
+
+impl Inscription {
+    pub fn from_transaction(transaction: &Transaction) -> Vec<Inscription> {
+        transaction
+            .inputs()
+            .iter()
+            .enumerate()
+            .filter_map(|(index, input)| input.witness().map(|witness| (index, witness)))
+            .flat_map(|(index, witness)| Self::from_witness(witness, index as u32))
+            .enumerate()
+            .map(|(index, mut inscription)| {
+                inscription.index = index as u32;
+                inscription
+            })
+            .collect()
+    }
+
+    pub fn from_witness(witness: &Witness, input: u32) -> Vec<Inscription> {
+        match witness.taproot().ok().and_then(|spend| spend.script()) {
+            Some(tapscript) => Self::from_tapscript(tapscript, input),
+            None => vec![],
+        }
+    }
+
+    // a script that does not parse holds no inscription at all, while an envelope that is not well formed is
+    // skipped along with nothing else, as ord does
+    pub fn from_tapscript(tapscript: &[u8], input: u32) -> Vec<Inscription> {
+        let instructions = match Instructions::new(tapscript).collect::<Result<Vec<_>, _>>() {
+            Ok(instructions) => instructions,
+            Err(_) => return vec![],
+        };
+        let mut inscriptions = vec![];
+        let mut position = 0;
+        while position + 2 < instructions.len() {
+            let is_envelope_start = matches!(
+                instructions[position..position + 3],
+                [Instruction::PushBytes(&[]), Instruction::Op(OP_IF), Instruction::PushBytes(protocol)]
+                    if protocol == PROTOCOL_ID
+            );
+            if !is_envelope_start {
+                position += 1;
+                continue;
+            }
+            match Self::read_envelope(&instructions[position + 3..]) {
+                Some((pushes, pushnum, consumed)) => {
+                    let offset = inscriptions.len() as u32;
+                    inscriptions.push(Self::from_pushes(pushes, pushnum, input, offset));
+                    position += 3 + consumed;
+                }
+                None => position += 1,
+            }
+        }
+        inscriptions
+    }
+
+    // returns the pushes of the envelope body and the amount of instructions consumed, including OP_ENDIF
+    fn read_envelope(instructions: &[Instruction<'_>]) -> Option<(Vec<Vec<u8>>, bool, usize)> {
+        let mut pushes = vec![];
+        let mut pushnum = false;
+        for (index, instruction) in instructions.iter().enumerate() {
+            match instruction {
+                Instruction::PushBytes(bytes) => pushes.push(bytes.to_vec()),
+                Instruction::Op(OP_ENDIF) => return Some((pushes, pushnum, index + 1)),
+                Instruction::Op(OP_1NEGATE) => {
+                    pushnum = true;
+                    pushes.push(vec![0x81]);
+                }
+                Instruction::Op(opcode) => match decode_small_int(*opcode) {
+                    Some(number) if *opcode != OP_0 => {
+                        pushnum = true;
+                        pushes.push(vec![number]);
+                    }
+                    _ => return None,
+                },
+            }
+        }
+        None
+    }
+
+    fn from_pushes(pushes: Vec<Vec<u8>>, pushnum: bool, input: u32, offset: u32) -> Self {
+        let mut inscription = Inscription {
+            input,
+            offset,
+            pushnum,
+            ..Default::default()
+        };
+        let mut fields = vec![];
+        let mut remaining = &pushes[..];
+        loop {
+            match remaining {
+                [tag, body @ ..] if tag.is_empty() => {
+                    inscription.body = Some(body.concat());
+                    break;
+                }
+                [tag, value, rest @ ..] => {
+                    fields.push((tag, value));
+                    remaining = rest;
+                }
+                [_] => {
+                    inscription.incomplete_field = true;
+                    break;
+                }
+                [] => break,
+            }
+        }
+
+        let mut seen = vec![];
+        let mut metadata: Option<Vec<u8>> = None;
+        for (tag, value) in fields {
+            let tag = match tag[..] {
+                [tag] => tag,
+                _ => {
+                    inscription.unrecognized_even_field |= tag.first().map(|byte| byte % 2 == 0).unwrap_or(false);
+                    continue;
+                }
+            };
+            let repeatable = tag == PARENT_TAG || tag == METADATA_TAG;
+            if !repeatable && seen.contains(&tag) {
+                inscription.duplicate_field = true;
+                continue;
+            }
+            seen.push(tag);
+            match tag {
+                CONTENT_TYPE_TAG => inscription.content_type = Some(String::from_utf8_lossy(value).into_owned()),
+                POINTER_TAG => inscription.pointer = decode_integer(value),
+                PARENT_TAG => inscription.parents.extend(decode_inscription_id(value)),
+                METADATA_TAG => metadata.get_or_insert_with(Vec::new).extend_from_slice(value),
+                METAPROTOCOL_TAG => inscription.metaprotocol = Some(String::from_utf8_lossy(value).into_owned()),
+                CONTENT_ENCODING_TAG => inscription.content_encoding = Some(value.clone()),
+                DELEGATE_TAG => inscription.delegate = decode_inscription_id(value),
+                RUNE_TAG => inscription.rune = Some(value.clone()),
+                tag if tag % 2 == 0 => inscription.unrecognized_even_field = true,
+                _ => (),
+            }
+        }
+        inscription.metadata = metadata;
+        inscription
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn input(&self) -> u32 {
+        self.input
+    }
+
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    pub fn content_encoding(&self) -> Option<&[u8]> {
+        self.content_encoding.as_deref()
+    }
+
+    pub fn pointer(&self) -> Option<u64> {
+        self.pointer
+    }
+
+    pub fn parents(&self) -> &[String] {
+        &self.parents
+    }
+
+    pub fn delegate(&self) -> Option<&str> {
+        self.delegate.as_deref()
+    }
+
+    pub fn metadata(&self) -> Option<&[u8]> {
+        self.metadata.as_deref()
+    }
+
+    pub fn metaprotocol(&self) -> Option<&str> {
+        self.metaprotocol.as_deref()
+    }
+
+    pub fn rune(&self) -> Option<&[u8]> {
+        self.rune.as_deref()
+    }
+
+    pub fn body(&self) -> Option<&[u8]> {
+        self.body.as_deref()
+    }
+
+    pub fn is_cursed(&self) -> bool {
+        self.pushnum || self.duplicate_field || self.incomplete_field || self.unrecognized_even_field
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        let media_type = self
+            .content_type()
+            .and_then(|content_type| content_type.split(';').next())
+            .map(str::trim)
+            .unwrap_or("");
+        match media_type {
+            "text/plain" => "txt",
+            "text/html" => "html",
+            "text/css" => "css",
+            "text/javascript" | "application/javascript" => "js",
+            "text/markdown" => "md",
+            "application/json" => "json",
+            "application/pdf" => "pdf",
+            "image/png" => "png",
+            "image/jpeg" => "jpg",
+            "image/gif" => "gif",
+            "image/webp" => "webp",
+            "image/avif" => "avif",
+            "image/svg+xml" => "svg",
+            "audio/mpeg" => "mp3",
+            "audio/wav" => "wav",
+            "video/mp4" => "mp4",
+            "video/webm" => "webm",
+            "model/gltf-binary" => "glb",
+            _ => "bin",
+        }
+    }
+}
+
+fn decode_integer(value: &[u8]) -> Option<u64> {
+    if value.len() > 8 {
+        return None;
+    }
+    Some(value.iter().rev().fold(0, |number, &byte| number << 8 | byte as u64))
+}
+
+fn decode_inscription_id(value: &[u8]) -> Option<String> {
+    if value.len() < 32 || value.len() > 36 {
+        return None;
+    }
+    let txid = Txid::new(*array_ref!(value, 0, 32));
+    let index = decode_integer(&value[32..])?;
+    Some(format!("{}i{}", txid, index))
+}
+
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct InscriptionRecord {
+    id: String,
+    #[cfg_attr(feature = "writer", serde(flatten))]
+    inscription: Inscription,
+}
+
+impl InscriptionRecord {
+    pub fn new(txid: &Txid, inscription: Inscription) -> Self {
+        Self {
+            id: format!("{}i{}", txid, inscription.index()),
+            inscription,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn inscription(&self) -> &Inscription {
+        &self.inscription
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Inscription;
+
+    const BAD_ENVELOPE: &str = "0063036f7264ac68";
+    const TEXT_ENVELOPE: &str = "0063036f726401010a746578742f706c61696e0002686968";
+
+    #[test]
+    fn skips_the_envelopes_that_are_not_well_formed() {
+        let tapscript = hex::decode([BAD_ENVELOPE, TEXT_ENVELOPE].concat()).unwrap();
+        let inscriptions = Inscription::from_tapscript(&tapscript, 0);
+        assert_eq!(inscriptions.len(), 1);
+        assert_eq!(inscriptions[0].offset(), 0);
+        assert_eq!(inscriptions[0].content_type(), Some("text/plain"));
+        assert_eq!(inscriptions[0].body(), Some(&b"hi"[..]));
+    }
+
+    #[test]
+    fn ignores_scripts_that_do_not_parse() {
+        let tapscript = hex::decode([TEXT_ENVELOPE, "4c"].concat()).unwrap();
+        assert!(Inscription::from_tapscript(&tapscript, 0).is_empty());
+    }
+}
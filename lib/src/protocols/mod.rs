@@ -0,0 +1,3 @@
+pub mod inscription;
+pub mod nulldata;
+pub mod runes;
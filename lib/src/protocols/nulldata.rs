@@ -0,0 +1,246 @@
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::convert::TryInto;
+use std::fmt;
+
+use crate::amount::Amount;
+use crate::blockchain::script::opcodes::{OP_13, OP_RETURN};
+use crate::blockchain::script::{BitcoinScript, Instruction};
+use crate::blockchain::transactions::Transaction;
+use crate::types::Txid;
+
+pub const OMNI_PREFIX: &[u8] = b"omni";
+pub const COUNTERPARTY_PREFIX: &[u8] = b"CNTRPRTY";
+pub const WITNESS_COMMITMENT_PREFIX: &[u8] = &[0xaa, 0x21, 0xa9, 0xed];
+pub const VERIBLOCK_PAYLOAD_SIZE: usize = 80;
+pub const VERIBLOCK_HEADER_SIZE: usize = 64;
+// veriblock mainnet started publishing its headers to bitcoin in march 2019
+pub const VERIBLOCK_MIN_TIMESTAMP: u32 = 1_550_000_000;
+
+pub struct NullData<'a> {
+    marker: Option<u8>,
+    pushes: Vec<&'a [u8]>,
+}
+
+impl<'a> NullData<'a> {
+    pub fn from_script(script: &'a BitcoinScript) -> Option<Self> {
+        let mut instructions = script.instructions();
+        if instructions.next()? != Ok(Instruction::Op(OP_RETURN)) {
+            return None;
+        }
+        let mut marker = None;
+        let mut pushes = vec![];
+        for instruction in instructions {
+            match instruction.ok()? {
+                Instruction::PushBytes(bytes) => pushes.push(bytes),
+                Instruction::Op(opcode) if pushes.is_empty() && marker.is_none() => marker = Some(opcode),
+                Instruction::Op(_) => return None,
+            }
+        }
+        Some(Self { marker, pushes })
+    }
+
+    pub fn marker(&self) -> Option<u8> {
+        self.marker
+    }
+
+    pub fn pushes(&self) -> &[&'a [u8]] {
+        &self.pushes
+    }
+
+    pub fn payload(&self) -> Vec<u8> {
+        self.pushes.concat()
+    }
+
+    pub fn size(&self) -> usize {
+        self.pushes.iter().map(|push| push.len()).sum()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Protocol {
+    Omni,
+    Counterparty,
+    Veriblock,
+    WitnessCommitment,
+    Runes,
+    Registered(String),
+    Unknown,
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Protocol::Omni => formatter.pad("omni"),
+            Protocol::Counterparty => formatter.pad("counterparty"),
+            Protocol::Veriblock => formatter.pad("veriblock"),
+            Protocol::WitnessCommitment => formatter.pad("witness_commitment"),
+            Protocol::Runes => formatter.pad("runes"),
+            Protocol::Registered(ref name) => formatter.pad(name),
+            Protocol::Unknown => formatter.pad("unknown"),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ProtocolRegistry {
+    prefixes: Vec<(String, Vec<u8>)>,
+}
+
+impl ProtocolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<S: Into<String>>(&mut self, name: S, prefix: Vec<u8>) {
+        self.prefixes.push((name.into(), prefix));
+    }
+
+    // the first input txid is the ARC4 key counterparty uses to obfuscate its messages
+    pub fn identify(&self, data: &NullData<'_>, first_input: Option<&Txid>) -> Protocol {
+        if data.marker() == Some(OP_13) {
+            return Protocol::Runes;
+        }
+        let payload = data.payload();
+        if let Some((name, _)) = self.prefixes.iter().find(|(_, prefix)| payload.starts_with(prefix)) {
+            return Protocol::Registered(name.clone());
+        }
+        if payload.starts_with(OMNI_PREFIX) {
+            Protocol::Omni
+        } else if payload.starts_with(WITNESS_COMMITMENT_PREFIX) && payload.len() >= 36 {
+            Protocol::WitnessCommitment
+        } else if Self::is_counterparty(&payload, first_input) {
+            Protocol::Counterparty
+        } else if Self::is_veriblock(data) {
+            Protocol::Veriblock
+        } else {
+            Protocol::Unknown
+        }
+    }
+
+    fn is_counterparty(payload: &[u8], first_input: Option<&Txid>) -> bool {
+        if payload.starts_with(COUNTERPARTY_PREFIX) {
+            return true;
+        }
+        match first_input {
+            Some(txid) if payload.len() >= COUNTERPARTY_PREFIX.len() => {
+                arc4(&txid.to_display_bytes(), &payload[..COUNTERPARTY_PREFIX.len()]) == COUNTERPARTY_PREFIX
+            }
+            _ => false,
+        }
+    }
+
+    // a proof of proof publication is a single 80 byte push: the 64 byte veriblock header, big endian, and
+    // the miner address. It carries no prefix, so the header fields have to hold sane values instead
+    fn is_veriblock(data: &NullData<'_>) -> bool {
+        let payload = match (data.marker(), data.pushes()) {
+            (None, [payload]) if payload.len() == VERIBLOCK_PAYLOAD_SIZE => payload,
+            _ => return false,
+        };
+        let header = &payload[..VERIBLOCK_HEADER_SIZE];
+        let field = |offset: usize| u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+        let height = field(0);
+        let version = u16::from_be_bytes([header[4], header[5]]);
+        let timestamp = field(52);
+        let difficulty = field(56);
+        (1..1 << 31).contains(&height)
+            && (1..=0xff).contains(&version)
+            && (VERIBLOCK_MIN_TIMESTAMP..1 << 31).contains(&timestamp)
+            && difficulty != 0
+    }
+}
+
+pub fn arc4(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut state: Vec<u8> = (0..=255).collect();
+    let mut j: u8 = 0;
+    for i in 0..256 {
+        j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+        state.swap(i, j as usize);
+    }
+    let (mut i, mut j) = (0u8, 0u8);
+    data.iter()
+        .map(|byte| {
+            i = i.wrapping_add(1);
+            j = j.wrapping_add(state[i as usize]);
+            state.swap(i as usize, j as usize);
+            byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+        })
+        .collect()
+}
+
+#[derive(Default, Clone, Copy)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct ProtocolStats {
+    outputs: u64,
+    bytes: u64,
+    value: Amount,
+}
+
+impl ProtocolStats {
+    pub fn outputs(&self) -> u64 {
+        self.outputs
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    pub fn value(&self) -> Amount {
+        self.value
+    }
+
+    fn add(&mut self, other: &ProtocolStats) {
+        self.outputs += other.outputs;
+        self.bytes += other.bytes;
+        self.value = self.value.saturating_add(other.value);
+    }
+}
+
+#[derive(Default)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct NullDataStats {
+    #[cfg_attr(feature = "writer", serde(flatten))]
+    total: ProtocolStats,
+    protocols: BTreeMap<String, ProtocolStats>,
+}
+
+impl NullDataStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, protocol: &Protocol, data: &NullData<'_>, value: Amount) {
+        let stats = ProtocolStats {
+            outputs: 1,
+            bytes: data.size() as u64,
+            value,
+        };
+        self.total.add(&stats);
+        self.protocols.entry(protocol.to_string()).or_default().add(&stats);
+    }
+
+    pub fn record_transaction(&mut self, registry: &ProtocolRegistry, transaction: &Transaction) {
+        let first_input = transaction.inputs().first().map(|input| input.utxo().txid());
+        for output in transaction.outputs() {
+            if let Some(data) = NullData::from_script(output.pubkey()) {
+                self.record(&registry.identify(&data, first_input), &data, output.value());
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &NullDataStats) {
+        self.total.add(&other.total);
+        for (protocol, stats) in &other.protocols {
+            self.protocols.entry(protocol.clone()).or_default().add(stats);
+        }
+    }
+
+    pub fn total(&self) -> &ProtocolStats {
+        &self.total
+    }
+
+    pub fn protocols(&self) -> impl Iterator<Item = (&str, &ProtocolStats)> {
+        self.protocols.iter().map(|(name, stats)| (name.as_str(), stats))
+    }
+}
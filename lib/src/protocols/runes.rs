@@ -0,0 +1,830 @@
+#[cfg(feature = "writer")]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+use crate::blockchain::script::opcodes::{OP_13, OP_RETURN};
+use crate::blockchain::script::{BitcoinScript, Instruction};
+use crate::blockchain::transactions::Transaction;
+use crate::types::Txid;
+
+pub const MAGIC_NUMBER: u8 = OP_13;
+pub const MAX_DIVISIBILITY: u8 = 38;
+pub const MAX_SPACERS: u32 = 0b0000_0111_1111_1111_1111_1111_1111_1111;
+
+const TAG_BODY: u128 = 0;
+const TAG_DIVISIBILITY: u128 = 1;
+const TAG_FLAGS: u128 = 2;
+const TAG_SPACERS: u128 = 3;
+const TAG_RUNE: u128 = 4;
+const TAG_SYMBOL: u128 = 5;
+const TAG_PREMINE: u128 = 6;
+const TAG_CAP: u128 = 8;
+const TAG_AMOUNT: u128 = 10;
+const TAG_HEIGHT_START: u128 = 12;
+const TAG_HEIGHT_END: u128 = 14;
+const TAG_OFFSET_START: u128 = 16;
+const TAG_OFFSET_END: u128 = 18;
+const TAG_MINT: u128 = 20;
+const TAG_POINTER: u128 = 22;
+
+const FLAG_ETCHING: u32 = 0;
+const FLAG_TERMS: u32 = 1;
+const FLAG_TURBO: u32 = 2;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct RuneId {
+    block: u64,
+    tx: u32,
+}
+
+impl RuneId {
+    pub fn new(block: u64, tx: u32) -> Option<Self> {
+        if block == 0 && tx > 0 {
+            return None;
+        }
+        Some(Self { block, tx })
+    }
+
+    pub fn block(&self) -> u64 {
+        self.block
+    }
+
+    pub fn tx(&self) -> u32 {
+        self.tx
+    }
+
+    // edict ids are delta encoded, the tx delta is absolute when the block changes
+    fn next(self, block: u128, tx: u128) -> Option<Self> {
+        let block = self.block.checked_add(u64::try_from(block).ok()?)?;
+        let tx = if block == self.block {
+            self.tx.checked_add(u32::try_from(tx).ok()?)?
+        } else {
+            u32::try_from(tx).ok()?
+        };
+        Self::new(block, tx)
+    }
+}
+
+impl fmt::Display for RuneId {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}:{}", self.block, self.tx)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Rune(u128);
+
+impl Rune {
+    pub fn new(value: u128) -> Self {
+        Self(value)
+    }
+
+    pub fn value(self) -> u128 {
+        self.0
+    }
+
+    // the spacers after the last letter are not shown, as ord does
+    pub fn spaced(self, spacers: u32) -> String {
+        let name = self.to_string();
+        let mut spaced = String::new();
+        for (index, letter) in name.chars().enumerate() {
+            spaced.push(letter);
+            if index + 1 < name.len() && spacers & (1 << index) != 0 {
+                spaced.push('•');
+            }
+        }
+        spaced
+    }
+}
+
+impl fmt::Display for Rune {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == u128::MAX {
+            return formatter.write_str("BCGDENLQRQWDSLRUGSNLBTMFIJAV");
+        }
+        let mut value = self.0 + 1;
+        let mut letters = vec![];
+        while value > 0 {
+            letters.push(b'A' + ((value - 1) % 26) as u8);
+            value = (value - 1) / 26;
+        }
+        letters.reverse();
+        formatter.write_str(&String::from_utf8_lossy(&letters))
+    }
+}
+
+impl FromStr for Rune {
+    type Err = RuneError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let mut value = 0u128;
+        for (index, letter) in name.chars().enumerate() {
+            if index > 0 {
+                value = value.checked_add(1).ok_or(RuneError::Range)?;
+            }
+            value = value.checked_mul(26).ok_or(RuneError::Range)?;
+            match letter {
+                'A'..='Z' => {
+                    value = value
+                        .checked_add(letter as u128 - 'A' as u128)
+                        .ok_or(RuneError::Range)?
+                }
+                _ => return Err(RuneError::Character(letter)),
+            }
+        }
+        Ok(Rune(value))
+    }
+}
+
+#[cfg(feature = "writer")]
+impl Serialize for Rune {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "writer")]
+impl<'de> Deserialize<'de> for Rune {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = <&str as Deserialize<'de>>::deserialize(deserializer)?;
+        Rune::from_str(name).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RuneError {
+    #[error("invalid rune character: {0}")]
+    Character(char),
+    #[error("rune name out of range")]
+    Range,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct Edict {
+    id: RuneId,
+    amount: u128,
+    output: u32,
+}
+
+impl Edict {
+    pub fn id(&self) -> RuneId {
+        self.id
+    }
+
+    pub fn amount(&self) -> u128 {
+        self.amount
+    }
+
+    pub fn output(&self) -> u32 {
+        self.output
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct Terms {
+    amount: Option<u128>,
+    cap: Option<u128>,
+    height: (Option<u64>, Option<u64>),
+    offset: (Option<u64>, Option<u64>),
+}
+
+impl Terms {
+    pub fn amount(&self) -> Option<u128> {
+        self.amount
+    }
+
+    pub fn cap(&self) -> Option<u128> {
+        self.cap
+    }
+
+    pub fn height(&self) -> (Option<u64>, Option<u64>) {
+        self.height
+    }
+
+    pub fn offset(&self) -> (Option<u64>, Option<u64>) {
+        self.offset
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct Etching {
+    divisibility: Option<u8>,
+    premine: Option<u128>,
+    rune: Option<Rune>,
+    spacers: Option<u32>,
+    symbol: Option<char>,
+    terms: Option<Terms>,
+    turbo: bool,
+}
+
+impl Etching {
+    pub fn divisibility(&self) -> Option<u8> {
+        self.divisibility
+    }
+
+    pub fn premine(&self) -> Option<u128> {
+        self.premine
+    }
+
+    pub fn rune(&self) -> Option<Rune> {
+        self.rune
+    }
+
+    pub fn spaced_rune(&self) -> Option<String> {
+        self.rune.map(|rune| rune.spaced(self.spacers.unwrap_or_default()))
+    }
+
+    pub fn spacers(&self) -> Option<u32> {
+        self.spacers
+    }
+
+    pub fn symbol(&self) -> Option<char> {
+        self.symbol
+    }
+
+    pub fn terms(&self) -> Option<&Terms> {
+        self.terms.as_ref()
+    }
+
+    pub fn turbo(&self) -> bool {
+        self.turbo
+    }
+
+    pub fn supply(&self) -> Option<u128> {
+        let premine = self.premine.unwrap_or_default();
+        let terms = self.terms.unwrap_or_default();
+        let minted = terms
+            .cap
+            .unwrap_or_default()
+            .checked_mul(terms.amount.unwrap_or_default())?;
+        premine.checked_add(minted)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "writer", serde(rename_all = "snake_case"))]
+pub enum Flaw {
+    EdictOutput,
+    EdictRuneId,
+    InvalidScript,
+    Opcode,
+    SupplyOverflow,
+    TrailingIntegers,
+    TruncatedField,
+    UnrecognizedEvenTag,
+    UnrecognizedFlag,
+    Varint,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "writer", serde(tag = "kind", rename_all = "snake_case"))]
+pub enum Artifact {
+    Runestone {
+        vout: u32,
+        edicts: Vec<Edict>,
+        etching: Option<Etching>,
+        mint: Option<RuneId>,
+        pointer: Option<u32>,
+    },
+    Cenotaph {
+        vout: u32,
+        flaws: Vec<Flaw>,
+        etching: Option<Rune>,
+        mint: Option<RuneId>,
+    },
+}
+
+impl Artifact {
+    // only the first OP_RETURN OP_13 output of a transaction is considered a runestone
+    pub fn from_transaction(transaction: &Transaction) -> Option<Self> {
+        let outputs = transaction.outputs().len();
+        let (vout, payload) = transaction
+            .outputs()
+            .iter()
+            .enumerate()
+            .find_map(|(vout, output)| payload(output.pubkey()).map(|payload| (vout as u32, payload)))?;
+        let mut flaws = vec![];
+        let payload = match payload {
+            Ok(payload) => payload,
+            Err(flaw) => {
+                return Some(Artifact::Cenotaph {
+                    vout,
+                    flaws: vec![flaw],
+                    etching: None,
+                    mint: None,
+                })
+            }
+        };
+        let integers = match decode_integers(&payload) {
+            Ok(integers) => integers,
+            Err(flaw) => {
+                flaws.push(flaw);
+                vec![]
+            }
+        };
+        let Message { mut fields, edicts } = Message::from_integers(&integers, outputs, &mut flaws);
+
+        let mut flags = take(&mut fields, TAG_FLAGS, |[flags]| Some(flags)).unwrap_or_default();
+        let etching = if take_flag(&mut flags, FLAG_ETCHING) {
+            Some(Etching {
+                divisibility: take(&mut fields, TAG_DIVISIBILITY, |[divisibility]| {
+                    u8::try_from(divisibility)
+                        .ok()
+                        .filter(|&divisibility| divisibility <= MAX_DIVISIBILITY)
+                }),
+                premine: take(&mut fields, TAG_PREMINE, |[premine]| Some(premine)),
+                rune: take(&mut fields, TAG_RUNE, |[rune]| Some(Rune(rune))),
+                spacers: take(&mut fields, TAG_SPACERS, |[spacers]| {
+                    u32::try_from(spacers).ok().filter(|&spacers| spacers <= MAX_SPACERS)
+                }),
+                symbol: take(&mut fields, TAG_SYMBOL, |[symbol]| {
+                    u32::try_from(symbol).ok().and_then(std::char::from_u32)
+                }),
+                terms: if take_flag(&mut flags, FLAG_TERMS) {
+                    Some(Terms {
+                        cap: take(&mut fields, TAG_CAP, |[cap]| Some(cap)),
+                        height: (
+                            take(&mut fields, TAG_HEIGHT_START, |[height]| u64::try_from(height).ok()),
+                            take(&mut fields, TAG_HEIGHT_END, |[height]| u64::try_from(height).ok()),
+                        ),
+                        amount: take(&mut fields, TAG_AMOUNT, |[amount]| Some(amount)),
+                        offset: (
+                            take(&mut fields, TAG_OFFSET_START, |[offset]| u64::try_from(offset).ok()),
+                            take(&mut fields, TAG_OFFSET_END, |[offset]| u64::try_from(offset).ok()),
+                        ),
+                    })
+                } else {
+                    None
+                },
+                turbo: take_flag(&mut flags, FLAG_TURBO),
+            })
+        } else {
+            None
+        };
+        let mint = take(&mut fields, TAG_MINT, |[block, tx]| {
+            RuneId::new(u64::try_from(block).ok()?, u32::try_from(tx).ok()?)
+        });
+        let pointer = take(&mut fields, TAG_POINTER, |[pointer]| {
+            u32::try_from(pointer)
+                .ok()
+                .filter(|&pointer| (pointer as usize) < outputs)
+        });
+
+        if etching
+            .as_ref()
+            .map(|etching| etching.supply().is_none())
+            .unwrap_or(false)
+        {
+            flaws.push(Flaw::SupplyOverflow);
+        }
+        if flags != 0 {
+            flaws.push(Flaw::UnrecognizedFlag);
+        }
+        if fields.keys().any(|tag| tag % 2 == 0) {
+            flaws.push(Flaw::UnrecognizedEvenTag);
+        }
+
+        if flaws.is_empty() {
+            Some(Artifact::Runestone {
+                vout,
+                edicts,
+                etching,
+                mint,
+                pointer,
+            })
+        } else {
+            Some(Artifact::Cenotaph {
+                vout,
+                flaws,
+                etching: etching.and_then(|etching| etching.rune),
+                mint,
+            })
+        }
+    }
+
+    pub fn vout(&self) -> u32 {
+        match *self {
+            Artifact::Runestone { vout, .. } | Artifact::Cenotaph { vout, .. } => vout,
+        }
+    }
+
+    pub fn mint(&self) -> Option<RuneId> {
+        match *self {
+            Artifact::Runestone { mint, .. } | Artifact::Cenotaph { mint, .. } => mint,
+        }
+    }
+
+    pub fn etched_rune(&self) -> Option<Rune> {
+        match *self {
+            Artifact::Runestone { ref etching, .. } => etching.as_ref().and_then(Etching::rune),
+            Artifact::Cenotaph { etching, .. } => etching,
+        }
+    }
+
+    pub fn is_etching(&self) -> bool {
+        match *self {
+            Artifact::Runestone { ref etching, .. } => etching.is_some(),
+            Artifact::Cenotaph { etching, .. } => etching.is_some(),
+        }
+    }
+
+    pub fn edicts(&self) -> &[Edict] {
+        match *self {
+            Artifact::Runestone { ref edicts, .. } => edicts,
+            Artifact::Cenotaph { .. } => &[],
+        }
+    }
+
+    pub fn is_cenotaph(&self) -> bool {
+        matches!(*self, Artifact::Cenotaph { .. })
+    }
+}
+
+struct Message {
+    fields: BTreeMap<u128, VecDeque<u128>>,
+    edicts: Vec<Edict>,
+}
+
+impl Message {
+    fn from_integers(integers: &[u128], outputs: usize, flaws: &mut Vec<Flaw>) -> Self {
+        let mut fields = BTreeMap::<u128, VecDeque<u128>>::new();
+        let mut edicts = vec![];
+        for index in (0..integers.len()).step_by(2) {
+            let tag = integers[index];
+            if tag == TAG_BODY {
+                let mut id = RuneId::default();
+                for chunk in integers[index + 1..].chunks(4) {
+                    if chunk.len() != 4 {
+                        flaws.push(Flaw::TrailingIntegers);
+                        break;
+                    }
+                    let next = match id.next(chunk[0], chunk[1]) {
+                        Some(next) => next,
+                        None => {
+                            flaws.push(Flaw::EdictRuneId);
+                            break;
+                        }
+                    };
+                    // an output equal to the amount of outputs splits the amount among all of them
+                    let output = match u32::try_from(chunk[3])
+                        .ok()
+                        .filter(|&output| output as usize <= outputs)
+                    {
+                        Some(output) => output,
+                        None => {
+                            flaws.push(Flaw::EdictOutput);
+                            break;
+                        }
+                    };
+                    id = next;
+                    edicts.push(Edict {
+                        id,
+                        amount: chunk[2],
+                        output,
+                    });
+                }
+                break;
+            }
+            match integers.get(index + 1) {
+                Some(&value) => fields.entry(tag).or_default().push_back(value),
+                None => {
+                    flaws.push(Flaw::TruncatedField);
+                    break;
+                }
+            }
+        }
+        Self { fields, edicts }
+    }
+}
+
+// values rejected by the decoder are kept in the fields, making unknown even tags a flaw
+fn take<const N: usize, T>(
+    fields: &mut BTreeMap<u128, VecDeque<u128>>,
+    tag: u128,
+    decode: impl FnOnce([u128; N]) -> Option<T>,
+) -> Option<T> {
+    let values = fields.get_mut(&tag)?;
+    if values.len() < N {
+        return None;
+    }
+    let mut array = [0; N];
+    array
+        .iter_mut()
+        .zip(values.iter())
+        .for_each(|(slot, &value)| *slot = value);
+    let value = decode(array)?;
+    values.drain(..N);
+    if values.is_empty() {
+        fields.remove(&tag);
+    }
+    Some(value)
+}
+
+fn take_flag(flags: &mut u128, flag: u32) -> bool {
+    let mask = 1 << flag;
+    let set = *flags & mask != 0;
+    *flags &= !mask;
+    set
+}
+
+fn payload(script: &BitcoinScript) -> Option<Result<Vec<u8>, Flaw>> {
+    let mut instructions = script.instructions();
+    if instructions.next()? != Ok(Instruction::Op(OP_RETURN)) {
+        return None;
+    }
+    if instructions.next()? != Ok(Instruction::Op(MAGIC_NUMBER)) {
+        return None;
+    }
+    let mut payload = vec![];
+    for instruction in instructions {
+        match instruction {
+            Ok(Instruction::PushBytes(bytes)) => payload.extend_from_slice(bytes),
+            Ok(Instruction::Op(_)) => return Some(Err(Flaw::Opcode)),
+            Err(_) => return Some(Err(Flaw::InvalidScript)),
+        }
+    }
+    Some(Ok(payload))
+}
+
+pub fn decode_integers(payload: &[u8]) -> Result<Vec<u128>, Flaw> {
+    let mut integers = vec![];
+    let mut position = 0;
+    while position < payload.len() {
+        let (integer, size) = decode_leb128(&payload[position..])?;
+        integers.push(integer);
+        position += size;
+    }
+    Ok(integers)
+}
+
+// u128 LEB128, rejecting encodings longer than 19 bytes or overflowing 128 bits
+pub fn decode_leb128(buffer: &[u8]) -> Result<(u128, usize), Flaw> {
+    let mut integer = 0u128;
+    for (index, &byte) in buffer.iter().enumerate() {
+        if index > 18 {
+            return Err(Flaw::Varint);
+        }
+        let value = u128::from(byte & 0x7f);
+        if index == 18 && value & 0x7c != 0 {
+            return Err(Flaw::Varint);
+        }
+        integer |= value << (7 * index);
+        if byte & 0x80 == 0 {
+            return Ok((integer, index + 1));
+        }
+    }
+    Err(Flaw::Varint)
+}
+
+pub fn encode_leb128(mut integer: u128, buffer: &mut Vec<u8>) {
+    while integer >> 7 > 0 {
+        buffer.push((integer as u8) | 0x80);
+        integer >>= 7;
+    }
+    buffer.push(integer as u8);
+}
+
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct RuneRecord {
+    txid: Txid,
+    #[cfg_attr(feature = "writer", serde(flatten))]
+    artifact: Artifact,
+}
+
+impl RuneRecord {
+    pub fn new(txid: Txid, artifact: Artifact) -> Self {
+        Self { txid, artifact }
+    }
+
+    pub fn txid(&self) -> &Txid {
+        &self.txid
+    }
+
+    pub fn artifact(&self) -> &Artifact {
+        &self.artifact
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::Amount;
+    use crate::blockchain::transactions::Output;
+
+    // the runestone in the first output, followed by as many outputs as needed
+    fn decipher(script: Vec<u8>, outputs: usize) -> Artifact {
+        let mut all = vec![Output::new(Amount::ZERO, BitcoinScript::new(script))];
+        all.resize(outputs, Output::new(Amount::ZERO, BitcoinScript::new(vec![0x51])));
+        Artifact::from_transaction(&Transaction::new(2, vec![], all, 0)).unwrap()
+    }
+
+    fn decipher_integers(integers: &[u128], outputs: usize) -> Artifact {
+        let mut payload = vec![];
+        integers
+            .iter()
+            .for_each(|&integer| encode_leb128(integer, &mut payload));
+        decipher(
+            [&[OP_RETURN, MAGIC_NUMBER, payload.len() as u8][..], &payload].concat(),
+            outputs,
+        )
+    }
+
+    fn cenotaph(flaws: Vec<Flaw>) -> Artifact {
+        Artifact::Cenotaph {
+            vout: 0,
+            flaws,
+            etching: None,
+            mint: None,
+        }
+    }
+
+    #[test]
+    fn decodes_leb128() {
+        for &integer in &[0, 1, 127, 128, 255, 16_384, u64::MAX as u128, u128::MAX] {
+            let mut buffer = vec![];
+            encode_leb128(integer, &mut buffer);
+            assert_eq!(decode_leb128(&buffer), Ok((integer, buffer.len())));
+        }
+        let mut largest = vec![0xff; 18];
+        largest.push(0x03);
+        assert_eq!(decode_leb128(&largest), Ok((u128::MAX, 19)));
+        largest[18] = 0x04;
+        assert_eq!(decode_leb128(&largest), Err(Flaw::Varint));
+        assert_eq!(decode_leb128(&[0x80; 19]), Err(Flaw::Varint));
+        assert_eq!(decode_leb128(&[0x80]), Err(Flaw::Varint));
+    }
+
+    #[test]
+    fn names_runes() {
+        let names = [
+            (0, "A"),
+            (25, "Z"),
+            (26, "AA"),
+            (27, "AB"),
+            (701, "ZZ"),
+            (702, "AAA"),
+            (u128::MAX, "BCGDENLQRQWDSLRUGSNLBTMFIJAV"),
+        ];
+        for &(value, name) in &names {
+            assert_eq!(Rune(value).to_string(), name);
+            assert_eq!(name.parse::<Rune>().unwrap(), Rune(value));
+        }
+        assert!("BCGDENLQRQWDSLRUGSNLBTMFIJAW".parse::<Rune>().is_err());
+        assert!("a".parse::<Rune>().is_err());
+        assert_eq!(Rune(702).spaced(0b01), "A•AA");
+        assert_eq!(Rune(702).spaced(0b111), "A•A•A");
+    }
+
+    #[test]
+    fn deciphers_edicts() {
+        let artifact = decipher_integers(&[TAG_BODY, 1, 1, 2, 0, 0, 3, 4, 1], 2);
+        assert_eq!(
+            artifact,
+            Artifact::Runestone {
+                vout: 0,
+                edicts: vec![
+                    Edict {
+                        id: RuneId::new(1, 1).unwrap(),
+                        amount: 2,
+                        output: 0,
+                    },
+                    Edict {
+                        id: RuneId::new(1, 4).unwrap(),
+                        amount: 4,
+                        output: 1,
+                    },
+                ],
+                etching: None,
+                mint: None,
+                pointer: None,
+            }
+        );
+    }
+
+    #[test]
+    fn deciphers_an_etching() {
+        let integers = [
+            TAG_FLAGS,
+            1 << FLAG_ETCHING | 1 << FLAG_TERMS,
+            TAG_RUNE,
+            4,
+            TAG_DIVISIBILITY,
+            2,
+            TAG_SYMBOL,
+            'R' as u128,
+            TAG_PREMINE,
+            100,
+            TAG_CAP,
+            10,
+            TAG_AMOUNT,
+            5,
+            TAG_POINTER,
+            0,
+        ];
+        let etching = Etching {
+            divisibility: Some(2),
+            premine: Some(100),
+            rune: Some(Rune(4)),
+            spacers: None,
+            symbol: Some('R'),
+            terms: Some(Terms {
+                amount: Some(5),
+                cap: Some(10),
+                height: (None, None),
+                offset: (None, None),
+            }),
+            turbo: false,
+        };
+        assert_eq!(etching.supply(), Some(150));
+        assert_eq!(
+            decipher_integers(&integers, 1),
+            Artifact::Runestone {
+                vout: 0,
+                edicts: vec![],
+                etching: Some(etching),
+                mint: None,
+                pointer: Some(0),
+            }
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_odd_tags() {
+        let artifact = decipher_integers(&[TAG_MINT, 1, TAG_MINT, 1, 9, 5], 1);
+        assert_eq!(artifact.mint(), RuneId::new(1, 1));
+        assert!(!artifact.is_cenotaph());
+    }
+
+    #[test]
+    fn deciphers_cenotaphs() {
+        let flaws = [
+            (vec![126, 0], Flaw::UnrecognizedEvenTag),
+            (vec![TAG_FLAGS, 1 << 127], Flaw::UnrecognizedFlag),
+            (vec![TAG_FLAGS], Flaw::TruncatedField),
+            (vec![TAG_BODY, 1, 1, 2, 0, 5], Flaw::TrailingIntegers),
+            (vec![TAG_BODY, 1, 1, 2, 2], Flaw::EdictOutput),
+            (vec![TAG_BODY, 0, 1, 2, 0], Flaw::EdictRuneId),
+        ];
+        for (integers, flaw) in flaws.iter() {
+            assert_eq!(decipher_integers(integers, 1), cenotaph(vec![*flaw]));
+        }
+        assert_eq!(
+            decipher(vec![OP_RETURN, MAGIC_NUMBER, 0x69], 1),
+            cenotaph(vec![Flaw::Opcode])
+        );
+        assert_eq!(
+            decipher(vec![OP_RETURN, MAGIC_NUMBER, 0x4c], 1),
+            cenotaph(vec![Flaw::InvalidScript])
+        );
+        assert_eq!(
+            decipher(vec![OP_RETURN, MAGIC_NUMBER, 1, 0x80], 1),
+            cenotaph(vec![Flaw::Varint])
+        );
+    }
+
+    #[test]
+    fn keeps_the_etched_rune_of_a_cenotaph() {
+        let integers = [
+            TAG_FLAGS,
+            1 << FLAG_ETCHING | 1 << FLAG_TERMS,
+            TAG_RUNE,
+            4,
+            TAG_PREMINE,
+            u128::MAX,
+            TAG_CAP,
+            1,
+            TAG_AMOUNT,
+            1,
+        ];
+        assert_eq!(
+            decipher_integers(&integers, 1),
+            Artifact::Cenotaph {
+                vout: 0,
+                flaws: vec![Flaw::SupplyOverflow],
+                etching: Some(Rune(4)),
+                mint: None,
+            }
+        );
+    }
+
+    #[test]
+    fn only_reads_op_return_op_13_outputs() {
+        let transaction = Transaction::new(
+            2,
+            vec![],
+            vec![Output::new(Amount::ZERO, BitcoinScript::new(vec![OP_RETURN, 0x5c]))],
+            0,
+        );
+        assert_eq!(Artifact::from_transaction(&transaction), None);
+    }
+}
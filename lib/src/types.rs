@@ -6,6 +6,9 @@ use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use sha2::Digest;
 use sha2::Sha256;
 use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
 
 #[derive(Copy, Clone)]
 pub struct VarInt(u64);
@@ -26,6 +29,15 @@ impl VarInt {
     pub fn from_8_bytes<O: ByteOrder>(data: &[u8; 8]) -> Self {
         Self(O::read_u64(data))
     }
+
+    pub fn to_bytes(self) -> Vec<u8> {
+        match self.0 {
+            0x00..=0xfc => vec![self.0 as u8],
+            0xfd..=0xffff => [&[0xfd][..], &(self.0 as u16).to_le_bytes()].concat(),
+            0x10000..=0xffff_ffff => [&[0xfe][..], &(self.0 as u32).to_le_bytes()].concat(),
+            _ => [&[0xff][..], &self.0.to_le_bytes()].concat(),
+        }
+    }
 }
 
 impl Deref for VarInt {
@@ -51,12 +63,85 @@ impl<'de> Deserialize<'de> for VarInt {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct BlockTarget([u8; 4]);
 
 impl BlockTarget {
     pub fn new(data: [u8; 4]) -> Self {
         Self(data)
     }
+
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.0
+    }
+
+    // the compact representation of the target as stored little endian in the header
+    pub fn to_compact(&self) -> u32 {
+        u32::from_le_bytes(self.0)
+    }
+
+    // the expected number of hashes to find a block meeting the target, 2^256 / (target + 1) as the reference
+    // client computes it. Invalid targets give no work, and the work saturates at u128::MAX, far above the
+    // one of any block mined
+    pub fn work(&self) -> u128 {
+        let compact = self.to_compact();
+        let exponent = compact >> 24;
+        let mantissa = compact & 0x007f_ffff;
+        let negative = compact & 0x0080_0000 != 0;
+        let overflow = mantissa != 0
+            && (exponent > 34 || (mantissa > 0xff && exponent > 33) || (mantissa > 0xffff && exponent > 32));
+        if negative || overflow {
+            return 0;
+        }
+        // the target as its high and low 128 bits
+        let target = match exponent {
+            0..=3 => (0, (mantissa >> (8 * (3 - exponent))) as u128),
+            _ => {
+                let shift = 8 * (exponent - 3);
+                match shift {
+                    0..=127 => ((mantissa as u128) >> (128 - shift), (mantissa as u128) << shift),
+                    _ => ((mantissa as u128) << (shift - 128), 0),
+                }
+            }
+        };
+        match target {
+            (0, 0) => 0,
+            (0, _) => u128::MAX,
+            (high, low) => {
+                // ~target / (target + 1) + 1, which fits as the target is at least 2^128
+                let divisor = match low.checked_add(1) {
+                    Some(low) => (high, low),
+                    None => (high + 1, 0),
+                };
+                divide(!high, !low, divisor).saturating_add(1)
+            }
+        }
+    }
+}
+
+// the quotient of a 256 bits number by a divisor of at least 2^128, both given as their high and low halves
+fn divide(high: u128, low: u128, divisor: (u128, u128)) -> u128 {
+    let mut remainder = (0u128, 0u128);
+    let mut quotient = 0u128;
+    for bit in (0..256).rev() {
+        let carry = remainder.0 >> 127 == 1;
+        let next = match bit {
+            128.. => (high >> (bit - 128)) & 1,
+            _ => (low >> bit) & 1,
+        };
+        remainder = ((remainder.0 << 1) | (remainder.1 >> 127), (remainder.1 << 1) | next);
+        if carry || remainder >= divisor {
+            let borrow = (remainder.1 < divisor.1) as u128;
+            remainder = (
+                remainder.0.wrapping_sub(divisor.0).wrapping_sub(borrow),
+                remainder.1.wrapping_sub(divisor.1),
+            );
+            if bit < 128 {
+                quotient |= 1 << bit;
+            }
+        }
+    }
+    quotient
 }
 
 impl<'a> From<&'a [u8]> for BlockTarget {
@@ -82,6 +167,9 @@ impl<'de> Deserialize<'de> for BlockTarget {
     }
 }
 
+// a double sha256 digest, stored in the internal byte order in which it is hashed and serialized,
+// and displayed reversed, the order used by the reference client RPC and block explorers
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct BitcoinHash([u8; 32]);
 
 impl BitcoinHash {
@@ -89,22 +177,54 @@ impl BitcoinHash {
         Self(data)
     }
 
-    pub fn from_little_endian(mut data: [u8; 32]) -> Self {
-        data.reverse();
-        Self(data)
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.iter().all(|&byte| byte == 0)
     }
 
     pub fn hash_header(digest: &[u8]) -> Self {
-        let mut hash = Sha256::digest(&Sha256::digest(digest)[..]);
-        hash.reverse();
+        let hash = Sha256::digest(&Sha256::digest(digest)[..]);
         Self::new(array_ref!(&hash[..], 0, 32).to_owned())
     }
+
+    // the bytes in the order they are displayed
+    pub fn to_display_bytes(&self) -> [u8; 32] {
+        let mut data = self.0;
+        data.reverse();
+        data
+    }
+}
+
+impl fmt::Display for BitcoinHash {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.pad(&hex::encode(self.to_display_bytes()))
+    }
+}
+
+impl fmt::Debug for BitcoinHash {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "BitcoinHash({})", self)
+    }
+}
+
+impl FromStr for BitcoinHash {
+    type Err = HashError;
+
+    fn from_str(hex_string: &str) -> Result<Self, Self::Err> {
+        let bytes = hex::decode(hex_string)?;
+        let mut data = <[u8; 32]>::try_from(&bytes[..]).map_err(|_| HashError::Length(bytes.len()))?;
+        data.reverse();
+        Ok(Self::new(data))
+    }
 }
 
 #[cfg(feature = "writer")]
 impl Serialize for BitcoinHash {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        hex::encode(self.0).serialize(serializer)
+        serializer.collect_str(self)
     }
 }
 
@@ -112,12 +232,94 @@ impl Serialize for BitcoinHash {
 impl<'de> Deserialize<'de> for BitcoinHash {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
         let hex_string = <&str as Deserialize<'de>>::deserialize(deserializer)?;
-        let bytes = hex::decode(hex_string).map_err(de::Error::custom)?;
-        let data = <&[u8; 32]>::try_from(&bytes[..]).map_err(de::Error::custom)?;
-        Ok(Self::new(data.to_owned()))
+        hex_string.parse().map_err(de::Error::custom)
     }
 }
 
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum HashError {
+    #[error("the hash is not valid hex: {0}")]
+    Hex(#[from] hex::FromHexError),
+    #[error("the hash is {0} bytes long instead of 32")]
+    Length(usize),
+}
+
+// typed hashes, so a txid can not be mistaken for a block hash, sharing the byte order of BitcoinHash
+macro_rules! hash_type {
+    ($name:ident) => {
+        #[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name(BitcoinHash);
+
+        impl $name {
+            pub fn new(data: [u8; 32]) -> Self {
+                Self(BitcoinHash::new(data))
+            }
+
+            pub fn hash(data: &[u8]) -> Self {
+                Self(BitcoinHash::hash_header(data))
+            }
+
+            pub fn from_raw_hash(hash: BitcoinHash) -> Self {
+                Self(hash)
+            }
+
+            pub fn to_raw_hash(self) -> BitcoinHash {
+                self.0
+            }
+
+            pub fn as_bytes(&self) -> &[u8; 32] {
+                self.0.as_bytes()
+            }
+
+            pub fn to_display_bytes(&self) -> [u8; 32] {
+                self.0.to_display_bytes()
+            }
+
+            pub fn is_zero(&self) -> bool {
+                self.0.is_zero()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.0, formatter)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(formatter, "{}({})", stringify!($name), self.0)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = HashError;
+
+            fn from_str(hex_string: &str) -> Result<Self, Self::Err> {
+                hex_string.parse().map(Self)
+            }
+        }
+
+        #[cfg(feature = "writer")]
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.0.serialize(serializer)
+            }
+        }
+
+        #[cfg(feature = "writer")]
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                BitcoinHash::deserialize(deserializer).map(Self)
+            }
+        }
+    };
+}
+
+hash_type!(Txid);
+hash_type!(Wtxid);
+hash_type!(BlockHash);
+
 pub struct BitcoinHashBuilder {
     contents: Vec<u8>,
 }
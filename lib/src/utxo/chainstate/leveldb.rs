@@ -0,0 +1,382 @@
+use byteorder::{ByteOrder, LittleEndian};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::vec;
+
+use super::log::LogReader;
+use super::table::{Table, TableEntries};
+use super::ChainstateError;
+
+const COMPARATOR: &[u8] = b"leveldb.BytewiseComparator";
+const LEVELS: u64 = 7;
+
+// the kinds of the entries, a deletion hides the older values of its key
+const DELETION: u8 = 0;
+const VALUE: u8 = 1;
+
+// the fields of the changes recorded in the manifest
+const EDIT_COMPARATOR: u64 = 1;
+const EDIT_LOG_NUMBER: u64 = 2;
+const EDIT_NEXT_FILE: u64 = 3;
+const EDIT_LAST_SEQUENCE: u64 = 4;
+const EDIT_COMPACT_POINTER: u64 = 5;
+const EDIT_DELETED_FILE: u64 = 6;
+const EDIT_NEW_FILE: u64 = 7;
+const EDIT_PREVIOUS_LOG: u64 = 9;
+
+// a user key with its value
+pub(crate) type KeyValue = (Vec<u8>, Vec<u8>);
+
+// a version of a key, the newest one has the highest sequence
+pub(crate) struct Entry {
+    pub(crate) key: Vec<u8>,
+    pub(crate) sequence: u64,
+    pub(crate) value: Option<Vec<u8>>,
+}
+
+// the table files of a level with the range of user keys they hold
+#[derive(Clone)]
+struct TableFile {
+    number: u64,
+    smallest: Vec<u8>,
+    largest: Vec<u8>,
+}
+
+// a read only view of a leveldb database as it was left on disk: the tables listed in the manifest and the
+// logs not yet written to them
+pub(crate) struct LevelDb {
+    path: PathBuf,
+    // the tables of level zero may overlap, the ones of each other level are sorted and do not
+    levels: Vec<Vec<TableFile>>,
+    memtable: BTreeMap<Vec<u8>, (u64, Option<Vec<u8>>)>,
+}
+
+impl LevelDb {
+    pub(crate) fn open(path: &Path) -> Result<Self, ChainstateError> {
+        let current = fs::read_to_string(path.join("CURRENT"))?;
+        let manifest = current
+            .strip_suffix('\n')
+            .filter(|name| !name.is_empty())
+            .ok_or(ChainstateError::Corrupted("the CURRENT file does not name a manifest"))?;
+        let mut files = BTreeMap::new();
+        let mut log = 0;
+        let mut previous = 0;
+        for edit in LogReader::new(BufReader::new(File::open(path.join(manifest))?)) {
+            let edit = edit?;
+            let mut data = &edit[..];
+            while !data.is_empty() {
+                match read_uvarint(&mut data)? {
+                    EDIT_COMPARATOR if read_prefixed(&mut data)? != COMPARATOR => {
+                        return Err(ChainstateError::Corrupted("the keys are not in bytewise order"))
+                    }
+                    EDIT_COMPARATOR => {}
+                    EDIT_LOG_NUMBER => log = read_uvarint(&mut data)?,
+                    EDIT_PREVIOUS_LOG => previous = read_uvarint(&mut data)?,
+                    EDIT_NEXT_FILE | EDIT_LAST_SEQUENCE => {
+                        read_uvarint(&mut data)?;
+                    }
+                    EDIT_COMPACT_POINTER => {
+                        read_level(&mut data)?;
+                        read_prefixed(&mut data)?;
+                    }
+                    EDIT_DELETED_FILE => {
+                        let level = read_level(&mut data)?;
+                        files.remove(&(level, read_uvarint(&mut data)?));
+                    }
+                    EDIT_NEW_FILE => {
+                        let level = read_level(&mut data)?;
+                        let number = read_uvarint(&mut data)?;
+                        read_uvarint(&mut data)?;
+                        let smallest = user_key(read_prefixed(&mut data)?)?.to_vec();
+                        let largest = user_key(read_prefixed(&mut data)?)?.to_vec();
+                        files.insert(
+                            (level, number),
+                            TableFile {
+                                number,
+                                smallest,
+                                largest,
+                            },
+                        );
+                    }
+                    _ => return Err(ChainstateError::Corrupted("the manifest has an unknown entry")),
+                }
+            }
+        }
+        let mut levels = vec![Vec::new(); LEVELS as usize];
+        for ((level, _), file) in files {
+            levels[level as usize].push(file);
+        }
+        for level in levels.iter_mut().skip(1) {
+            level.sort_by(|first, second| first.smallest.cmp(&second.smallest));
+        }
+        let mut database = Self {
+            path: path.to_owned(),
+            levels,
+            memtable: BTreeMap::new(),
+        };
+        // the writes after the last table are only in the logs, which are replayed in the order they were
+        // created
+        let mut logs = fs::read_dir(path)?
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                name.strip_suffix(".log")?.parse::<u64>().ok()
+            })
+            .filter(|&number| number >= log || number == previous)
+            .collect::<Vec<_>>();
+        logs.sort_unstable();
+        for number in logs {
+            let file = BufReader::new(File::open(path.join(format!("{:06}.log", number)))?);
+            for batch in LogReader::new(file) {
+                database.apply_batch(&batch?)?;
+            }
+        }
+        Ok(database)
+    }
+
+    // a batch is its first sequence and the number of its entries, each entry taking the next sequence
+    fn apply_batch(&mut self, batch: &[u8]) -> Result<(), ChainstateError> {
+        if batch.len() < 12 {
+            return Err(ChainstateError::Corrupted("a write batch is too short"));
+        }
+        let first = LittleEndian::read_u64(&batch[..8]);
+        let count = LittleEndian::read_u32(&batch[8..12]);
+        let mut data = &batch[12..];
+        for sequence in (first..).take(count as usize) {
+            let kind = take(&mut data, 1)?[0];
+            let key = read_prefixed(&mut data)?.to_vec();
+            let value = match kind {
+                VALUE => Some(read_prefixed(&mut data)?.to_vec()),
+                DELETION => None,
+                _ => return Err(ChainstateError::Corrupted("a write batch has an unknown entry")),
+            };
+            self.memtable.insert(key, (sequence, value));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, ChainstateError> {
+        match self.entries_from(key)?.next().transpose()? {
+            Some((found, value)) if found == key => Ok(Some(value)),
+            _ => Ok(None),
+        }
+    }
+
+    // the live values in key order, from the first key not below the given one
+    pub(crate) fn entries_from(&self, start: &[u8]) -> Result<Entries<'_>, ChainstateError> {
+        let memtable = self.memtable.range(start.to_vec()..).map(|(key, (sequence, value))| {
+            Ok(Entry {
+                key: key.clone(),
+                sequence: *sequence,
+                value: value.clone(),
+            })
+        });
+        let mut sources: Vec<Box<dyn Iterator<Item = Result<Entry, ChainstateError>> + '_>> = vec![Box::new(memtable)];
+        for file in &self.levels[0] {
+            if file.largest[..] >= *start {
+                sources.push(Box::new(
+                    Table::open(&self.table_path(file.number))?.entries_from(start),
+                ));
+            }
+        }
+        for level in &self.levels[1..] {
+            let files = level
+                .iter()
+                .filter(|file| file.largest[..] >= *start)
+                .cloned()
+                .collect::<Vec<_>>();
+            sources.push(Box::new(LevelEntries {
+                database: self,
+                files: files.into_iter(),
+                current: None,
+                start: start.to_vec(),
+            }));
+        }
+        let mut entries = Entries {
+            sources,
+            heap: BinaryHeap::new(),
+        };
+        for source in 0..entries.sources.len() {
+            entries.refill(source)?;
+        }
+        Ok(entries)
+    }
+
+    // the tables written by older versions have the sst extension
+    fn table_path(&self, number: u64) -> PathBuf {
+        let path = self.path.join(format!("{:06}.ldb", number));
+        match path.exists() {
+            true => path,
+            false => self.path.join(format!("{:06}.sst", number)),
+        }
+    }
+}
+
+// the tables of a level one after the other, each one opened once the previous is done
+struct LevelEntries<'a> {
+    database: &'a LevelDb,
+    files: vec::IntoIter<TableFile>,
+    current: Option<TableEntries>,
+    start: Vec<u8>,
+}
+
+impl LevelEntries<'_> {
+    fn read_entry(&mut self) -> Result<Option<Entry>, ChainstateError> {
+        loop {
+            if let Some(entry) = self.current.as_mut().and_then(Iterator::next) {
+                return entry.map(Some);
+            }
+            match self.files.next() {
+                Some(file) => {
+                    let table = Table::open(&self.database.table_path(file.number))?;
+                    self.current = Some(table.entries_from(&self.start));
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+impl Iterator for LevelEntries<'_> {
+    type Item = Result<Entry, ChainstateError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_entry().transpose()
+    }
+}
+
+// the next entry of a source, ordered by key and then from the newest to the oldest version
+struct Head {
+    entry: Entry,
+    source: usize,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.entry
+            .key
+            .cmp(&other.entry.key)
+            .then(other.entry.sequence.cmp(&self.entry.sequence))
+            .then(self.source.cmp(&other.source))
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+// merges the memtable and the tables, keeping the newest version of each key and leaving out the deleted ones
+pub(crate) struct Entries<'a> {
+    sources: Vec<Box<dyn Iterator<Item = Result<Entry, ChainstateError>> + 'a>>,
+    heap: BinaryHeap<Reverse<Head>>,
+}
+
+impl Entries<'_> {
+    fn refill(&mut self, source: usize) -> Result<(), ChainstateError> {
+        if let Some(entry) = self.sources[source].next().transpose()? {
+            self.heap.push(Reverse(Head { entry, source }));
+        }
+        Ok(())
+    }
+
+    fn read_entry(&mut self) -> Result<Option<KeyValue>, ChainstateError> {
+        while let Some(Reverse(head)) = self.heap.pop() {
+            self.refill(head.source)?;
+            while let Some(Reverse(older)) = self.heap.peek() {
+                if older.entry.key != head.entry.key {
+                    break;
+                }
+                let source = older.source;
+                self.heap.pop();
+                self.refill(source)?;
+            }
+            if let Some(value) = head.entry.value {
+                return Ok(Some((head.entry.key, value)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for Entries<'_> {
+    type Item = Result<KeyValue, ChainstateError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.read_entry().transpose();
+        // an error leaves the sources behind each other
+        if matches!(entry, Some(Err(_))) {
+            self.heap.clear();
+        }
+        entry
+    }
+}
+
+// the keys of the tables end with the sequence and kind of the entry
+pub(crate) fn split_internal_key(mut key: Vec<u8>, value: Vec<u8>) -> Result<Entry, ChainstateError> {
+    let tag = LittleEndian::read_u64(&key[user_key(&key)?.len()..]);
+    key.truncate(key.len() - 8);
+    let value = match tag as u8 {
+        VALUE => Some(value),
+        DELETION => None,
+        _ => return Err(ChainstateError::Corrupted("a table entry has an unknown kind")),
+    };
+    Ok(Entry {
+        key,
+        sequence: tag >> 8,
+        value,
+    })
+}
+
+fn user_key(key: &[u8]) -> Result<&[u8], ChainstateError> {
+    key.len()
+        .checked_sub(8)
+        .map(|size| &key[..size])
+        .ok_or(ChainstateError::Corrupted("a key is too short"))
+}
+
+// the little endian base 128 numbers of leveldb
+pub(crate) fn read_uvarint(data: &mut &[u8]) -> Result<u64, ChainstateError> {
+    let mut number = 0;
+    for shift in (0..64).step_by(7) {
+        let byte = take(data, 1)?[0];
+        number |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(number);
+        }
+    }
+    Err(ChainstateError::Corrupted("a varint is too large"))
+}
+
+pub(crate) fn take<'a>(data: &mut &'a [u8], size: usize) -> Result<&'a [u8], ChainstateError> {
+    if data.len() < size {
+        return Err(ChainstateError::Corrupted("a record ends before its fields"));
+    }
+    let (taken, rest) = data.split_at(size);
+    *data = rest;
+    Ok(taken)
+}
+
+fn read_prefixed<'a>(data: &mut &'a [u8]) -> Result<&'a [u8], ChainstateError> {
+    let size = read_uvarint(data)? as usize;
+    take(data, size)
+}
+
+fn read_level(data: &mut &[u8]) -> Result<u64, ChainstateError> {
+    match read_uvarint(data)? {
+        level if level < LEVELS => Ok(level),
+        _ => Err(ChainstateError::Corrupted("a table is in an unknown level")),
+    }
+}
@@ -0,0 +1,131 @@
+use byteorder::{ByteOrder, LittleEndian};
+use std::io::Read;
+
+use super::ChainstateError;
+
+// the logs and manifests are split in blocks, a record that does not fit in one is fragmented
+const BLOCK_SIZE: u64 = 32 * 1024;
+// a checksum, the length of the fragment and its kind
+const HEADER_SIZE: usize = 7;
+
+const ZERO: u8 = 0;
+const FULL: u8 = 1;
+const FIRST: u8 = 2;
+const MIDDLE: u8 = 3;
+const LAST: u8 = 4;
+
+// reads the records of a leveldb log. The checksums are not verified, a record cut by the end of the file
+// is the one being written when the database was closed and ends the log as it does for leveldb
+pub(crate) struct LogReader<R: Read> {
+    reader: R,
+    block: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read> LogReader<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        Self {
+            reader,
+            block: Vec::new(),
+            position: 0,
+        }
+    }
+
+    fn read_record(&mut self) -> Result<Option<Vec<u8>>, ChainstateError> {
+        let mut record: Option<Vec<u8>> = None;
+        loop {
+            // the end of a block too short for a header is padding
+            if self.block.len() - self.position < HEADER_SIZE {
+                self.block.clear();
+                self.position = 0;
+                self.reader.by_ref().take(BLOCK_SIZE).read_to_end(&mut self.block)?;
+                if self.block.len() < HEADER_SIZE {
+                    return Ok(None);
+                }
+            }
+            let header = &self.block[self.position..self.position + HEADER_SIZE];
+            let length = LittleEndian::read_u16(&header[4..6]) as usize;
+            let kind = header[6];
+            let start = self.position + HEADER_SIZE;
+            if start + length > self.block.len() {
+                return Ok(None);
+            }
+            // the space preallocated for the log is left zeroed
+            if kind == ZERO && length == 0 {
+                self.position = self.block.len();
+                continue;
+            }
+            let fragment = &self.block[start..start + length];
+            self.position = start + length;
+            match (kind, record.as_mut()) {
+                (FULL, None) => return Ok(Some(fragment.to_vec())),
+                (FIRST, None) => record = Some(fragment.to_vec()),
+                (MIDDLE, Some(record)) => record.extend_from_slice(fragment),
+                (LAST, Some(record)) => {
+                    record.extend_from_slice(fragment);
+                    return Ok(Some(std::mem::take(record)));
+                }
+                _ => return Err(ChainstateError::Corrupted("a log record is not fragmented as expected")),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for LogReader<R> {
+    type Item = Result<Vec<u8>, ChainstateError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut fragment = vec![0; HEADER_SIZE];
+        LittleEndian::write_u16(&mut fragment[4..6], data.len() as u16);
+        fragment[6] = kind;
+        fragment.extend_from_slice(data);
+        fragment
+    }
+
+    fn read(log: &[u8]) -> Vec<Vec<u8>> {
+        LogReader::new(log).collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn joins_the_fragments_across_blocks() {
+        let block = BLOCK_SIZE as usize;
+        let mut log = fragment(FULL, b"first");
+        // the record starts at the end of the first block and goes on in the second one
+        let split = block - log.len() - HEADER_SIZE;
+        let record = vec![7; split + 100];
+        log.extend(fragment(FIRST, &record[..split]));
+        log.extend(fragment(LAST, &record[split..]));
+        // the rest of the second block is too short for a header
+        let filler = vec![8; 2 * block - 3 - log.len() - HEADER_SIZE];
+        log.extend(fragment(FULL, &filler));
+        log.resize(2 * block, 0);
+        log.extend(fragment(FULL, b"last"));
+        assert_eq!(read(&log), vec![b"first".to_vec(), record, filler, b"last".to_vec()]);
+    }
+
+    #[test]
+    fn ends_at_the_preallocated_space_and_the_records_cut_short() {
+        let mut log = fragment(FULL, b"written");
+        log.resize(100, 0);
+        assert_eq!(read(&log), vec![b"written".to_vec()]);
+        let mut log = fragment(FULL, b"written");
+        log.extend(&fragment(FULL, b"cut short")[..10]);
+        assert_eq!(read(&log), vec![b"written".to_vec()]);
+    }
+
+    #[test]
+    fn refuses_fragments_out_of_order() {
+        let log = [fragment(FIRST, b"a"), fragment(FULL, b"b")].concat();
+        let mut reader = LogReader::new(&log[..]);
+        assert!(matches!(reader.next(), Some(Err(ChainstateError::Corrupted(_)))));
+    }
+}
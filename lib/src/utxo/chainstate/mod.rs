@@ -0,0 +1,329 @@
+use arrayref::array_ref;
+use std::convert::TryFrom;
+use std::io;
+use std::path::Path;
+use thiserror::Error;
+
+mod leveldb;
+mod log;
+mod table;
+
+use self::leveldb::{Entries, LevelDb};
+use super::compress::{read_coin, read_compact_size, read_varint};
+use super::{Coin, UtxoError, UtxoSet, UtxoStore};
+use crate::blockchain::transactions::{Output, Utxo};
+use crate::types::{BlockHash, Txid};
+
+// the keys of the records, the ones of the coins being followed by the txid and the VARINT of the index
+const OBFUSCATE_KEY: &[u8] = b"\x0e\x00obfuscate_key";
+const COIN: u8 = b'C';
+const BEST_BLOCK: u8 = b'B';
+const HEAD_BLOCKS: u8 = b'H';
+
+// reads the set of unspent outputs kept by the reference client in the chainstate directory of its data
+// directory. The client must not be running, as it changes the database in place
+pub struct ChainstateReader {
+    database: LevelDb,
+    obfuscation: Vec<u8>,
+    best_block: Option<BlockHash>,
+}
+
+impl ChainstateReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ChainstateError> {
+        let database = LevelDb::open(path.as_ref())?;
+        // the values are XORed with the key to keep antivirus from matching them, the key itself is not
+        let obfuscation = match database.get(OBFUSCATE_KEY)? {
+            Some(value) => {
+                let mut value = &value[..];
+                let size = read_compact_size(&mut value)?;
+                if size != value.len() as u64 {
+                    return Err(ChainstateError::Corrupted("the obfuscation key has the wrong size"));
+                }
+                value.to_vec()
+            }
+            None => Vec::new(),
+        };
+        let mut reader = Self {
+            database,
+            obfuscation,
+            best_block: None,
+        };
+        // the blocks of a flush that did not finish, the coins are a mix of both until the client replays them
+        if reader.get(&[HEAD_BLOCKS])?.is_some() {
+            return Err(ChainstateError::Interrupted);
+        }
+        reader.best_block = reader
+            .get(&[BEST_BLOCK])?
+            .map(|hash| match hash.len() {
+                32 => Ok(BlockHash::new(*array_ref!(hash, 0, 32))),
+                _ => Err(ChainstateError::Corrupted("the best block is not a hash")),
+            })
+            .transpose()?;
+        Ok(reader)
+    }
+
+    // the block the coins are the result of, none for a database without blocks
+    pub fn best_block(&self) -> Option<&BlockHash> {
+        self.best_block.as_ref()
+    }
+
+    pub fn obfuscation_key(&self) -> &[u8] {
+        &self.obfuscation
+    }
+
+    // the coins in outpoint order
+    pub fn coins(&self) -> Result<ChainstateCoins<'_>, ChainstateError> {
+        Ok(ChainstateCoins {
+            entries: self.database.entries_from(&[COIN])?,
+            obfuscation: &self.obfuscation,
+            done: false,
+        })
+    }
+
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, ChainstateError> {
+        Ok(self
+            .database
+            .get(key)?
+            .map(|value| deobfuscate(value, &self.obfuscation)))
+    }
+}
+
+pub struct ChainstateCoins<'a> {
+    entries: Entries<'a>,
+    obfuscation: &'a [u8],
+    done: bool,
+}
+
+impl ChainstateCoins<'_> {
+    fn read_coin(&mut self) -> Result<Option<(Utxo, Coin)>, ChainstateError> {
+        let (key, value) = match self.entries.next().transpose()? {
+            Some((key, value)) if key.first() == Some(&COIN) => (key, value),
+            _ => return Ok(None),
+        };
+        if key.len() < 34 {
+            return Err(ChainstateError::Corrupted("the key of a coin is malformed"));
+        }
+        let mut index = &key[33..];
+        let vout = read_varint(&mut index)
+            .ok()
+            .filter(|_| index.is_empty())
+            .and_then(|vout| u32::try_from(vout).ok())
+            .ok_or(ChainstateError::Corrupted("the key of a coin is malformed"))?;
+        let utxo = Utxo::new(Txid::new(*array_ref!(key, 1, 32)), vout);
+        let value = deobfuscate(value, self.obfuscation);
+        let coin = read_coin(&mut &value[..]).map_err(|err| ChainstateError::Coin(utxo, err))?;
+        Ok(Some((utxo, coin)))
+    }
+}
+
+impl Iterator for ChainstateCoins<'_> {
+    type Item = Result<(Utxo, Output, u32, bool), ChainstateError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let coin = self.read_coin().transpose();
+        self.done = !matches!(coin, Some(Ok(_)));
+        coin.map(|coin| {
+            coin.map(|(utxo, coin)| {
+                let (height, coinbase) = (coin.height(), coin.is_coinbase());
+                (utxo, coin.into_output(), height, coinbase)
+            })
+        })
+    }
+}
+
+impl<S: UtxoStore> UtxoSet<S> {
+    // loads the coins into an empty store, the height is the one of the best block of the database
+    pub fn from_chainstate(store: S, height: u32, reader: &ChainstateReader) -> Result<Self, ChainstateError> {
+        let hash = *reader.best_block().ok_or(UtxoError::Empty)?;
+        let coins = reader
+            .coins()?
+            .map(|coin| coin.map(|(utxo, output, height, coinbase)| (utxo, Coin::new(output, height, coinbase))));
+        Self::from_coins(store, height, hash, coins)
+    }
+}
+
+fn deobfuscate(mut value: Vec<u8>, key: &[u8]) -> Vec<u8> {
+    value
+        .iter_mut()
+        .zip(key.iter().cycle())
+        .for_each(|(byte, key)| *byte ^= key);
+    value
+}
+
+#[derive(Debug, Error)]
+pub enum ChainstateError {
+    #[error("unable to read the chainstate database")]
+    Io(#[from] io::Error),
+    #[error("the chainstate database is corrupted: {0}")]
+    Corrupted(&'static str),
+    #[error("the chainstate table uses the unsupported compression {0}")]
+    Compression(u8),
+    #[error("the last write of the chainstate was interrupted, the reference client finishes it when started")]
+    Interrupted,
+    #[error("the coin for {}:{} is corrupted", .0.txid(), .0.vout())]
+    Coin(Utxo, #[source] io::Error),
+    #[error(transparent)]
+    Utxo(#[from] UtxoError),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::amount::Amount;
+    use crate::blockchain::script::BitcoinScript;
+    use crate::utxo::compress::{compress_coin, write_varint};
+
+    const OBFUSCATION: [u8; 8] = [0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88];
+
+    fn uvarint(buffer: &mut Vec<u8>, mut number: u64) {
+        while number >= 0x80 {
+            buffer.push(number as u8 | 0x80);
+            number >>= 7;
+        }
+        buffer.push(number as u8);
+    }
+
+    fn prefixed(buffer: &mut Vec<u8>, data: &[u8]) {
+        uvarint(buffer, data.len() as u64);
+        buffer.extend_from_slice(data);
+    }
+
+    fn internal_key(key: &[u8], sequence: u64) -> Vec<u8> {
+        [key, &(sequence << 8 | 1).to_le_bytes()].concat()
+    }
+
+    // a single record that fits in the first block of the log
+    fn log_record(data: &[u8]) -> Vec<u8> {
+        [&[0, 0, 0, 0][..], &(data.len() as u16).to_le_bytes(), &[1], data].concat()
+    }
+
+    // the entries with their whole key and a single restart, followed by the trailer of no compression
+    fn table_block(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        let mut block = vec![];
+        for (key, value) in entries {
+            block.extend(&[0]);
+            uvarint(&mut block, key.len() as u64);
+            uvarint(&mut block, value.len() as u64);
+            block.extend(key);
+            block.extend(value);
+        }
+        block.extend(&[0u32.to_le_bytes(), 1u32.to_le_bytes()].concat());
+        block
+    }
+
+    fn table(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        let data = table_block(entries);
+        let mut handle = vec![];
+        uvarint(&mut handle, 0);
+        uvarint(&mut handle, data.len() as u64);
+        let index = table_block(&[(entries.last().unwrap().0.clone(), handle)]);
+        let mut table = [&data[..], &[0; 5], &index, &[0; 5]].concat();
+        let mut footer = vec![];
+        // the metaindex is empty
+        uvarint(&mut footer, 0);
+        uvarint(&mut footer, 0);
+        uvarint(&mut footer, data.len() as u64 + 5);
+        uvarint(&mut footer, index.len() as u64);
+        footer.resize(40, 0);
+        footer.extend(&0xdb47_7524_8b80_fb57u64.to_le_bytes());
+        table.extend(footer);
+        table
+    }
+
+    fn coin_key(txid: u8, vout: u64) -> Vec<u8> {
+        let mut key = [&[COIN][..], &[txid; 32]].concat();
+        write_varint(&mut key, vout);
+        key
+    }
+
+    fn coin(value: u64, height: u32) -> (Coin, Vec<u8>) {
+        let output = Output::new(Amount::from_sat(value), BitcoinScript::new(vec![0x51]));
+        let coin = Coin::new(output, height, false);
+        let mut value = vec![];
+        compress_coin(&mut value, &coin);
+        (coin, deobfuscate(value, &OBFUSCATION))
+    }
+
+    // a database with the coins of two transactions in a table, of which the log spends one and adds another
+    fn fixture(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("chainstate-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        let mut obfuscation = vec![OBFUSCATION.len() as u8];
+        obfuscation.extend(&OBFUSCATION);
+        let entries = vec![
+            (internal_key(OBFUSCATE_KEY, 1), obfuscation),
+            (
+                internal_key(&[BEST_BLOCK], 2),
+                deobfuscate(vec![0xbb; 32], &OBFUSCATION),
+            ),
+            (internal_key(&coin_key(1, 0), 3), coin(1_000, 10).1),
+            (internal_key(&coin_key(2, 200), 4), coin(2_000, 11).1),
+        ];
+        fs::write(path.join("000004.ldb"), table(&entries)).unwrap();
+        // the comparator, the number of the log and the table in level zero with its size and keys
+        let mut edit = vec![];
+        uvarint(&mut edit, 1);
+        prefixed(&mut edit, b"leveldb.BytewiseComparator");
+        uvarint(&mut edit, 2);
+        uvarint(&mut edit, 5);
+        uvarint(&mut edit, 7);
+        uvarint(&mut edit, 0);
+        uvarint(&mut edit, 4);
+        uvarint(&mut edit, 1_000);
+        prefixed(&mut edit, &entries[0].0);
+        prefixed(&mut edit, &entries[3].0);
+        fs::write(path.join("MANIFEST-000003"), log_record(&edit)).unwrap();
+        fs::write(path.join("CURRENT"), "MANIFEST-000003\n").unwrap();
+        // a deletion and a value from sequence 5
+        let mut batch = [&5u64.to_le_bytes()[..], &2u32.to_le_bytes()].concat();
+        batch.push(0);
+        prefixed(&mut batch, &coin_key(1, 0));
+        batch.push(1);
+        prefixed(&mut batch, &coin_key(3, 1));
+        prefixed(&mut batch, &coin(3_000, 12).1);
+        fs::write(path.join("000005.log"), log_record(&batch)).unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_the_coins_of_the_tables_and_the_log() {
+        let path = fixture("coins");
+        let reader = ChainstateReader::open(&path).unwrap();
+        assert_eq!(reader.obfuscation_key(), &OBFUSCATION);
+        assert_eq!(reader.best_block(), Some(&BlockHash::new([0xbb; 32])));
+        let coins = reader
+            .coins()
+            .unwrap()
+            .map(|coin| coin.map(|(utxo, output, height, coinbase)| (utxo, Coin::new(output, height, coinbase))))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let expected = vec![
+            (Utxo::new(Txid::new([2; 32]), 200), coin(2_000, 11).0),
+            (Utxo::new(Txid::new([3; 32]), 1), coin(3_000, 12).0),
+        ];
+        assert!(coins == expected);
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn refuses_an_interrupted_flush() {
+        let path = fixture("interrupted");
+        let mut batch = [&9u64.to_le_bytes()[..], &1u32.to_le_bytes()].concat();
+        batch.push(1);
+        prefixed(&mut batch, &[HEAD_BLOCKS]);
+        prefixed(&mut batch, &[0; 64]);
+        fs::write(path.join("000006.log"), log_record(&batch)).unwrap();
+        assert!(matches!(
+            ChainstateReader::open(&path),
+            Err(ChainstateError::Interrupted)
+        ));
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,199 @@
+use byteorder::{ByteOrder, LittleEndian};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::vec;
+
+use super::leveldb::{read_uvarint, split_internal_key, take, Entry, KeyValue};
+use super::ChainstateError;
+
+// the handles of the metaindex and index blocks with their padding, and the magic number
+const FOOTER_SIZE: usize = 48;
+const TABLE_MAGIC: u64 = 0xdb47_7524_8b80_fb57;
+// the kind of compression and a checksum follow each block
+const BLOCK_TRAILER_SIZE: usize = 5;
+const NO_COMPRESSION: u8 = 0;
+
+#[derive(Copy, Clone)]
+struct BlockHandle {
+    offset: u64,
+    size: u64,
+}
+
+impl BlockHandle {
+    fn read(data: &mut &[u8]) -> Result<Self, ChainstateError> {
+        Ok(Self {
+            offset: read_uvarint(data)?,
+            size: read_uvarint(data)?,
+        })
+    }
+}
+
+// a sorted table, the index with the last key of each data block is kept in memory and the data blocks are
+// read when iterating
+pub(crate) struct Table {
+    file: File,
+    index: Vec<(Vec<u8>, BlockHandle)>,
+}
+
+impl Table {
+    pub(crate) fn open(path: &Path) -> Result<Self, ChainstateError> {
+        let mut file = File::open(path)?;
+        let size = file.seek(SeekFrom::End(0))?;
+        if size < FOOTER_SIZE as u64 {
+            return Err(ChainstateError::Corrupted("a table is too short"));
+        }
+        let mut footer = [0; FOOTER_SIZE];
+        file.seek(SeekFrom::Start(size - FOOTER_SIZE as u64))?;
+        file.read_exact(&mut footer)?;
+        if LittleEndian::read_u64(&footer[FOOTER_SIZE - 8..]) != TABLE_MAGIC {
+            return Err(ChainstateError::Corrupted("a table has the wrong magic number"));
+        }
+        let mut handles = &footer[..FOOTER_SIZE - 8];
+        // the metaindex only points to the filters, which are not needed to iterate
+        BlockHandle::read(&mut handles)?;
+        let index = BlockHandle::read(&mut handles)?;
+        let mut table = Self {
+            file,
+            index: Vec::new(),
+        };
+        table.index = read_block(&table.read_block(index)?)?
+            .into_iter()
+            .map(|(key, value)| Ok((key, BlockHandle::read(&mut &value[..])?)))
+            .collect::<Result<_, ChainstateError>>()?;
+        Ok(table)
+    }
+
+    // the entries from the first one with a user key not below the given one
+    pub(crate) fn entries_from(self, start: &[u8]) -> TableEntries {
+        // the key of each index entry is not below the keys of its block
+        let block = self
+            .index
+            .iter()
+            .position(|(key, _)| key.len() < 8 || &key[..key.len() - 8] >= start)
+            .unwrap_or(self.index.len());
+        TableEntries {
+            table: self,
+            block,
+            entries: Vec::new().into_iter(),
+            start: start.to_vec(),
+        }
+    }
+
+    fn read_block(&mut self, handle: BlockHandle) -> Result<Vec<u8>, ChainstateError> {
+        let mut block = vec![0; handle.size as usize + BLOCK_TRAILER_SIZE];
+        self.file.seek(SeekFrom::Start(handle.offset))?;
+        self.file.read_exact(&mut block)?;
+        // the reference client opens its databases without compression
+        match block[handle.size as usize] {
+            NO_COMPRESSION => {
+                block.truncate(handle.size as usize);
+                Ok(block)
+            }
+            compression => Err(ChainstateError::Compression(compression)),
+        }
+    }
+}
+
+pub(crate) struct TableEntries {
+    table: Table,
+    block: usize,
+    entries: vec::IntoIter<KeyValue>,
+    start: Vec<u8>,
+}
+
+impl TableEntries {
+    fn read_entry(&mut self) -> Result<Option<Entry>, ChainstateError> {
+        loop {
+            match self.entries.next() {
+                Some((key, value)) => {
+                    let entry = split_internal_key(key, value)?;
+                    if entry.key >= self.start {
+                        return Ok(Some(entry));
+                    }
+                }
+                None if self.block < self.table.index.len() => {
+                    let handle = self.table.index[self.block].1;
+                    self.entries = read_block(&self.table.read_block(handle)?)?.into_iter();
+                    self.block += 1;
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+impl Iterator for TableEntries {
+    type Item = Result<Entry, ChainstateError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_entry().transpose()
+    }
+}
+
+// the keys share their prefix with the previous one, the offsets of the entries with a full key are at
+// the end of the block
+fn read_block(block: &[u8]) -> Result<Vec<KeyValue>, ChainstateError> {
+    let corrupted = || ChainstateError::Corrupted("a table block is malformed");
+    let restarts = block
+        .len()
+        .checked_sub(4)
+        .map(|end| LittleEndian::read_u32(&block[end..]) as usize)
+        .ok_or_else(corrupted)?;
+    let end = restarts
+        .checked_mul(4)
+        .and_then(|size| block.len().checked_sub(4 + size))
+        .ok_or_else(corrupted)?;
+    let mut data = &block[..end];
+    let mut key = Vec::new();
+    let mut entries = Vec::new();
+    while !data.is_empty() {
+        let shared = read_uvarint(&mut data)? as usize;
+        let unshared = read_uvarint(&mut data)? as usize;
+        let size = read_uvarint(&mut data)? as usize;
+        if shared > key.len() {
+            return Err(corrupted());
+        }
+        key.truncate(shared);
+        key.extend_from_slice(take(&mut data, unshared)?);
+        entries.push((key.clone(), take(&mut data, size)?.to_vec()));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebuilds_the_keys_from_their_shared_prefix() {
+        let mut block = vec![];
+        // the first key is whole, the next ones share the start of the previous key
+        block.extend(&[0, 5, 1]);
+        block.extend(b"apple1");
+        block.extend(&[3, 5, 2]);
+        block.extend(b"ricot22");
+        block.extend(&[0, 6, 0]);
+        block.extend(b"banana");
+        block.extend(&[0u32.to_le_bytes(), 19u32.to_le_bytes(), 2u32.to_le_bytes()].concat());
+        let entries = read_block(&block).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                (b"apple".to_vec(), b"1".to_vec()),
+                (b"appricot".to_vec(), b"22".to_vec()),
+                (b"banana".to_vec(), vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn refuses_malformed_blocks() {
+        assert!(read_block(&[1, 0]).is_err());
+        // the restarts go past the start of the block
+        assert!(read_block(&9u32.to_le_bytes()).is_err());
+        // the key shares more than the previous one has
+        let block = [&[2, 1, 0, b'a'][..], &0u32.to_le_bytes()].concat();
+        assert!(read_block(&block).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+use byteorder::{LittleEndian, ReadBytesExt};
+use k256::elliptic_curve::sec1::{FromEncodedPoint, ToEncodedPoint};
+use k256::{AffinePoint, EncodedPoint};
+use std::convert::TryFrom;
+use std::io::{self, Read};
+
+use super::Coin;
+use crate::amount::Amount;
+use crate::analysis::pubkeys::PublicKey;
+use crate::blockchain::script::opcodes::{OP_CHECKSIG, OP_DUP, OP_EQUAL, OP_EQUALVERIFY, OP_HASH160, OP_RETURN};
+use crate::blockchain::script::{BitcoinScript, MAX_SCRIPT_SIZE};
+use crate::blockchain::transactions::Output;
+
+// the scripts with a shorter form take the first codes, the size of any other script is offset by them
+const SPECIAL_SCRIPTS: u64 = 6;
+
+// the reference client VARINT, a base 128 number where every byte but the last has one subtracted. It is
+// not the compact size used by the consensus encoding
+pub(crate) fn write_varint(buffer: &mut Vec<u8>, mut number: u64) {
+    let mut bytes = [0u8; 10];
+    let mut len = 0;
+    loop {
+        bytes[len] = (number & 0x7f) as u8 | if len > 0 { 0x80 } else { 0x00 };
+        if number <= 0x7f {
+            break;
+        }
+        number = (number >> 7) - 1;
+        len += 1;
+    }
+    buffer.extend(bytes[..=len].iter().rev());
+}
+
+pub(crate) fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut number = 0u64;
+    loop {
+        let byte = read_byte(reader)?;
+        if number > u64::MAX >> 7 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "VARINT is too large"));
+        }
+        number = (number << 7) | (byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Ok(number);
+        }
+        number = number
+            .checked_add(1)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "VARINT is too large"))?;
+    }
+}
+
+// the size prefix of the consensus encoding
+pub(crate) fn read_compact_size<R: Read>(reader: &mut R) -> io::Result<u64> {
+    match reader.read_u8()? {
+        0xfd => reader.read_u16::<LittleEndian>().map(u64::from),
+        0xfe => reader.read_u32::<LittleEndian>().map(u64::from),
+        0xff => reader.read_u64::<LittleEndian>(),
+        size => Ok(size as u64),
+    }
+}
+
+// trailing zeros are moved to an exponent, so round values take few bytes
+pub(crate) fn compress_amount(amount: u64) -> u64 {
+    if amount == 0 {
+        return 0;
+    }
+    let mut number = amount;
+    let mut exponent = 0;
+    while number.is_multiple_of(10) && exponent < 9 {
+        number /= 10;
+        exponent += 1;
+    }
+    if exponent < 9 {
+        let digit = number % 10;
+        number /= 10;
+        1 + (number * 9 + digit - 1) * 10 + exponent
+    } else {
+        1 + (number - 1) * 10 + 9
+    }
+}
+
+pub(crate) fn decompress_amount(compressed: u64) -> u64 {
+    if compressed == 0 {
+        return 0;
+    }
+    let mut compressed = compressed - 1;
+    let mut exponent = compressed % 10;
+    compressed /= 10;
+    let mut number = if exponent < 9 {
+        let digit = compressed % 9 + 1;
+        compressed /= 9;
+        compressed.wrapping_mul(10).wrapping_add(digit)
+    } else {
+        compressed.wrapping_add(1)
+    };
+    while exponent > 0 {
+        number = number.wrapping_mul(10);
+        exponent -= 1;
+    }
+    number
+}
+
+pub(crate) fn compress_script(buffer: &mut Vec<u8>, script: &[u8]) {
+    match script {
+        [OP_DUP, OP_HASH160, 20, hash @ .., OP_EQUALVERIFY, OP_CHECKSIG] if hash.len() == 20 => {
+            buffer.push(0x00);
+            buffer.extend_from_slice(hash);
+        }
+        [OP_HASH160, 20, hash @ .., OP_EQUAL] if hash.len() == 20 => {
+            buffer.push(0x01);
+            buffer.extend_from_slice(hash);
+        }
+        [33, key @ .., OP_CHECKSIG] if key.len() == 33 && (key[0] == 0x02 || key[0] == 0x03) => {
+            buffer.extend_from_slice(key);
+        }
+        // only the keys on the curve, as the y coordinate is recomputed from x when reading them
+        [65, key @ .., OP_CHECKSIG]
+            if key.len() == 65 && key[0] == 0x04 && PublicKey::parse(key).is_some_and(|key| key.is_on_curve()) =>
+        {
+            buffer.push(0x04 | (key[64] & 0x01));
+            buffer.extend_from_slice(&key[1..33]);
+        }
+        _ => {
+            write_varint(buffer, script.len() as u64 + SPECIAL_SCRIPTS);
+            buffer.extend_from_slice(script);
+        }
+    }
+}
+
+pub(crate) fn read_script<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let code = read_varint(reader)?;
+    let script = match code {
+        0x00 => [
+            &[OP_DUP, OP_HASH160, 20][..],
+            &read_bytes(reader, 20)?,
+            &[OP_EQUALVERIFY, OP_CHECKSIG],
+        ]
+        .concat(),
+        0x01 => [&[OP_HASH160, 20][..], &read_bytes(reader, 20)?, &[OP_EQUAL]].concat(),
+        0x02 | 0x03 => [&[33, code as u8][..], &read_bytes(reader, 32)?, &[OP_CHECKSIG]].concat(),
+        0x04 | 0x05 => {
+            let compressed = [&[code as u8 - 2][..], &read_bytes(reader, 32)?].concat();
+            [&[65][..], &decompress_key(&compressed)?, &[OP_CHECKSIG]].concat()
+        }
+        _ => {
+            let size = (code - SPECIAL_SCRIPTS) as usize;
+            // the reference client stores oversized scripts, which are unspendable, as a single OP_RETURN
+            if size > MAX_SCRIPT_SIZE {
+                // skips the script like the ignore of the reference client, failing if it is cut short
+                let skipped = io::copy(&mut reader.take(size as u64), &mut io::sink())?;
+                if skipped != size as u64 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "The oversized script goes past the end of the data",
+                    ));
+                }
+                vec![OP_RETURN]
+            } else {
+                read_bytes(reader, size)?
+            }
+        }
+    };
+    Ok(script)
+}
+
+// the coin as the reference client keeps it: the height and coinbase flag, then the compressed output
+pub(crate) fn compress_coin(buffer: &mut Vec<u8>, coin: &Coin) {
+    write_varint(buffer, (coin.height() as u64) << 1 | coin.is_coinbase() as u64);
+    write_varint(buffer, compress_amount(coin.output().value().to_sat()));
+    compress_script(buffer, coin.output().pubkey().as_bytes());
+}
+
+pub(crate) fn read_coin<R: Read>(reader: &mut R) -> io::Result<Coin> {
+    let code = read_varint(reader)?;
+    let height = u32::try_from(code >> 1)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "The coin height is too large"))?;
+    let value = Amount::from_sat(decompress_amount(read_varint(reader)?));
+    let script = BitcoinScript::new(read_script(reader)?);
+    Ok(Coin::new(Output::new(value, script), height, code & 1 == 1))
+}
+
+fn decompress_key(compressed: &[u8]) -> io::Result<Vec<u8>> {
+    EncodedPoint::from_bytes(compressed)
+        .ok()
+        .and_then(|point| Option::<AffinePoint>::from(AffinePoint::from_encoded_point(&point)))
+        .map(|point| point.to_encoded_point(false).as_bytes().to_vec())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The compressed public key is not on the curve",
+            )
+        })
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0u8];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_bytes<R: Read>(reader: &mut R, size: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0; size];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798\
+                       483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8";
+
+    // the bit patterns of the serialize tests of the reference client
+    #[test]
+    fn writes_and_reads_varints() {
+        let vectors: [(u64, &str); 9] = [
+            (0, "00"),
+            (0x7f, "7f"),
+            (0x80, "8000"),
+            (0x1234, "a334"),
+            (0xffff, "82fe7f"),
+            (0x123456, "c7e756"),
+            (0x80123456, "86ffc7e756"),
+            (0xffffffff, "8efefefe7f"),
+            (u64::MAX, "80fefefefefefefefe7f"),
+        ];
+        for &(number, encoded) in &vectors {
+            let mut buffer = vec![];
+            write_varint(&mut buffer, number);
+            assert_eq!(hex::encode(&buffer), encoded);
+            assert_eq!(read_varint(&mut &buffer[..]).unwrap(), number);
+        }
+        assert!(read_varint(&mut &hex::decode("80fefefefefefefefeff00").unwrap()[..]).is_err());
+    }
+
+    // the amount vectors of the compress tests of the reference client
+    #[test]
+    fn compresses_amounts() {
+        const COIN: u64 = 100_000_000;
+        let vectors = [
+            (0, 0),
+            (1, 1),
+            (COIN / 100, 7),
+            (COIN, 9),
+            (50 * COIN, 50),
+            (21_000_000 * COIN, 21_000_000),
+        ];
+        for &(amount, compressed) in &vectors {
+            assert_eq!(compress_amount(amount), compressed);
+            assert_eq!(decompress_amount(compressed), amount);
+        }
+        for amount in 0..100_000 {
+            assert_eq!(decompress_amount(compress_amount(amount)), amount);
+        }
+    }
+
+    fn round_trip(script: &str, compressed: &str) {
+        let script = hex::decode(script).unwrap();
+        let mut buffer = vec![];
+        compress_script(&mut buffer, &script);
+        assert_eq!(hex::encode(&buffer), compressed);
+        assert_eq!(read_script(&mut &buffer[..]).unwrap(), script);
+    }
+
+    #[test]
+    fn compresses_the_standard_scripts() {
+        let hash = "00112233445566778899aabbccddeeff00112233";
+        round_trip(&format!("76a914{}88ac", hash), &format!("00{}", hash));
+        round_trip(&format!("a914{}87", hash), &format!("01{}", hash));
+        let x = &KEY[2..66];
+        round_trip(&format!("2102{}ac", x), &format!("02{}", x));
+        round_trip(&format!("41{}ac", KEY), &format!("04{}", x));
+        round_trip("6a", "076a");
+    }
+
+    // the uncompressed keys off the curve can not be rebuilt from their x coordinate
+    #[test]
+    fn keeps_the_keys_off_the_curve_whole() {
+        let script = format!("4104{}{}ac", &KEY[2..66], "00".repeat(32));
+        round_trip(&script, &format!("49{}", script));
+    }
+
+    #[test]
+    fn reads_oversized_scripts_as_op_return() {
+        let mut buffer = vec![];
+        write_varint(&mut buffer, (MAX_SCRIPT_SIZE + 1) as u64 + SPECIAL_SCRIPTS);
+        let size = buffer.len();
+        buffer.resize(size + MAX_SCRIPT_SIZE + 1, 0);
+        assert_eq!(read_script(&mut &buffer[..]).unwrap(), vec![OP_RETURN]);
+        assert!(read_script(&mut &buffer[..buffer.len() - 1]).is_err());
+    }
+}
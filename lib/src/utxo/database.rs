@@ -0,0 +1,192 @@
+use arrayref::array_ref;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use redb::{Database, ReadableTable, TableDefinition};
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::{Coin, UtxoError, UtxoStore};
+use crate::amount::Amount;
+use crate::blockchain::script::BitcoinScript;
+use crate::blockchain::transactions::{Output, Utxo};
+use crate::types::{BlockHash, Txid};
+
+// the coins by outpoint, the vout is big endian so the outputs of a transaction are kept in order
+const COINS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("coins");
+const STATE: TableDefinition<&str, &[u8]> = TableDefinition::new("state");
+const TIP_KEY: &str = "tip";
+const COUNT_KEY: &str = "count";
+
+// coins changed in memory before they are written, the writes only happen once a block is complete
+pub const DEFAULT_CACHE_SIZE: usize = 1_000_000;
+
+// keeps the set in a redb database, so it does not need to fit in memory and survives between runs
+pub struct DatabaseStore {
+    database: Database,
+    // a spent coin is kept as None until the cache is written
+    cache: HashMap<Utxo, Option<Coin>>,
+    cache_size: usize,
+    count: u64,
+    tip: Option<(u32, BlockHash)>,
+}
+
+impl DatabaseStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, UtxoError> {
+        let database = Database::create(path).map_err(redb::Error::from)?;
+        let transaction = database.begin_write().map_err(redb::Error::from)?;
+        let (count, tip) = {
+            transaction.open_table(COINS).map_err(redb::Error::from)?;
+            let state = transaction.open_table(STATE).map_err(redb::Error::from)?;
+            let count = state
+                .get(COUNT_KEY)
+                .map_err(redb::Error::from)?
+                .map_or(0, |count| LittleEndian::read_u64(count.value()));
+            let tip = state
+                .get(TIP_KEY)
+                .map_err(redb::Error::from)?
+                .map(|tip| decode_tip(tip.value()));
+            (count, tip)
+        };
+        transaction.commit().map_err(redb::Error::from)?;
+        Ok(Self {
+            database,
+            cache: HashMap::new(),
+            cache_size: DEFAULT_CACHE_SIZE,
+            count,
+            tip,
+        })
+    }
+
+    pub fn with_cache_size(mut self, coins: usize) -> Self {
+        self.cache_size = coins;
+        self
+    }
+
+    fn read(&self, utxo: &Utxo) -> Result<Option<Coin>, UtxoError> {
+        let transaction = self.database.begin_read().map_err(redb::Error::from)?;
+        let coins = transaction.open_table(COINS).map_err(redb::Error::from)?;
+        let coin = coins.get(&encode_key(utxo)[..]).map_err(redb::Error::from)?;
+        coin.map(|coin| decode_coin(utxo, coin.value())).transpose()
+    }
+}
+
+impl UtxoStore for DatabaseStore {
+    fn get(&self, utxo: &Utxo) -> Result<Option<Coin>, UtxoError> {
+        match self.cache.get(utxo) {
+            Some(coin) => Ok(coin.clone()),
+            None => self.read(utxo),
+        }
+    }
+
+    fn insert(&mut self, utxo: Utxo, coin: Coin) -> Result<Option<Coin>, UtxoError> {
+        let previous = self.get(&utxo)?;
+        self.count += previous.is_none() as u64;
+        self.cache.insert(utxo, Some(coin));
+        Ok(previous)
+    }
+
+    fn remove(&mut self, utxo: &Utxo) -> Result<Option<Coin>, UtxoError> {
+        let previous = self.get(utxo)?;
+        if previous.is_some() {
+            self.count -= 1;
+            self.cache.insert(*utxo, None);
+        }
+        Ok(previous)
+    }
+
+    fn len(&self) -> u64 {
+        self.count
+    }
+
+    fn tip(&self) -> Option<(u32, BlockHash)> {
+        self.tip
+    }
+
+    fn set_tip(&mut self, height: u32, hash: BlockHash) -> Result<(), UtxoError> {
+        self.tip = Some((height, hash));
+        if self.cache.len() >= self.cache_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn for_each_coin(&mut self, visit: &mut dyn FnMut(&Utxo, &Coin)) -> Result<(), UtxoError> {
+        self.flush()?;
+        let transaction = self.database.begin_read().map_err(redb::Error::from)?;
+        let coins = transaction.open_table(COINS).map_err(redb::Error::from)?;
+        for entry in coins.iter().map_err(redb::Error::from)? {
+            let (key, coin) = entry.map_err(redb::Error::from)?;
+            let utxo = decode_key(key.value());
+            visit(&utxo, &decode_coin(&utxo, coin.value())?);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), UtxoError> {
+        let transaction = self.database.begin_write().map_err(redb::Error::from)?;
+        {
+            let mut coins = transaction.open_table(COINS).map_err(redb::Error::from)?;
+            for (utxo, coin) in self.cache.drain() {
+                let key = encode_key(&utxo);
+                match coin {
+                    Some(coin) => coins.insert(&key[..], &encode_coin(&coin)[..]),
+                    None => coins.remove(&key[..]),
+                }
+                .map_err(redb::Error::from)?;
+            }
+            let mut state = transaction.open_table(STATE).map_err(redb::Error::from)?;
+            state
+                .insert(COUNT_KEY, &self.count.to_le_bytes()[..])
+                .map_err(redb::Error::from)?;
+            if let Some(tip) = self.tip {
+                state.insert(TIP_KEY, &encode_tip(tip)[..]).map_err(redb::Error::from)?;
+            }
+        }
+        transaction.commit().map_err(redb::Error::from)?;
+        Ok(())
+    }
+}
+
+fn encode_key(utxo: &Utxo) -> [u8; 36] {
+    let mut key = [0; 36];
+    key[..32].copy_from_slice(utxo.txid().as_bytes());
+    BigEndian::write_u32(&mut key[32..], utxo.vout());
+    key
+}
+
+fn decode_key(key: &[u8]) -> Utxo {
+    Utxo::new(Txid::new(*array_ref!(key, 0, 32)), BigEndian::read_u32(&key[32..36]))
+}
+
+// the height and coinbase flag packed as the reference client does, then the value and the script
+fn encode_coin(coin: &Coin) -> Vec<u8> {
+    let output = coin.output();
+    let mut data = Vec::with_capacity(12 + output.pubkey().len());
+    data.extend_from_slice(&(coin.height() << 1 | coin.is_coinbase() as u32).to_le_bytes());
+    data.extend_from_slice(&output.value().to_sat().to_le_bytes());
+    data.extend_from_slice(output.pubkey().as_bytes());
+    data
+}
+
+fn decode_coin(utxo: &Utxo, data: &[u8]) -> Result<Coin, UtxoError> {
+    if data.len() < 12 {
+        return Err(UtxoError::Corrupted(*utxo));
+    }
+    let code = LittleEndian::read_u32(&data[..4]);
+    let value = Amount::from_sat(LittleEndian::read_u64(&data[4..12]));
+    let output = Output::new(value, BitcoinScript::new(data[12..].to_vec()));
+    Ok(Coin::new(output, code >> 1, code & 1 == 1))
+}
+
+fn encode_tip((height, hash): (u32, BlockHash)) -> [u8; 36] {
+    let mut data = [0; 36];
+    LittleEndian::write_u32(&mut data[..4], height);
+    data[4..].copy_from_slice(hash.as_bytes());
+    data
+}
+
+fn decode_tip(data: &[u8]) -> (u32, BlockHash) {
+    (
+        LittleEndian::read_u32(&data[..4]),
+        BlockHash::new(*array_ref!(data, 4, 32)),
+    )
+}
@@ -0,0 +1,444 @@
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use thiserror::Error;
+
+use super::compress::{compress_coin, read_coin, read_compact_size};
+use super::{Coin, UtxoError, UtxoSet, UtxoStore};
+use crate::blockchain::transactions::Utxo;
+use crate::network::Network;
+use crate::types::{BlockHash, Txid, VarInt};
+
+// the start of the snapshots written since version 28 of the reference client, the earlier ones begin
+// directly with the hash of the block
+pub const DUMP_MAGIC: [u8; 5] = *b"utxo\xff";
+pub const DUMP_VERSION: u16 = 2;
+
+// what comes before the coins of a dumptxoutset file
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DumpMetadata {
+    network: Option<Network>,
+    hash: BlockHash,
+    coins: u64,
+}
+
+impl DumpMetadata {
+    pub fn new(network: Option<Network>, hash: BlockHash, coins: u64) -> Self {
+        Self { network, hash, coins }
+    }
+
+    // only known for the snapshots with the magic bytes
+    pub fn network(&self) -> Option<Network> {
+        self.network
+    }
+
+    pub fn hash(&self) -> &BlockHash {
+        &self.hash
+    }
+
+    pub fn coins(&self) -> u64 {
+        self.coins
+    }
+}
+
+// reads the coins of a dumptxoutset file in the order they were written, which is by outpoint
+pub struct DumpReader<R: Read> {
+    reader: R,
+    metadata: DumpMetadata,
+    read: u64,
+    // the transaction being read with the number of its coins left, the legacy format has no groups
+    group: Option<(Txid, u64)>,
+    legacy: bool,
+    failed: bool,
+}
+
+impl<R: Read> DumpReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, DumpError> {
+        let mut start = [0; 32];
+        reader.read_exact(&mut start[..DUMP_MAGIC.len()])?;
+        let legacy = start[..DUMP_MAGIC.len()] != DUMP_MAGIC;
+        let network = if legacy {
+            reader.read_exact(&mut start[DUMP_MAGIC.len()..])?;
+            None
+        } else {
+            let version = reader.read_u16::<LittleEndian>()?;
+            if version != DUMP_VERSION {
+                return Err(DumpError::Version(version));
+            }
+            // the network magic is written in the order it is sent on the wire
+            let magic = reader.read_u32::<BigEndian>()?;
+            reader.read_exact(&mut start)?;
+            Some(Network::from_magic(magic).ok_or(DumpError::Network(magic))?)
+        };
+        let hash = BlockHash::new(start);
+        let coins = reader.read_u64::<LittleEndian>()?;
+        Ok(Self {
+            reader,
+            metadata: DumpMetadata::new(network, hash, coins),
+            read: 0,
+            group: None,
+            legacy,
+            failed: false,
+        })
+    }
+
+    pub fn metadata(&self) -> &DumpMetadata {
+        &self.metadata
+    }
+
+    fn read_coin(&mut self) -> Result<(Utxo, Coin), DumpError> {
+        let (txid, vout) = if self.legacy {
+            let mut txid = [0; 32];
+            self.reader.read_exact(&mut txid)?;
+            (Txid::new(txid), self.reader.read_u32::<LittleEndian>()?)
+        } else {
+            let (txid, left) = match self.group {
+                Some(group) => group,
+                None => {
+                    let mut txid = [0; 32];
+                    self.reader.read_exact(&mut txid)?;
+                    let count = read_compact_size(&mut self.reader)?;
+                    if count == 0 || count > self.metadata.coins - self.read {
+                        return Err(DumpError::Count {
+                            expected: self.metadata.coins,
+                            found: self.read.saturating_add(count),
+                        });
+                    }
+                    (Txid::new(txid), count)
+                }
+            };
+            self.group = Some((txid, left - 1)).filter(|&(_, left)| left > 0);
+            let vout = u32::try_from(read_compact_size(&mut self.reader)?)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "The output index is too large"))?;
+            (txid, vout)
+        };
+        let coin = read_coin(&mut self.reader)?;
+        self.read += 1;
+        Ok((Utxo::new(txid, vout), coin))
+    }
+
+    // the reference client refuses the snapshots with anything after the last coin
+    fn check_end(&mut self) -> Result<(), DumpError> {
+        match self.reader.read(&mut [0]) {
+            Ok(0) => Ok(()),
+            Ok(_) => Err(DumpError::Trailing),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // compares the coins with the ones of the set, which must be at the block of the snapshot
+    pub fn compare<S: UtxoStore>(mut self, set: &mut UtxoSet<S>) -> Result<DumpComparison, DumpError> {
+        let (_, hash) = set.tip().ok_or(UtxoError::Empty)?;
+        if hash != self.metadata.hash {
+            return Err(DumpError::Block {
+                expected: self.metadata.hash,
+                found: hash,
+            });
+        }
+        let mut comparison = DumpComparison::default();
+        let mut next = self.next().transpose()?;
+        let mut failure = None;
+        // both sides are ordered by outpoint, so a coin is missing from the side that skips over it
+        set.store.for_each_coin(&mut |utxo, coin| {
+            while failure.is_none() && next.as_ref().is_some_and(|(dumped, _)| dumped < utxo) {
+                comparison.only_in_dump += 1;
+                next = self.advance(&mut failure);
+            }
+            match next.take() {
+                Some((dumped, dumped_coin)) if dumped == *utxo => {
+                    match dumped_coin == *coin {
+                        true => comparison.matching += 1,
+                        false => comparison.different += 1,
+                    }
+                    next = self.advance(&mut failure);
+                }
+                dumped => {
+                    next = dumped;
+                    comparison.only_in_set += 1;
+                }
+            }
+        })?;
+        if let Some(err) = failure {
+            return Err(err);
+        }
+        if next.is_some() {
+            comparison.only_in_dump += 1;
+        }
+        for coin in self {
+            coin?;
+            comparison.only_in_dump += 1;
+        }
+        Ok(comparison)
+    }
+
+    // the next coin, keeping the error aside for the callers that can not return it
+    fn advance(&mut self, failure: &mut Option<DumpError>) -> Option<(Utxo, Coin)> {
+        match self.next().transpose() {
+            Ok(coin) => coin,
+            Err(err) => {
+                *failure = Some(err);
+                None
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for DumpReader<R> {
+    type Item = Result<(Utxo, Coin), DumpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let item = if self.read < self.metadata.coins {
+            Some(self.read_coin())
+        } else {
+            self.check_end().err().map(Err)
+        };
+        self.failed = matches!(item, Some(Err(_)));
+        item
+    }
+}
+
+// writes a dumptxoutset file in the format of the current reference client, the coins are expected in
+// outpoint order as they are grouped by transaction
+pub struct DumpWriter<W: Write> {
+    writer: W,
+    expected: u64,
+    written: u64,
+    group: Option<(Txid, Vec<(u32, Coin)>)>,
+}
+
+impl<W: Write> DumpWriter<W> {
+    pub fn new(mut writer: W, metadata: &DumpMetadata) -> Result<Self, DumpError> {
+        writer.write_all(&DUMP_MAGIC)?;
+        writer.write_u16::<LittleEndian>(DUMP_VERSION)?;
+        writer.write_u32::<BigEndian>(metadata.network.unwrap_or(Network::Bitcoin).magic())?;
+        writer.write_all(metadata.hash.as_bytes())?;
+        writer.write_u64::<LittleEndian>(metadata.coins)?;
+        Ok(Self {
+            writer,
+            expected: metadata.coins,
+            written: 0,
+            group: None,
+        })
+    }
+
+    pub fn write(&mut self, utxo: &Utxo, coin: &Coin) -> Result<(), DumpError> {
+        match self.group {
+            Some((ref txid, ref mut coins)) if txid == utxo.txid() => {
+                if coins.last().is_some_and(|&(vout, _)| vout >= utxo.vout()) {
+                    return Err(DumpError::Order);
+                }
+                coins.push((utxo.vout(), coin.clone()));
+            }
+            Some((ref txid, _)) if txid > utxo.txid() => return Err(DumpError::Order),
+            _ => {
+                self.write_group()?;
+                self.group = Some((*utxo.txid(), vec![(utxo.vout(), coin.clone())]));
+            }
+        }
+        self.written += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<W, DumpError> {
+        self.write_group()?;
+        if self.written != self.expected {
+            return Err(DumpError::Count {
+                expected: self.expected,
+                found: self.written,
+            });
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_group(&mut self) -> Result<(), DumpError> {
+        if let Some((txid, coins)) = self.group.take() {
+            let mut buffer = txid.as_bytes().to_vec();
+            buffer.extend(VarInt::new(coins.len() as u64).to_bytes());
+            for (vout, coin) in coins {
+                buffer.extend(VarInt::new(vout as u64).to_bytes());
+                compress_coin(&mut buffer, &coin);
+            }
+            self.writer.write_all(&buffer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: UtxoStore> UtxoSet<S> {
+    // loads the coins into an empty store, the height is the one of the block the snapshot was taken at
+    pub fn from_dump<R: Read>(store: S, height: u32, reader: DumpReader<R>) -> Result<Self, DumpError> {
+        let hash = *reader.metadata().hash();
+        Self::from_coins(store, height, hash, reader)
+    }
+
+    pub fn write_dump<W: Write>(&mut self, network: Network, writer: W) -> Result<W, DumpError> {
+        let (_, hash) = self.tip().ok_or(UtxoError::Empty)?;
+        let mut writer = DumpWriter::new(writer, &DumpMetadata::new(Some(network), hash, self.len()))?;
+        let mut failure = None;
+        self.store.for_each_coin(&mut |utxo, coin| {
+            if failure.is_none() {
+                failure = writer.write(utxo, coin).err();
+            }
+        })?;
+        match failure {
+            Some(err) => Err(err),
+            None => writer.finish(),
+        }
+    }
+}
+
+// how a dumptxoutset file differs from a set at the same block
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DumpComparison {
+    matching: u64,
+    different: u64,
+    only_in_dump: u64,
+    only_in_set: u64,
+}
+
+impl DumpComparison {
+    pub fn matching(&self) -> u64 {
+        self.matching
+    }
+
+    // the coins with the same outpoint but a different output, height or coinbase flag
+    pub fn different(&self) -> u64 {
+        self.different
+    }
+
+    pub fn only_in_dump(&self) -> u64 {
+        self.only_in_dump
+    }
+
+    pub fn only_in_set(&self) -> u64 {
+        self.only_in_set
+    }
+
+    pub fn is_match(&self) -> bool {
+        self.different == 0 && self.only_in_dump == 0 && self.only_in_set == 0
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum DumpError {
+    #[error("unable to read or write the snapshot")]
+    Io(#[from] io::Error),
+    #[error("unsupported snapshot version {0}")]
+    Version(u16),
+    #[error("the snapshot is for an unknown network with magic {0:08x}")]
+    Network(u32),
+    #[error("expected {expected} coins in the snapshot but found {found}")]
+    Count { expected: u64, found: u64 },
+    #[error("the snapshot has data after its last coin")]
+    Trailing,
+    #[error("the coins are not ordered by outpoint")]
+    Order,
+    #[error("the snapshot is for the block {expected} but the set is at {found}")]
+    Block { expected: BlockHash, found: BlockHash },
+    #[error(transparent)]
+    Utxo(#[from] UtxoError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::Amount;
+    use crate::blockchain::script::BitcoinScript;
+    use crate::blockchain::transactions::Output;
+    use crate::utxo::MemoryStore;
+
+    fn coins() -> Vec<(Utxo, Coin)> {
+        let output = |value, script: &str| {
+            Output::new(
+                Amount::from_sat(value),
+                BitcoinScript::new(hex::decode(script).unwrap()),
+            )
+        };
+        let p2pkh = "76a91400112233445566778899aabbccddeeff0011223388ac";
+        vec![
+            (
+                Utxo::new(Txid::new([1; 32]), 0),
+                Coin::new(output(5_000_000_000, p2pkh), 1, true),
+            ),
+            (
+                Utxo::new(Txid::new([2; 32]), 1),
+                Coin::new(output(1_234, "6a"), 7, false),
+            ),
+            (Utxo::new(Txid::new([2; 32]), 300), Coin::new(output(0, "51"), 7, false)),
+        ]
+    }
+
+    fn dump(coins: &[(Utxo, Coin)]) -> Vec<u8> {
+        let metadata = DumpMetadata::new(Some(Network::Signet), BlockHash::new([9; 32]), coins.len() as u64);
+        let mut writer = DumpWriter::new(vec![], &metadata).unwrap();
+        for (utxo, coin) in coins {
+            writer.write(utxo, coin).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn reads_back_the_written_coins() {
+        let coins = coins();
+        let dumped = dump(&coins);
+        // the header, then each txid with its count followed by the vout, height, amount and script of its coins
+        assert_eq!(
+            dumped.len(),
+            51 + (33 + 1 + 1 + 1 + 21) + (33 + (1 + 1 + 2 + 2) + (3 + 1 + 1 + 2))
+        );
+        let reader = DumpReader::new(&dumped[..]).unwrap();
+        assert_eq!(reader.metadata().network(), Some(Network::Signet));
+        assert_eq!(reader.metadata().hash(), &BlockHash::new([9; 32]));
+        assert_eq!(reader.metadata().coins(), 3);
+        let read = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        assert!(read == coins);
+    }
+
+    #[test]
+    fn reads_the_snapshots_without_magic() {
+        let coins = coins();
+        let mut legacy = [9; 32].to_vec();
+        legacy.extend(&(coins.len() as u64).to_le_bytes());
+        for (utxo, coin) in &coins {
+            legacy.extend(utxo.txid().as_bytes());
+            legacy.extend(&utxo.vout().to_le_bytes());
+            compress_coin(&mut legacy, coin);
+        }
+        let reader = DumpReader::new(&legacy[..]).unwrap();
+        assert_eq!(reader.metadata().network(), None);
+        assert!(reader.collect::<Result<Vec<_>, _>>().unwrap() == coins);
+    }
+
+    #[test]
+    fn refuses_the_data_after_the_last_coin() {
+        let mut dumped = dump(&coins());
+        dumped.push(0);
+        let read = DumpReader::new(&dumped[..]).unwrap().collect::<Result<Vec<_>, _>>();
+        assert!(matches!(read, Err(DumpError::Trailing)));
+    }
+
+    #[test]
+    fn refuses_the_coins_out_of_order() {
+        let coins = coins();
+        let metadata = DumpMetadata::new(None, BlockHash::new([9; 32]), 2);
+        let mut writer = DumpWriter::new(vec![], &metadata).unwrap();
+        writer.write(&coins[1].0, &coins[1].1).unwrap();
+        assert!(matches!(writer.write(&coins[0].0, &coins[0].1), Err(DumpError::Order)));
+    }
+
+    #[test]
+    fn loads_and_compares_a_set() {
+        let dumped = dump(&coins());
+        let mut set = UtxoSet::from_dump(MemoryStore::new(), 7, DumpReader::new(&dumped[..]).unwrap()).unwrap();
+        assert_eq!(set.tip(), Some((7, BlockHash::new([9; 32]))));
+        let comparison = DumpReader::new(&dumped[..]).unwrap().compare(&mut set).unwrap();
+        assert_eq!(comparison.matching(), 3);
+        assert!(comparison.is_match());
+        let written = set.write_dump(Network::Signet, vec![]).unwrap();
+        assert_eq!(written, dumped);
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use super::{Coin, UtxoError, UtxoStore};
+use crate::blockchain::transactions::Utxo;
+use crate::types::BlockHash;
+
+#[derive(Default)]
+pub struct MemoryStore {
+    coins: HashMap<Utxo, Coin>,
+    tip: Option<(u32, BlockHash)>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UtxoStore for MemoryStore {
+    fn get(&self, utxo: &Utxo) -> Result<Option<Coin>, UtxoError> {
+        Ok(self.coins.get(utxo).cloned())
+    }
+
+    fn insert(&mut self, utxo: Utxo, coin: Coin) -> Result<Option<Coin>, UtxoError> {
+        Ok(self.coins.insert(utxo, coin))
+    }
+
+    fn remove(&mut self, utxo: &Utxo) -> Result<Option<Coin>, UtxoError> {
+        Ok(self.coins.remove(utxo))
+    }
+
+    fn len(&self) -> u64 {
+        self.coins.len() as u64
+    }
+
+    fn tip(&self) -> Option<(u32, BlockHash)> {
+        self.tip
+    }
+
+    fn set_tip(&mut self, height: u32, hash: BlockHash) -> Result<(), UtxoError> {
+        self.tip = Some((height, hash));
+        Ok(())
+    }
+
+    fn for_each_coin(&mut self, visit: &mut dyn FnMut(&Utxo, &Coin)) -> Result<(), UtxoError> {
+        let mut utxos = self.coins.keys().collect::<Vec<_>>();
+        utxos.sort_unstable();
+        utxos.into_iter().for_each(|utxo| visit(utxo, &self.coins[utxo]));
+        Ok(())
+    }
+}
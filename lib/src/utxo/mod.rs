@@ -0,0 +1,370 @@
+#[cfg(feature = "writer")]
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+mod chainstate;
+mod compress;
+#[cfg(feature = "database")]
+mod database;
+mod dump;
+mod memory;
+
+pub use chainstate::{ChainstateCoins, ChainstateError, ChainstateReader};
+#[cfg(feature = "database")]
+pub use database::{DatabaseStore, DEFAULT_CACHE_SIZE};
+pub use dump::{DumpComparison, DumpError, DumpMetadata, DumpReader, DumpWriter, DUMP_MAGIC, DUMP_VERSION};
+pub use memory::MemoryStore;
+
+use crate::amount::Amount;
+#[cfg(feature = "parser")]
+use crate::blkparser::SerialBlock;
+use crate::blockchain::transactions::{Output, Transaction, Utxo};
+use crate::types::{BlockHash, Txid};
+#[cfg(feature = "parser")]
+use crate::visitor::ChainVisitor;
+
+// coins loaded between the writes of a store, as nothing else writes them while loading a set
+const LOAD_BATCH: usize = 1_000_000;
+
+// an unspent output with the height of the block that created it
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct Coin {
+    output: Output,
+    height: u32,
+    coinbase: bool,
+}
+
+impl Coin {
+    pub fn new(output: Output, height: u32, coinbase: bool) -> Self {
+        Self {
+            output,
+            height,
+            coinbase,
+        }
+    }
+
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn is_coinbase(&self) -> bool {
+        self.coinbase
+    }
+
+    pub fn into_output(self) -> Output {
+        self.output
+    }
+}
+
+// where the coins are kept. The store also keeps the last block applied, so a persisted set can resume
+// after it
+pub trait UtxoStore {
+    fn get(&self, utxo: &Utxo) -> Result<Option<Coin>, UtxoError>;
+
+    fn insert(&mut self, utxo: Utxo, coin: Coin) -> Result<Option<Coin>, UtxoError>;
+
+    fn remove(&mut self, utxo: &Utxo) -> Result<Option<Coin>, UtxoError>;
+
+    fn len(&self) -> u64;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn tip(&self) -> Option<(u32, BlockHash)>;
+
+    // called once every block is applied, the store may write its changes to disk at this point
+    fn set_tip(&mut self, height: u32, hash: BlockHash) -> Result<(), UtxoError>;
+
+    // visits the coins in outpoint order, the one of the reference client snapshots
+    fn for_each_coin(&mut self, visit: &mut dyn FnMut(&Utxo, &Coin)) -> Result<(), UtxoError>;
+
+    fn flush(&mut self) -> Result<(), UtxoError> {
+        Ok(())
+    }
+}
+
+// replays the blocks in height order, adding their outputs and removing the ones they spend
+pub struct UtxoSet<S: UtxoStore = MemoryStore> {
+    store: S,
+    stop: Option<u32>,
+    error: Option<UtxoError>,
+}
+
+impl UtxoSet<MemoryStore> {
+    pub fn new() -> Self {
+        Self::with_store(MemoryStore::new())
+    }
+}
+
+impl Default for UtxoSet<MemoryStore> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: UtxoStore> UtxoSet<S> {
+    pub fn with_store(store: S) -> Self {
+        Self {
+            store,
+            stop: None,
+            error: None,
+        }
+    }
+
+    // rebuilds the set as it was at the height of the snapshot
+    pub fn from_snapshot(mut store: S, snapshot: UtxoSnapshot) -> Result<Self, UtxoError> {
+        for (utxo, coin) in snapshot.coins {
+            store.insert(utxo, coin)?;
+        }
+        store.set_tip(snapshot.height, snapshot.hash)?;
+        store.flush()?;
+        Ok(Self::with_store(store))
+    }
+
+    // loads the coins of a set kept elsewhere into an empty store. Its height is not kept with the coins, so
+    // it has to be resolved from the hash of its block, see BitcoinParser::locate
+    fn from_coins<E: From<UtxoError>>(
+        mut store: S,
+        height: u32,
+        hash: BlockHash,
+        coins: impl Iterator<Item = Result<(Utxo, Coin), E>>,
+    ) -> Result<Self, E> {
+        for (position, coin) in coins.enumerate() {
+            let (utxo, coin) = coin?;
+            store.insert(utxo, coin)?;
+            if (position + 1) % LOAD_BATCH == 0 {
+                store.flush()?;
+            }
+        }
+        store.set_tip(height, hash)?;
+        store.flush()?;
+        Ok(Self::with_store(store))
+    }
+
+    // blocks above the height are ignored when visiting the chain, leaving the set as it was at that height.
+    // A persisted set already past it cannot go back
+    pub fn stop_at(mut self, height: u32) -> Result<Self, UtxoError> {
+        if let Some(tip) = self.height().filter(|&tip| tip > height) {
+            return Err(UtxoError::Ahead { tip, stop: height });
+        }
+        self.stop = Some(height);
+        Ok(self)
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    pub fn get(&self, utxo: &Utxo) -> Result<Option<Coin>, UtxoError> {
+        self.store.get(utxo)
+    }
+
+    pub fn len(&self) -> u64 {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    pub fn tip(&self) -> Option<(u32, BlockHash)> {
+        self.store.tip()
+    }
+
+    pub fn height(&self) -> Option<u32> {
+        self.tip().map(|(height, _)| height)
+    }
+
+    // the blocks must follow each other, the block at height zero being the genesis block. A set resumed
+    // from a store, a snapshot or a chainstate only takes the block built on its tip
+    pub fn apply_transactions<'a>(
+        &mut self,
+        height: u32,
+        hash: BlockHash,
+        previous: BlockHash,
+        transactions: impl IntoIterator<Item = (Txid, &'a Transaction)>,
+    ) -> Result<(), UtxoError> {
+        let expected = self.height().map_or(0, |height| height + 1);
+        if height != expected {
+            return Err(UtxoError::Height {
+                expected,
+                found: height,
+            });
+        }
+        if let Some((_, tip)) = self.tip().filter(|&(_, tip)| tip != previous) {
+            return Err(UtxoError::Previous { tip, found: previous });
+        }
+        for (txid, transaction) in transactions {
+            self.apply_transaction(height, txid, transaction)?;
+        }
+        self.store.set_tip(height, hash)
+    }
+
+    #[cfg(feature = "parser")]
+    pub fn apply_block(&mut self, height: u32, block: &SerialBlock) -> Result<(), UtxoError> {
+        let transactions = block
+            .transactions()
+            .iter()
+            .map(|transaction| (*transaction.hash(), transaction.transaction()));
+        self.apply_transactions(height, *block.hash(), *block.header().previous(), transactions)
+    }
+
+    fn apply_transaction(&mut self, height: u32, txid: Txid, transaction: &Transaction) -> Result<(), UtxoError> {
+        let coinbase = transaction.is_coinbase();
+        if !coinbase {
+            for input in transaction.inputs() {
+                if self.store.remove(input.utxo())?.is_none() {
+                    return Err(UtxoError::Missing(*input.utxo(), height));
+                }
+            }
+        }
+        // the outputs of the genesis block were never added to the reference client database
+        if height == 0 {
+            return Ok(());
+        }
+        for (vout, output) in transaction.outputs().iter().enumerate() {
+            if output.pubkey().is_unspendable() {
+                continue;
+            }
+            let utxo = Utxo::new(txid, vout as u32);
+            // the coinbases of blocks 91842 and 91880 repeat earlier ones before BIP30, the reference client
+            // overwrites the unspent outputs and so do we
+            if self
+                .store
+                .insert(utxo, Coin::new(output.clone(), height, coinbase))?
+                .is_some()
+                && !coinbase
+            {
+                return Err(UtxoError::Duplicate(utxo, height));
+            }
+        }
+        Ok(())
+    }
+
+    // the sum of the coins without keeping them, none if it overflows
+    pub fn total_value(&mut self) -> Result<Option<Amount>, UtxoError> {
+        let mut total = Some(Amount::ZERO);
+        self.store.for_each_coin(&mut |_, coin| {
+            total = total.and_then(|total| total.checked_add(coin.output().value()));
+        })?;
+        Ok(total)
+    }
+
+    // the coins ordered by outpoint, along with the block they are the result of
+    pub fn snapshot(&mut self) -> Result<UtxoSnapshot, UtxoError> {
+        let (height, hash) = self.tip().ok_or(UtxoError::Empty)?;
+        let mut coins = Vec::with_capacity(self.len() as usize);
+        self.store
+            .for_each_coin(&mut |utxo, coin| coins.push((*utxo, coin.clone())))?;
+        Ok(UtxoSnapshot { height, hash, coins })
+    }
+
+    pub fn flush(&mut self) -> Result<(), UtxoError> {
+        self.store.flush()
+    }
+
+    // the error that stopped the set while visiting the chain, if any
+    pub fn take_error(&mut self) -> Option<UtxoError> {
+        self.error.take()
+    }
+}
+
+#[cfg(feature = "parser")]
+impl<S: UtxoStore> ChainVisitor for UtxoSet<S> {
+    fn anchor(&self) -> Option<(u32, BlockHash)> {
+        self.tip()
+    }
+
+    fn on_block(&mut self, height: u32, block: &SerialBlock) {
+        // the blocks already in a persisted set are skipped, so it resumes after its tip
+        let applied = self.height().is_some_and(|tip| height <= tip);
+        let stopped = self.stop.is_some_and(|stop| height > stop);
+        if self.error.is_some() || applied || stopped {
+            return;
+        }
+        if let Err(err) = self.apply_block(height, block) {
+            self.error = Some(err);
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Err(err) = self.store.flush() {
+            self.error.get_or_insert(err);
+        }
+    }
+}
+
+#[cfg_attr(feature = "writer", derive(Serialize, Deserialize))]
+pub struct UtxoSnapshot {
+    height: u32,
+    hash: BlockHash,
+    coins: Vec<(Utxo, Coin)>,
+}
+
+impl UtxoSnapshot {
+    pub fn new(height: u32, hash: BlockHash, coins: Vec<(Utxo, Coin)>) -> Self {
+        Self { height, hash, coins }
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn hash(&self) -> &BlockHash {
+        &self.hash
+    }
+
+    pub fn coins(&self) -> &[(Utxo, Coin)] {
+        &self.coins
+    }
+
+    pub fn len(&self) -> usize {
+        self.coins.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coins.is_empty()
+    }
+
+    pub fn total_value(&self) -> Option<Amount> {
+        Amount::checked_sum(self.coins.iter().map(|(_, coin)| coin.output().value()))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum UtxoError {
+    #[error("the output {}:{} spent at height {1} is not in the set", .0.txid(), .0.vout())]
+    Missing(Utxo, u32),
+    #[error("the output {}:{} created at height {1} is already in the set", .0.txid(), .0.vout())]
+    Duplicate(Utxo, u32),
+    #[error("expected the block at height {expected} but found the one at height {found}")]
+    Height { expected: u32, found: u32 },
+    #[error("the block is built on {found} instead of the tip {tip} of the set")]
+    Previous { tip: BlockHash, found: BlockHash },
+    #[error("the set is at height {tip}, past the height {stop} to stop at")]
+    Ahead { tip: u32, stop: u32 },
+    #[error("no block has been applied to the set")]
+    Empty,
+    #[error("the stored coin for {}:{} is corrupted", .0.txid(), .0.vout())]
+    Corrupted(Utxo),
+    #[cfg(feature = "database")]
+    #[error("unable to access the utxo database")]
+    Database(#[source] Box<redb::Error>),
+}
+
+#[cfg(feature = "database")]
+impl From<redb::Error> for UtxoError {
+    fn from(source: redb::Error) -> Self {
+        Self::Database(Box::new(source))
+    }
+}
@@ -0,0 +1,206 @@
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::blkparser::SerialBlock;
+use crate::blockchain::transactions::{Input, Output};
+use crate::types::BlockHash;
+use crate::{Transaction, TransactionBlock};
+
+// hooks for a single pass over the chain, called in height order without keeping the blocks around. The
+// block model is the one the parser decodes the blocks into
+pub trait ChainVisitor<B: TransactionBlock = SerialBlock> {
+    // the height and hash of the last block the visitor already has, which places the blocks of files that
+    // do not start at the genesis block
+    fn anchor(&self) -> Option<(u32, BlockHash)> {
+        None
+    }
+
+    fn start(&mut self) {}
+
+    fn on_block(&mut self, _height: u32, _block: &B) {}
+
+    fn on_transaction(&mut self, _height: u32, _transaction: &B::Transaction) {}
+
+    fn on_input(&mut self, _transaction: &B::Transaction, _vin: usize, _input: &Input) {}
+
+    fn on_output(&mut self, _transaction: &B::Transaction, _vout: usize, _output: &Output) {}
+
+    fn finish(&mut self) {}
+
+    // walks the block calling the other hooks, each transaction goes before its inputs and outputs
+    fn visit_block(&mut self, height: u32, block: &B) {
+        self.on_block(height, block);
+        for transaction in block.transactions() {
+            self.on_transaction(height, transaction);
+            for (vin, input) in transaction.inputs().iter().enumerate() {
+                self.on_input(transaction, vin, input);
+            }
+            for (vout, output) in transaction.outputs().iter().enumerate() {
+                self.on_output(transaction, vout, output);
+            }
+        }
+    }
+}
+
+impl<B: TransactionBlock, V: ChainVisitor<B> + ?Sized> ChainVisitor<B> for &mut V {
+    fn anchor(&self) -> Option<(u32, BlockHash)> {
+        (**self).anchor()
+    }
+
+    fn start(&mut self) {
+        (**self).start()
+    }
+
+    fn on_block(&mut self, height: u32, block: &B) {
+        (**self).on_block(height, block)
+    }
+
+    fn on_transaction(&mut self, height: u32, transaction: &B::Transaction) {
+        (**self).on_transaction(height, transaction)
+    }
+
+    fn on_input(&mut self, transaction: &B::Transaction, vin: usize, input: &Input) {
+        (**self).on_input(transaction, vin, input)
+    }
+
+    fn on_output(&mut self, transaction: &B::Transaction, vout: usize, output: &Output) {
+        (**self).on_output(transaction, vout, output)
+    }
+
+    fn finish(&mut self) {
+        (**self).finish()
+    }
+
+    fn visit_block(&mut self, height: u32, block: &B) {
+        (**self).visit_block(height, block)
+    }
+}
+
+impl<B: TransactionBlock, V: ChainVisitor<B> + ?Sized> ChainVisitor<B> for Box<V> {
+    fn anchor(&self) -> Option<(u32, BlockHash)> {
+        (**self).anchor()
+    }
+
+    fn start(&mut self) {
+        (**self).start()
+    }
+
+    fn on_block(&mut self, height: u32, block: &B) {
+        (**self).on_block(height, block)
+    }
+
+    fn on_transaction(&mut self, height: u32, transaction: &B::Transaction) {
+        (**self).on_transaction(height, transaction)
+    }
+
+    fn on_input(&mut self, transaction: &B::Transaction, vin: usize, input: &Input) {
+        (**self).on_input(transaction, vin, input)
+    }
+
+    fn on_output(&mut self, transaction: &B::Transaction, vout: usize, output: &Output) {
+        (**self).on_output(transaction, vout, output)
+    }
+
+    fn finish(&mut self) {
+        (**self).finish()
+    }
+
+    fn visit_block(&mut self, height: u32, block: &B) {
+        (**self).visit_block(height, block)
+    }
+}
+
+// runs several visitors in the same pass. The ones that can be sent to another thread visit each block
+// at the same time when the parallel feature is enabled, the local ones visit it afterwards in order
+pub struct VisitorSet<'a, B: TransactionBlock = SerialBlock> {
+    shared: Vec<Box<dyn ChainVisitor<B> + Send + 'a>>,
+    local: Vec<Box<dyn ChainVisitor<B> + 'a>>,
+}
+
+impl<B: TransactionBlock> Default for VisitorSet<'_, B> {
+    fn default() -> Self {
+        Self {
+            shared: vec![],
+            local: vec![],
+        }
+    }
+}
+
+impl<'a, B: TransactionBlock> VisitorSet<'a, B> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push<V: ChainVisitor<B> + Send + 'a>(&mut self, visitor: V) {
+        self.shared.push(Box::new(visitor));
+    }
+
+    pub fn push_local<V: ChainVisitor<B> + 'a>(&mut self, visitor: V) {
+        self.local.push(Box::new(visitor));
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.len() + self.local.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn for_each(&mut self, mut hook: impl FnMut(&mut dyn ChainVisitor<B>)) {
+        for visitor in &mut self.shared {
+            hook(visitor.as_mut());
+        }
+        for visitor in &mut self.local {
+            hook(visitor.as_mut());
+        }
+    }
+}
+
+// the shared visitors get the block from several threads, so the block model has to be Sync
+impl<B: TransactionBlock + Sync> ChainVisitor<B> for VisitorSet<'_, B> {
+    // the visitors that resume a pass have to agree on where it resumes, the first anchor places the blocks
+    fn anchor(&self) -> Option<(u32, BlockHash)> {
+        let shared = self.shared.iter().map(|visitor| visitor.anchor());
+        let local = self.local.iter().map(|visitor| visitor.anchor());
+        shared.chain(local).flatten().next()
+    }
+
+    fn start(&mut self) {
+        self.for_each(|visitor| visitor.start());
+    }
+
+    fn on_block(&mut self, height: u32, block: &B) {
+        self.for_each(|visitor| visitor.on_block(height, block));
+    }
+
+    fn on_transaction(&mut self, height: u32, transaction: &B::Transaction) {
+        self.for_each(|visitor| visitor.on_transaction(height, transaction));
+    }
+
+    fn on_input(&mut self, transaction: &B::Transaction, vin: usize, input: &Input) {
+        self.for_each(|visitor| visitor.on_input(transaction, vin, input));
+    }
+
+    fn on_output(&mut self, transaction: &B::Transaction, vout: usize, output: &Output) {
+        self.for_each(|visitor| visitor.on_output(transaction, vout, output));
+    }
+
+    fn finish(&mut self) {
+        self.for_each(|visitor| visitor.finish());
+    }
+
+    fn visit_block(&mut self, height: u32, block: &B) {
+        #[cfg(feature = "parallel")]
+        self.shared
+            .par_iter_mut()
+            .for_each(|visitor| visitor.visit_block(height, block));
+        #[cfg(not(feature = "parallel"))]
+        self.shared
+            .iter_mut()
+            .for_each(|visitor| visitor.visit_block(height, block));
+        for visitor in &mut self.local {
+            visitor.visit_block(height, block);
+        }
+    }
+}
@@ -15,7 +15,7 @@ impl App {
     pub fn parse_from_cli() -> Result<Self, Error> {
         let config = load_yaml!("interface.yml");
         let matches = CApp::from_yaml(config).get_matches();
-        Ok(App::from_matches(&matches)?)
+        App::from_matches(&matches)
     }
 
     fn from_matches(matches: &ArgMatches) -> Result<Self, Error> {
@@ -26,6 +26,10 @@ impl App {
     }
 
     pub fn run(&self) -> Result<()> {
+        // the errors only capture their backtrace when asked to through the environment
+        if self.trace {
+            std::env::set_var("RUST_LIB_BACKTRACE", "1");
+        }
         self.operation.run()
     }
 }
@@ -33,12 +37,34 @@ impl App {
 #[derive(Debug)]
 enum Operation {
     Dump(ops::Dump),
+    NullData(ops::NullData),
+    Inscriptions(ops::Inscriptions),
+    Runes(ops::Runes),
+    PubKeys(ops::PubKeys),
+    Descriptors(ops::Descriptors),
+    Signatures(ops::Signatures),
+    Utxo(ops::Utxo),
+    TxGraph(ops::TxGraph),
+    AddrGraph(ops::AddrGraph),
+    Clusters(ops::Clusters),
+    Change(ops::Change),
 }
 
 impl Operation {
     fn from_matches(matches: &ArgMatches) -> Result<Self, Error> {
         match matches.subcommand() {
             ("dump", Some(sb_matches)) => Ok(Self::Dump(ops::Dump::from_matches(sb_matches))),
+            ("nulldata", Some(sb_matches)) => Ok(Self::NullData(ops::NullData::from_matches(sb_matches))),
+            ("inscriptions", Some(sb_matches)) => Ok(Self::Inscriptions(ops::Inscriptions::from_matches(sb_matches))),
+            ("runes", Some(sb_matches)) => Ok(Self::Runes(ops::Runes::from_matches(sb_matches))),
+            ("pubkeys", Some(sb_matches)) => Ok(Self::PubKeys(ops::PubKeys::from_matches(sb_matches))),
+            ("descriptors", Some(sb_matches)) => Ok(Self::Descriptors(ops::Descriptors::from_matches(sb_matches))),
+            ("signatures", Some(sb_matches)) => Ok(Self::Signatures(ops::Signatures::from_matches(sb_matches))),
+            ("utxo", Some(sb_matches)) => Ok(Self::Utxo(ops::Utxo::from_matches(sb_matches))),
+            ("txgraph", Some(sb_matches)) => Ok(Self::TxGraph(ops::TxGraph::from_matches(sb_matches))),
+            ("addrgraph", Some(sb_matches)) => Ok(Self::AddrGraph(ops::AddrGraph::from_matches(sb_matches))),
+            ("clusters", Some(sb_matches)) => Ok(Self::Clusters(ops::Clusters::from_matches(sb_matches))),
+            ("change", Some(sb_matches)) => Ok(Self::Change(ops::Change::from_matches(sb_matches))),
             _ => unreachable!(), // subcommand specification is required from a restricted subset in interface.yml
         }
     }
@@ -46,6 +72,17 @@ impl Operation {
     fn run(&self) -> Result<()> {
         match *self {
             Operation::Dump(ref op) => op.run(),
+            Operation::NullData(ref op) => op.run(),
+            Operation::Inscriptions(ref op) => op.run(),
+            Operation::Runes(ref op) => op.run(),
+            Operation::PubKeys(ref op) => op.run(),
+            Operation::Descriptors(ref op) => op.run(),
+            Operation::Signatures(ref op) => op.run(),
+            Operation::Utxo(ref op) => op.run(),
+            Operation::TxGraph(ref op) => op.run(),
+            Operation::AddrGraph(ref op) => op.run(),
+            Operation::Clusters(ref op) => op.run(),
+            Operation::Change(ref op) => op.run(),
         }
     }
 }
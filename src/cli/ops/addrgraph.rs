@@ -0,0 +1,161 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::path::{Path, PathBuf};
+
+use btlib::address::Address;
+use btlib::amount::AmountFormat;
+use btlib::blkparser::BitcoinParser;
+use btlib::disk::JsonWriter;
+use btlib::disk::Writer;
+use btlib::graph::addresses::{AddressGraph, Aggregation};
+use btlib::graph::chain::ResolvedChain;
+use btlib::graph::scripts::ScriptIndex;
+use btlib::graph::Direction;
+use btlib::network::Network;
+
+use super::{get_amount_format, get_block_files, get_optional_path, get_path, ExclusionArgs};
+
+#[derive(Debug)]
+pub struct AddrGraph {
+    folder: bool,
+    path: PathBuf,
+    target: Option<PathBuf>,
+    amounts: AmountFormat,
+    start: Option<String>,
+    height: Option<String>,
+    aggregation: Aggregation,
+    exclusions: ExclusionArgs,
+    address: Option<String>,
+    network: Network,
+}
+
+impl AddrGraph {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let (folder, path) = get_path(matches);
+        let aggregation = match matches.value_of("aggregation") {
+            Some("day") => Aggregation::Day,
+            _ => Aggregation::Transaction,
+        };
+        let network = matches
+            .value_of("network")
+            .unwrap_or("bitcoin")
+            .parse()
+            .expect("Value is restricted to the networks present at interface.yml");
+
+        Self {
+            folder,
+            path,
+            target: get_optional_path(matches, "target"),
+            amounts: get_amount_format(matches),
+            start: matches.value_of("start").map(ToOwned::to_owned),
+            height: matches.value_of("height").map(ToOwned::to_owned),
+            aggregation,
+            exclusions: ExclusionArgs::from_matches(matches),
+            address: matches.value_of("address").map(ToOwned::to_owned),
+            network,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let mut chain = ResolvedChain::new();
+        if let Some(ref start) = self.start {
+            chain = chain.start_at(
+                start
+                    .parse()
+                    .with_context(|| format!("Invalid block height {}", start))?,
+            );
+        }
+        if let Some(ref height) = self.height {
+            chain = chain.stop_at(
+                height
+                    .parse()
+                    .with_context(|| format!("Invalid block height {}", height))?,
+            );
+        }
+        let mut graph = AddressGraph::new(self.aggregation, self.exclusions.build()?);
+        BitcoinParser::default()
+            .visit(
+                &get_block_files(self.folder, &self.path)?,
+                &mut chain.pass().with(&mut graph),
+            )
+            .context("Unable to parse the blk files")?;
+        if let Some(err) = chain.take_error() {
+            return Err(err).context("Unable to add the blocks to the graph");
+        }
+        if let Some(tip) = chain.tip() {
+            println!("{:<16}{}", "height", tip);
+        }
+        println!("{:<16}{}", "addresses", graph.len());
+        println!("{:<16}{}", "edges", graph.edges().len());
+        println!("{:<16}{}", "excluded", graph.excluded());
+        if let Some(ref address) = self.address {
+            self.print_address(&graph, chain.scripts(), address)?;
+        }
+        if let Some(ref target) = self.target {
+            self.save(&graph, chain.scripts(), target)?;
+        }
+        Ok(())
+    }
+
+    fn print_address(&self, graph: &AddressGraph, scripts: &ScriptIndex, address: &str) -> Result<()> {
+        let script = Address::decode(address, self.network)
+            .with_context(|| format!("Invalid address {}", address))?
+            .script_pubkey();
+        let id = scripts
+            .id(script.as_bytes())
+            .with_context(|| format!("The address {} is not in the graph", address))?;
+        let stats = graph.stats(id);
+        println!();
+        println!("{:<16}{}", "address", address);
+        println!("{:<16}{:?}", "type", scripts.script_type(id));
+        println!(
+            "{:<16}{} ({})",
+            "first seen",
+            stats.first_seen().height(),
+            stats.first_seen().time()
+        );
+        println!(
+            "{:<16}{} ({})",
+            "last seen",
+            stats.last_seen().height(),
+            stats.last_seen().time()
+        );
+        println!("{:<16}{} in {} outputs", "received", stats.received(), stats.outputs());
+        println!("{:<16}{} from {} outputs", "sent", stats.sent(), stats.spent());
+        println!("{:<16}{}", "balance", stats.balance());
+        for (title, direction) in [("sends to", Direction::Forward), ("receives from", Direction::Backward)] {
+            println!();
+            println!("{:<68}{:>20}{:>14}", title, "value", "transactions");
+            for neighbour in graph.neighbours(id, direction) {
+                println!(
+                    "{:<68}{:>20}{:>14}",
+                    scripts.label(neighbour.script(), self.network),
+                    neighbour.value().to_string(),
+                    neighbour.transactions()
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&self, graph: &AddressGraph, scripts: &ScriptIndex, target: &Path) -> Result<()> {
+        let addresses = scripts
+            .ids()
+            .map(|id| (scripts.label(id, self.network), graph.stats(id)))
+            .collect::<Vec<_>>();
+        let edges = graph
+            .edges()
+            .iter()
+            .map(|edge| graph.labeled(edge, scripts, self.network))
+            .collect::<Vec<_>>();
+        let mut writer = JsonWriter::new(target).with_amounts(self.amounts);
+        writer
+            .save(&addresses, "addresses.json")
+            .context("Unable to save the addresses")?;
+        writer
+            .save(&edges, "address_edges.json")
+            .context("Unable to save the address edges")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::path::PathBuf;
+
+use btlib::blkparser::BitcoinParser;
+use btlib::disk::{JsonWriter, RecordBatches};
+use btlib::graph::addresses::Seen;
+use btlib::graph::chain::ResolvedChain;
+use btlib::graph::change::{
+    AddressReuse, ChangeAnalysis, ChangeDetector, ChangeVerdict, FreshAddress, OptimalChange, RoundNumber,
+    ScriptTypeMatch, UnnecessaryInput,
+};
+use btlib::graph::GraphError;
+use btlib::types::Txid;
+
+use super::{get_block_files, get_optional_path, get_path, RECORD_BLOCKS};
+
+#[derive(Debug)]
+pub struct Change {
+    folder: bool,
+    path: PathBuf,
+    target: Option<PathBuf>,
+    start: Option<String>,
+    height: Option<String>,
+    heuristics: Vec<String>,
+    threshold: String,
+    txid: Option<String>,
+}
+
+impl Change {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let (folder, path) = get_path(matches);
+        let heuristics = matches
+            .values_of("heuristic")
+            .map(|values| values.map(ToOwned::to_owned).collect())
+            .unwrap_or_default();
+
+        Self {
+            folder,
+            path,
+            target: get_optional_path(matches, "target"),
+            start: matches.value_of("start").map(ToOwned::to_owned),
+            height: matches.value_of("height").map(ToOwned::to_owned),
+            heuristics,
+            threshold: matches.value_of("threshold").unwrap_or("0.5").to_owned(),
+            txid: matches.value_of("txid").map(ToOwned::to_owned),
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let mut chain = ResolvedChain::new().detect_change(self.build_detector()?);
+        if let Some(ref start) = self.start {
+            chain = chain.start_at(
+                start
+                    .parse()
+                    .with_context(|| format!("Invalid block height {}", start))?,
+            );
+        }
+        if let Some(ref height) = self.height {
+            chain = chain.stop_at(
+                height
+                    .parse()
+                    .with_context(|| format!("Invalid block height {}", height))?,
+            );
+        }
+        let txid = match self.txid {
+            Some(ref txid) => Some(
+                txid.parse::<Txid>()
+                    .with_context(|| format!("Invalid transaction id {}", txid))?,
+            ),
+            None => None,
+        };
+        // the verdicts are written as they come, only the one of the transaction asked for is kept
+        let mut records = self
+            .target
+            .as_deref()
+            .map(|target| RecordBatches::new(JsonWriter::new(target), "change", RECORD_BLOCKS));
+        let mut found = None;
+        let mut analysis = ChangeAnalysis::new(|seen: Seen, verdict: &ChangeVerdict| {
+            if txid.as_ref() == Some(verdict.txid()) {
+                found = Some(verdict.clone());
+            }
+            if let Some(records) = records.as_mut() {
+                records.push(seen.height(), verdict.clone())?;
+            }
+            Ok::<_, GraphError>(())
+        });
+        BitcoinParser::default()
+            .visit(
+                &get_block_files(self.folder, &self.path)?,
+                &mut chain.pass().with(&mut analysis),
+            )
+            .context("Unable to parse the blk files")?;
+        if let Some(err) = chain.take_error() {
+            return Err(err).context("Unable to analyse the blocks");
+        }
+        if let Some(tip) = chain.tip() {
+            println!("{:<16}{}", "height", tip);
+        }
+        println!("{:<16}{}", "transactions", analysis.transactions());
+        println!("{:<16}{}", "change found", analysis.changes());
+        self.print_heuristics(&chain, &analysis);
+        if let Some(records) = records.as_mut() {
+            records
+                .flush()
+                .context("Unable to save the change of the transactions")?;
+        }
+        if let Some(txid) = txid {
+            let verdict = found.with_context(|| format!("The transaction {} has no verdict", txid))?;
+            self.print_verdict(&verdict);
+        }
+        Ok(())
+    }
+
+    fn build_detector(&self) -> Result<ChangeDetector> {
+        let threshold = self
+            .threshold
+            .parse::<f64>()
+            .ok()
+            .filter(|threshold| (0.0..=1.0).contains(threshold))
+            .with_context(|| format!("Invalid score {}", self.threshold))?;
+        let detector = match self.heuristics.is_empty() {
+            true => ChangeDetector::default(),
+            false => self
+                .heuristics
+                .iter()
+                .fold(ChangeDetector::new(), |detector, heuristic| match heuristic.as_str() {
+                    "address-reuse" => detector.with(AddressReuse, 1.0),
+                    "script-type" => detector.with(ScriptTypeMatch, 1.0),
+                    "round-number" => detector.with(RoundNumber::default(), 1.0),
+                    "unnecessary-input" => detector.with(UnnecessaryInput, 1.0),
+                    "fresh-address" => detector.with(FreshAddress, 1.0),
+                    "optimal-change" => detector.with(OptimalChange, 1.0),
+                    _ => unreachable!("Value is restricted to the heuristics present at interface.yml"),
+                }),
+        };
+        Ok(detector.threshold(threshold))
+    }
+
+    // the transactions each heuristic tells something about, and how many times its best output is the change
+    fn print_heuristics<F>(&self, chain: &ResolvedChain, analysis: &ChangeAnalysis<F>)
+    where
+        F: FnMut(Seen, &ChangeVerdict) -> Result<(), GraphError>,
+    {
+        let mut names = chain
+            .detector()
+            .into_iter()
+            .flat_map(|detector| detector.names())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        println!();
+        println!("{:<24}{:>14}{:>14}", "heuristic", "applied", "agreeing");
+        for name in names {
+            let counts = analysis.counts(name);
+            println!("{:<24}{:>14}{:>14}", name, counts.applied(), counts.agreeing());
+        }
+    }
+
+    fn print_verdict(&self, verdict: &ChangeVerdict) {
+        println!();
+        println!("{:<16}{}", "txid", verdict.txid());
+        println!(
+            "{:<16}{}",
+            "change",
+            verdict
+                .change()
+                .map(|vout| vout.to_string())
+                .unwrap_or_else(|| "none".to_owned())
+        );
+        println!();
+        print!("{:<24}", "vout");
+        for vout in 0..verdict.scores().len() {
+            print!("{:>8}", vout);
+        }
+        println!();
+        for heuristic in verdict.heuristics() {
+            print!("{:<24}", heuristic.heuristic());
+            for score in heuristic.scores() {
+                print!("{:>8.2}", score);
+            }
+            println!();
+        }
+        print!("{:<24}", "combined");
+        for score in verdict.scores() {
+            print!("{:>8.2}", score);
+        }
+        println!();
+    }
+}
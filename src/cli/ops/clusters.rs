@@ -0,0 +1,220 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use btlib::address::Address;
+use btlib::amount::AmountFormat;
+use btlib::blkparser::BitcoinParser;
+use btlib::disk::JsonWriter;
+use btlib::disk::Writer;
+use btlib::graph::chain::ResolvedChain;
+use btlib::graph::change::ChangeDetector;
+use btlib::graph::clusters::{AddressClusters, Clusters as ClusterSet};
+use btlib::graph::scripts::{ScriptIndex, ScriptResolver};
+use btlib::network::Network;
+use btlib::utxo::{DatabaseStore, UtxoStore};
+
+use super::{get_amount_format, get_block_files, get_optional_path, get_path, ExclusionArgs};
+
+#[derive(Debug)]
+pub struct Clusters {
+    blocks: Option<(bool, PathBuf)>,
+    target: Option<PathBuf>,
+    amounts: AmountFormat,
+    start: Option<String>,
+    height: Option<String>,
+    load: Option<PathBuf>,
+    save: Option<PathBuf>,
+    exclusions: ExclusionArgs,
+    change: bool,
+    address: Option<String>,
+    network: Network,
+}
+
+impl Clusters {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let blocks = Some(matches)
+            .filter(|matches| matches.is_present("file") || matches.is_present("dir"))
+            .map(get_path);
+        let network = matches
+            .value_of("network")
+            .unwrap_or("bitcoin")
+            .parse()
+            .expect("Value is restricted to the networks present at interface.yml");
+
+        Self {
+            blocks,
+            target: get_optional_path(matches, "target"),
+            amounts: get_amount_format(matches),
+            start: matches.value_of("start").map(ToOwned::to_owned),
+            height: matches.value_of("height").map(ToOwned::to_owned),
+            load: get_optional_path(matches, "load"),
+            save: get_optional_path(matches, "save"),
+            exclusions: ExclusionArgs::from_matches(matches),
+            change: matches.is_present("change"),
+            address: matches.value_of("address").map(ToOwned::to_owned),
+            network,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let exclusions = self.exclusions.build()?;
+        let dir = match self.load {
+            Some(ref dir) => dir,
+            None => match self.save {
+                Some(ref dir) => dir,
+                None => return self.build(ResolvedChain::new(), AddressClusters::new(exclusions), None),
+            },
+        };
+        // the unspent outputs are kept along with the clusters, they are only valid for the scripts saved with them
+        fs::create_dir_all(dir).context("Unable to create the clusters directory")?;
+        let store = DatabaseStore::open(dir.join("unspent.redb")).context("Unable to open the utxo database")?;
+        if self.load.is_none() {
+            let store = Some(store).filter(|store| store.tip().is_none()).with_context(|| {
+                format!(
+                    "The directory {} already holds clusters, load them instead",
+                    dir.display()
+                )
+            })?;
+            let chain = ResolvedChain::with_resolver(ScriptResolver::with_store(ScriptIndex::new(), store));
+            return self.build(chain, AddressClusters::new(exclusions), Some(dir));
+        }
+        let reader = BufReader::new(File::open(dir.join("scripts")).context("Unable to open the scripts file")?);
+        let chain = ResolvedChain::read(reader, store)
+            .with_context(|| format!("Unable to read the scripts of the clusters {}", dir.display()))?;
+        let reader = BufReader::new(File::open(dir.join("clusters")).context("Unable to open the clusters file")?);
+        let clusters = AddressClusters::read(exclusions, reader)
+            .with_context(|| format!("Unable to read the clusters {}", dir.display()))?;
+        let clusters = Some(clusters)
+            .filter(|clusters| clusters.len() == chain.scripts().len())
+            .with_context(|| {
+                format!(
+                    "The clusters {} do not match the scripts saved with them",
+                    dir.display()
+                )
+            })?;
+        self.build(chain, clusters, Some(dir))
+    }
+
+    // adds the blocks to the clusters, saving them to the directory given
+    fn build<S: UtxoStore>(
+        &self,
+        mut chain: ResolvedChain<S>,
+        mut clusters: AddressClusters,
+        dir: Option<&Path>,
+    ) -> Result<()> {
+        if self.change {
+            chain = chain.detect_change(ChangeDetector::default());
+            clusters = clusters.join_change();
+        }
+        if let Some(ref start) = self.start {
+            chain = chain.start_at(
+                start
+                    .parse()
+                    .with_context(|| format!("Invalid block height {}", start))?,
+            );
+        }
+        if let Some(ref height) = self.height {
+            chain = chain.stop_at(
+                height
+                    .parse()
+                    .with_context(|| format!("Invalid block height {}", height))?,
+            );
+        }
+        if let Some((folder, ref path)) = self.blocks {
+            BitcoinParser::default()
+                .visit(&get_block_files(folder, path)?, &mut chain.pass().with(&mut clusters))
+                .context("Unable to parse the blk files")?;
+            if let Some(err) = chain.take_error() {
+                return Err(err).context("Unable to add the blocks to the clusters");
+            }
+        }
+        if let Some(dir) = dir {
+            let writer =
+                BufWriter::new(File::create(dir.join("scripts")).context("Unable to create the scripts file")?);
+            chain
+                .write(writer)
+                .with_context(|| format!("Unable to write the scripts of the clusters {}", dir.display()))?;
+            let writer =
+                BufWriter::new(File::create(dir.join("clusters")).context("Unable to create the clusters file")?);
+            clusters
+                .write(writer)
+                .with_context(|| format!("Unable to write the clusters {}", dir.display()))?;
+        }
+        let set = clusters.clusters();
+        if let Some(tip) = chain.tip() {
+            println!("{:<16}{}", "height", tip);
+        }
+        println!("{:<16}{}", "addresses", clusters.len());
+        println!("{:<16}{}", "clusters", set.len());
+        println!("{:<16}{}", "excluded", clusters.excluded());
+        if self.change {
+            println!("{:<16}{}", "changes", clusters.changes());
+        }
+        if let Some(ref address) = self.address {
+            self.print_cluster(&clusters, chain.scripts(), &set, address)?;
+        }
+        if let Some(ref target) = self.target {
+            self.save(chain.scripts(), &set, target)?;
+        }
+        Ok(())
+    }
+
+    fn print_cluster(
+        &self,
+        clusters: &AddressClusters,
+        scripts: &ScriptIndex,
+        set: &ClusterSet,
+        address: &str,
+    ) -> Result<()> {
+        let script = Address::decode(address, self.network)
+            .with_context(|| format!("Invalid address {}", address))?
+            .script_pubkey();
+        let id = scripts
+            .id(script.as_bytes())
+            .with_context(|| format!("The address {} is not in the clusters", address))?;
+        let cluster = set.cluster_of(id);
+        let stats = set.stats(cluster);
+        println!();
+        println!("{:<16}{}", "cluster", cluster.index());
+        println!("{:<16}{}", "size", stats.size());
+        println!(
+            "{:<16}{} ({})",
+            "first seen",
+            stats.first_seen().height(),
+            stats.first_seen().time()
+        );
+        println!("{:<16}{}", "received", stats.received());
+        println!("{:<16}{}", "sent", stats.sent());
+        println!("{:<16}{}", "balance", stats.balance());
+        println!();
+        println!("{:<68}{:>20}", "address", "balance");
+        for member in set.members(cluster) {
+            println!(
+                "{:<68}{:>20}",
+                scripts.label(member, self.network),
+                clusters.stats(member).balance().to_string()
+            );
+        }
+        Ok(())
+    }
+
+    fn save(&self, scripts: &ScriptIndex, set: &ClusterSet, target: &Path) -> Result<()> {
+        let membership = scripts
+            .ids()
+            .map(|id| (scripts.label(id, self.network), set.cluster_of(id)))
+            .collect::<Vec<_>>();
+        let stats = set.iter().collect::<Vec<_>>();
+        let mut writer = JsonWriter::new(target).with_amounts(self.amounts);
+        writer
+            .save(&membership, "clusters.json")
+            .context("Unable to save the cluster of the addresses")?;
+        writer
+            .save(&stats, "cluster_stats.json")
+            .context("Unable to save the stats of the clusters")?;
+        Ok(())
+    }
+}
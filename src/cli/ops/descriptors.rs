@@ -0,0 +1,76 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::io;
+use std::path::PathBuf;
+
+use btlib::blkparser::{BitcoinParser, SerialTransaction};
+use btlib::blockchain::descriptor::TransactionDescriptors;
+use btlib::disk::{JsonWriter, RecordBatches};
+use btlib::network::Network;
+use btlib::visitor::ChainVisitor;
+
+use super::{get_block_files, get_path, get_required_path, RECORD_BLOCKS};
+
+#[derive(Debug)]
+pub struct Descriptors {
+    folder: bool,
+    path: PathBuf,
+    target: PathBuf,
+    network: Network,
+}
+
+impl Descriptors {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let (folder, path) = get_path(matches);
+        let target = get_required_path(matches, "target");
+        let network = matches
+            .value_of("network")
+            .unwrap_or("bitcoin")
+            .parse()
+            .expect("Value is restricted to the networks present at interface.yml");
+
+        Self {
+            folder,
+            path,
+            target,
+            network,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let mut visitor = DescriptorsVisitor {
+            records: RecordBatches::new(JsonWriter::new(&self.target), "descriptors", RECORD_BLOCKS),
+            network: self.network,
+            error: None,
+        };
+        BitcoinParser::default()
+            .visit(&get_block_files(self.folder, &self.path)?, &mut visitor)
+            .context("Unable to parse the blk files")?;
+        if let Some(err) = visitor.error {
+            return Err(err).context("Unable to save the descriptors");
+        }
+        Ok(())
+    }
+}
+
+struct DescriptorsVisitor<'a> {
+    records: RecordBatches<'a, TransactionDescriptors>,
+    network: Network,
+    error: Option<io::Error>,
+}
+
+impl ChainVisitor for DescriptorsVisitor<'_> {
+    fn on_transaction(&mut self, height: u32, transaction: &SerialTransaction) {
+        if self.error.is_none() {
+            let descriptors = TransactionDescriptors::new(*transaction.hash(), transaction.transaction(), self.network);
+            self.error = self.records.push(height, descriptors).err();
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.error.is_none() {
+            self.error = self.records.flush().err();
+        }
+    }
+}
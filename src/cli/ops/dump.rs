@@ -1,65 +1,48 @@
 use anyhow::Context;
 use anyhow::Result;
 use clap::ArgMatches;
-use std::env::current_dir;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 
-use btlib::blkparser::BitcoinParser;
+use btlib::amount::AmountFormat;
+use btlib::blkparser::{BitcoinParser, SerialBlock};
+use btlib::blockchain::BlockChain;
 use btlib::disk::JsonWriter;
 use btlib::disk::Writer;
 use btlib::parser::ParallelParser;
 
+use super::{get_amount_format, get_path, get_required_path};
+
 #[derive(Debug)]
 pub struct Dump {
     folder: bool,
     path: PathBuf,
     target: PathBuf,
+    amounts: AmountFormat,
 }
 
 impl Dump {
     pub fn from_matches(matches: &ArgMatches) -> Self {
-        let (folder, path) = Self::get_path(matches);
-        let target = Self::get_required_path(matches, "target");
-
-        Self { folder, path, target }
-    }
+        let (folder, path) = get_path(matches);
+        let target = get_required_path(matches, "target");
+        let amounts = get_amount_format(matches);
 
-    fn get_path(matches: &ArgMatches) -> (bool, PathBuf) {
-        let folder = matches.is_present("dir");
-        (
+        Self {
             folder,
-            if folder {
-                Self::get_required_path(matches, "dir")
-            } else {
-                Self::get_required_path(matches, "file")
-            },
-        )
-    }
-
-    fn get_required_path(matches: &ArgMatches, path: &str) -> PathBuf {
-        let path_value = matches
-            .value_of(path)
-            .expect("Value is required in configuration present at interface.yml");
-        let path = PathBuf::from(path_value);
-
-        if !path.is_absolute() {
-            let mut curr_dir = current_dir().expect("Unable to retrieve the current directory");
-            curr_dir.push(path);
-            curr_dir
-        } else {
-            path
+            path,
+            target,
+            amounts,
         }
     }
 
     pub fn run(&self) -> Result<()> {
         if self.folder {
-            let mut runner = FolderRunner::new(&self.path, &self.target);
+            let mut runner = FolderRunner::new(&self.path, &self.target, self.amounts);
             runner.run()
         } else {
             let mut parser = BitcoinParser::default();
-            let mut writer = JsonWriter::new(&self.target);
+            let mut writer = JsonWriter::new(&self.target).with_amounts(self.amounts);
             let mut runner = FileRunnerRef::new(&self.path, &mut parser, &mut writer);
             runner.run()
         }
@@ -77,27 +60,26 @@ impl Dump {
 struct FolderRunner<'a> {
     path: &'a Path,
     target: &'a Path,
+    amounts: AmountFormat,
 }
 
 impl<'a> FolderRunner<'a> {
-    fn new(path: &'a Path, target: &'a Path) -> Self {
-        Self { path, target }
+    fn new(path: &'a Path, target: &'a Path, amounts: AmountFormat) -> Self {
+        Self { path, target, amounts }
     }
 
     fn run(&mut self) -> Result<()> {
         let mut parser = BitcoinParser::default();
-        let mut writer = JsonWriter::new(&self.target);
-        fs::read_dir(&self.path)
+        let mut writer = JsonWriter::new(self.target).with_amounts(self.amounts);
+        fs::read_dir(self.path)
             .context("Unable to read the given folder")?
             .filter_map(Result::ok)
             .filter(|entry| entry.path().is_file())
-            .map(|entry| {
+            .try_for_each(|entry| {
                 let path = entry.path();
                 let mut runner = FileRunnerRef::new(&path, &mut parser, &mut writer);
                 runner.run()
             })
-            .collect::<Result<()>>()?;
-        Ok(())
     }
 }
 
@@ -113,10 +95,10 @@ impl<'b, 'a, 'c> FileRunnerRef<'b, 'a, 'c> {
     }
 
     fn run(&mut self) -> Result<()> {
-        let path = Dump::get_file_save_path(&self.path);
-        let blockchain = self
+        let path = Dump::get_file_save_path(self.path);
+        let blockchain: BlockChain<SerialBlock> = self
             .parser
-            .parse(&self.path)
+            .parse(self.path)
             .context("Unable to parse the blk file contents")?;
         self.writer
             .save(blockchain, path)
@@ -0,0 +1,97 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use btlib::blkparser::{BitcoinParser, SerialTransaction};
+use btlib::disk::{JsonWriter, RecordBatches};
+use btlib::protocols::inscription::{Inscription, InscriptionRecord};
+use btlib::visitor::ChainVisitor;
+
+use super::{get_block_files, get_path, get_required_path, RECORD_BLOCKS};
+
+#[derive(Debug)]
+pub struct Inscriptions {
+    folder: bool,
+    path: PathBuf,
+    target: PathBuf,
+    records: bool,
+}
+
+impl Inscriptions {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let (folder, path) = get_path(matches);
+        let target = get_required_path(matches, "target");
+        let records = matches.value_of("format") == Some("records");
+
+        Self {
+            folder,
+            path,
+            target,
+            records,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let mut visitor = InscriptionsVisitor {
+            target: &self.target,
+            records: self
+                .records
+                .then(|| RecordBatches::new(JsonWriter::new(&self.target), "inscriptions", RECORD_BLOCKS)),
+            error: None,
+        };
+        BitcoinParser::default()
+            .visit(&get_block_files(self.folder, &self.path)?, &mut visitor)
+            .context("Unable to parse the blk files")?;
+        if let Some(err) = visitor.error {
+            return Err(err).context("Unable to save the inscriptions");
+        }
+        Ok(())
+    }
+}
+
+// without records each inscription body is written as a file once found
+struct InscriptionsVisitor<'a> {
+    target: &'a Path,
+    records: Option<RecordBatches<'a, InscriptionRecord>>,
+    error: Option<io::Error>,
+}
+
+impl InscriptionsVisitor<'_> {
+    fn save(&mut self, height: u32, record: InscriptionRecord) -> io::Result<()> {
+        if let Some(ref mut records) = self.records {
+            return records.push(height, record);
+        }
+        let inscription = record.inscription();
+        match inscription.body() {
+            Some(body) => fs::write(
+                self.target
+                    .join(format!("{}.{}", record.id(), inscription.file_extension())),
+                body,
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ChainVisitor for InscriptionsVisitor<'_> {
+    fn on_transaction(&mut self, height: u32, transaction: &SerialTransaction) {
+        if self.error.is_some() {
+            return;
+        }
+        for inscription in Inscription::from_transaction(transaction.transaction()) {
+            if let Err(err) = self.save(height, InscriptionRecord::new(transaction.hash(), inscription)) {
+                self.error = Some(err);
+                return;
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        if let (Some(records), None) = (&mut self.records, &self.error) {
+            self.error = records.flush().err();
+        }
+    }
+}
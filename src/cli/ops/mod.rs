@@ -0,0 +1,146 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::env::current_dir;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use btlib::amount::AmountFormat;
+use btlib::graph::clusters::Exclusions;
+
+mod addrgraph;
+mod change;
+mod clusters;
+mod descriptors;
+mod dump;
+mod inscriptions;
+mod nulldata;
+mod pubkeys;
+mod runes;
+mod signatures;
+mod txgraph;
+mod utxo;
+
+pub use addrgraph::AddrGraph;
+pub use change::Change;
+pub use clusters::Clusters;
+pub use descriptors::Descriptors;
+pub use dump::Dump;
+pub use inscriptions::Inscriptions;
+pub use nulldata::NullData;
+pub use pubkeys::PubKeys;
+pub use runes::Runes;
+pub use signatures::Signatures;
+pub use txgraph::TxGraph;
+pub use utxo::Utxo;
+
+// the blocks whose records are written in the same file, about the ones of a blk file at current sizes
+const RECORD_BLOCKS: u32 = 100;
+
+// the transactions left apart by the graphs of the scripts, parsed when the op runs
+#[derive(Debug)]
+struct ExclusionArgs {
+    coinjoin_outputs: Option<String>,
+    max_inputs: Option<String>,
+    max_pairs: Option<String>,
+}
+
+impl ExclusionArgs {
+    fn from_matches(matches: &ArgMatches) -> Self {
+        Self {
+            coinjoin_outputs: matches.value_of("skip-coinjoins").map(ToOwned::to_owned),
+            max_inputs: matches.value_of("max-inputs").map(ToOwned::to_owned),
+            max_pairs: matches.value_of("max-pairs").map(ToOwned::to_owned),
+        }
+    }
+
+    fn build(&self) -> Result<Exclusions> {
+        let mut exclusions = Exclusions::new();
+        if let Some(ref outputs) = self.coinjoin_outputs {
+            exclusions = exclusions.skip_coinjoins(
+                outputs
+                    .parse()
+                    .with_context(|| format!("Invalid number of outputs {}", outputs))?,
+            );
+        }
+        if let Some(ref inputs) = self.max_inputs {
+            exclusions = exclusions.max_inputs(
+                inputs
+                    .parse()
+                    .with_context(|| format!("Invalid number of inputs {}", inputs))?,
+            );
+        }
+        if let Some(ref pairs) = self.max_pairs {
+            exclusions = exclusions.max_pairs(
+                pairs
+                    .parse()
+                    .with_context(|| format!("Invalid number of pairs {}", pairs))?,
+            );
+        }
+        Ok(exclusions)
+    }
+}
+
+fn get_path(matches: &ArgMatches) -> (bool, PathBuf) {
+    let folder = matches.is_present("dir");
+    (
+        folder,
+        if folder {
+            get_required_path(matches, "dir")
+        } else {
+            get_required_path(matches, "file")
+        },
+    )
+}
+
+fn get_required_path(matches: &ArgMatches, path: &str) -> PathBuf {
+    let path_value = matches
+        .value_of(path)
+        .expect("Value is required in configuration present at interface.yml");
+    get_absolute_path(path_value)
+}
+
+fn get_amount_format(matches: &ArgMatches) -> AmountFormat {
+    match matches.value_of("amounts") {
+        Some("btc") => AmountFormat::Bitcoins,
+        _ => AmountFormat::Satoshis,
+    }
+}
+
+fn get_optional_path(matches: &ArgMatches, path: &str) -> Option<PathBuf> {
+    matches.value_of(path).map(get_absolute_path)
+}
+
+fn get_absolute_path(path_value: &str) -> PathBuf {
+    let path = PathBuf::from(path_value);
+
+    if !path.is_absolute() {
+        let mut curr_dir = current_dir().expect("Unable to retrieve the current directory");
+        curr_dir.push(path);
+        curr_dir
+    } else {
+        path
+    }
+}
+
+fn get_block_files(folder: bool, path: &Path) -> Result<Vec<PathBuf>> {
+    if !folder {
+        return Ok(vec![path.to_owned()]);
+    }
+    let mut files = fs::read_dir(path)
+        .context("Unable to read the given folder")?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_block_file(path))
+        .collect::<Vec<_>>();
+    files.sort();
+    Ok(files)
+}
+
+fn is_block_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .map(|name| name.starts_with("blk") && name.ends_with(".dat"))
+        .unwrap_or(false)
+}
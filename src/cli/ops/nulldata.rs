@@ -0,0 +1,107 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::path::PathBuf;
+
+use btlib::amount::AmountFormat;
+use btlib::blkparser::{BitcoinParser, SerialTransaction};
+use btlib::disk::JsonWriter;
+use btlib::disk::Writer;
+use btlib::protocols::nulldata::{NullDataStats, ProtocolRegistry};
+use btlib::visitor::ChainVisitor;
+
+use super::{get_amount_format, get_block_files, get_optional_path, get_path};
+
+#[derive(Debug)]
+pub struct NullData {
+    folder: bool,
+    path: PathBuf,
+    target: Option<PathBuf>,
+    amounts: AmountFormat,
+    prefixes: Vec<String>,
+}
+
+impl NullData {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let (folder, path) = get_path(matches);
+        let target = get_optional_path(matches, "target");
+        let prefixes = matches
+            .values_of("prefix")
+            .map(|values| values.map(ToOwned::to_owned).collect())
+            .unwrap_or_default();
+
+        Self {
+            folder,
+            path,
+            target,
+            amounts: get_amount_format(matches),
+            prefixes,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let mut visitor = NullDataVisitor {
+            registry: self.build_registry()?,
+            stats: NullDataStats::new(),
+        };
+        BitcoinParser::default()
+            .visit(&get_block_files(self.folder, &self.path)?, &mut visitor)
+            .context("Unable to parse the blk files")?;
+        let stats = visitor.stats;
+        Self::print_stats(&stats);
+        if let Some(ref target) = self.target {
+            JsonWriter::new(target)
+                .with_amounts(self.amounts)
+                .save(&stats, "nulldata.json")
+                .context("Unable to save the null data statistics")?;
+        }
+        Ok(())
+    }
+
+    fn build_registry(&self) -> Result<ProtocolRegistry> {
+        let mut registry = ProtocolRegistry::new();
+        for prefix in &self.prefixes {
+            let (name, hex_prefix) = prefix
+                .split_once(':')
+                .with_context(|| format!("Expected NAME:HEX for the prefix {}", prefix))?;
+            let bytes = hex::decode(hex_prefix).with_context(|| format!("Invalid hex prefix {}", hex_prefix))?;
+            registry.register(name, bytes);
+        }
+        Ok(registry)
+    }
+
+    fn print_stats(stats: &NullDataStats) {
+        println!(
+            "{:<24}{:>14}{:>16}{:>20}",
+            "protocol", "outputs", "bytes", "value (sat)"
+        );
+        for (protocol, protocol_stats) in stats.protocols() {
+            println!(
+                "{:<24}{:>14}{:>16}{:>20}",
+                protocol,
+                protocol_stats.outputs(),
+                protocol_stats.bytes(),
+                protocol_stats.value().to_sat()
+            );
+        }
+        let total = stats.total();
+        println!(
+            "{:<24}{:>14}{:>16}{:>20}",
+            "total",
+            total.outputs(),
+            total.bytes(),
+            total.value().to_sat()
+        );
+    }
+}
+
+struct NullDataVisitor {
+    registry: ProtocolRegistry,
+    stats: NullDataStats,
+}
+
+impl ChainVisitor for NullDataVisitor {
+    fn on_transaction(&mut self, _height: u32, transaction: &SerialTransaction) {
+        self.stats.record_transaction(&self.registry, transaction.transaction());
+    }
+}
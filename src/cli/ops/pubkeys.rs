@@ -0,0 +1,84 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use btlib::analysis::pubkeys::{KeyFormat, KeyIndex};
+use btlib::blkparser::BitcoinParser;
+use btlib::disk::JsonWriter;
+use btlib::disk::Writer;
+
+use super::{get_block_files, get_optional_path, get_path};
+
+#[derive(Debug)]
+pub struct PubKeys {
+    folder: bool,
+    path: PathBuf,
+    target: Option<PathBuf>,
+}
+
+impl PubKeys {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let (folder, path) = get_path(matches);
+        let target = get_optional_path(matches, "target");
+
+        Self { folder, path, target }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let mut index = KeyIndex::new();
+        BitcoinParser::default()
+            .visit(&get_block_files(self.folder, &self.path)?, &mut index)
+            .context("Unable to parse the blk files")?;
+        Self::print_stats(&index);
+        if let Some(ref target) = self.target {
+            let entries = index.entries().collect::<Vec<_>>();
+            JsonWriter::new(target)
+                .save(&entries, "pubkeys.json")
+                .context("Unable to save the public key index")?;
+        }
+        Ok(())
+    }
+
+    fn print_stats(index: &KeyIndex) {
+        // a key seen in several formats counts once under each of them
+        let mut formats = BTreeMap::<KeyFormat, (u64, u64, u64, u64)>::new();
+        for entry in index.entries() {
+            let mut off_curve = BTreeMap::<KeyFormat, bool>::new();
+            for encoding in entry.encodings() {
+                *off_curve.entry(encoding.key().format()).or_default() |= !encoding.is_on_curve();
+            }
+            for (format, off_curve) in off_curve {
+                let stats = formats.entry(format).or_default();
+                stats.0 += 1;
+                stats.1 += off_curve as u64;
+                stats.2 += entry.is_reused() as u64;
+                stats.3 += entry.is_exposed_in_output() as u64;
+            }
+        }
+        println!(
+            "{:<24}{:>14}{:>14}{:>14}{:>14}",
+            "format", "keys", "off curve", "reused", "in outputs"
+        );
+        for (format, (keys, off_curve, reused, exposed)) in formats {
+            println!(
+                "{:<24}{:>14}{:>14}{:>14}{:>14}",
+                format, keys, off_curve, reused, exposed
+            );
+        }
+        println!(
+            "{:<24}{:>14}{:>14}{:>14}{:>14}",
+            "total",
+            index.len(),
+            index.entries().filter(|entry| !entry.is_on_curve()).count(),
+            index.reused().count(),
+            index.entries().filter(|entry| entry.is_exposed_in_output()).count()
+        );
+        println!(
+            "{:<24}{:>14}",
+            "mixed encodings",
+            index.entries().filter(|entry| entry.encodings().len() > 1).count()
+        );
+    }
+}
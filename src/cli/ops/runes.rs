@@ -0,0 +1,102 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::io;
+use std::path::PathBuf;
+
+use btlib::blkparser::{BitcoinParser, SerialTransaction};
+use btlib::disk::{JsonWriter, RecordBatches};
+use btlib::protocols::runes::{Artifact, RuneRecord};
+use btlib::visitor::ChainVisitor;
+
+use super::{get_block_files, get_optional_path, get_path, RECORD_BLOCKS};
+
+#[derive(Debug)]
+pub struct Runes {
+    folder: bool,
+    path: PathBuf,
+    target: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct RuneSummary {
+    runestones: u64,
+    cenotaphs: u64,
+    etchings: u64,
+    mints: u64,
+    edicts: u64,
+}
+
+impl Runes {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let (folder, path) = get_path(matches);
+        let target = get_optional_path(matches, "target");
+
+        Self { folder, path, target }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let mut visitor = RunesVisitor {
+            summary: RuneSummary::default(),
+            records: self
+                .target
+                .as_deref()
+                .map(|target| RecordBatches::new(JsonWriter::new(target), "runes", RECORD_BLOCKS)),
+            error: None,
+        };
+        BitcoinParser::default()
+            .visit(&get_block_files(self.folder, &self.path)?, &mut visitor)
+            .context("Unable to parse the blk files")?;
+        if let Some(err) = visitor.error {
+            return Err(err).context("Unable to save the rune records");
+        }
+        visitor.summary.print();
+        Ok(())
+    }
+}
+
+struct RunesVisitor<'a> {
+    summary: RuneSummary,
+    records: Option<RecordBatches<'a, RuneRecord>>,
+    error: Option<io::Error>,
+}
+
+impl ChainVisitor for RunesVisitor<'_> {
+    fn on_transaction(&mut self, height: u32, transaction: &SerialTransaction) {
+        if let Some(artifact) = Artifact::from_transaction(transaction.transaction()) {
+            self.summary.record(&artifact);
+            if let (Some(records), None) = (&mut self.records, &self.error) {
+                self.error = records
+                    .push(height, RuneRecord::new(*transaction.hash(), artifact))
+                    .err();
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        if let (Some(records), None) = (&mut self.records, &self.error) {
+            self.error = records.flush().err();
+        }
+    }
+}
+
+impl RuneSummary {
+    fn record(&mut self, artifact: &Artifact) {
+        if artifact.is_cenotaph() {
+            self.cenotaphs += 1;
+        } else {
+            self.runestones += 1;
+        }
+        self.etchings += artifact.is_etching() as u64;
+        self.mints += artifact.mint().is_some() as u64;
+        self.edicts += artifact.edicts().len() as u64;
+    }
+
+    fn print(&self) {
+        println!("{:<24}{:>14}", "runestones", self.runestones);
+        println!("{:<24}{:>14}", "cenotaphs", self.cenotaphs);
+        println!("{:<24}{:>14}", "etchings", self.etchings);
+        println!("{:<24}{:>14}", "mints", self.mints);
+        println!("{:<24}{:>14}", "edicts", self.edicts);
+    }
+}
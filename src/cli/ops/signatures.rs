@@ -0,0 +1,88 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::io;
+use std::path::PathBuf;
+
+use btlib::analysis::signatures::{BlockSignatureStats, SignatureStats};
+use btlib::blkparser::{BitcoinParser, SerialBlock};
+use btlib::disk::{JsonWriter, RecordBatches};
+use btlib::visitor::{ChainVisitor, VisitorSet};
+
+use super::{get_block_files, get_optional_path, get_path, RECORD_BLOCKS};
+
+#[derive(Debug)]
+pub struct Signatures {
+    folder: bool,
+    path: PathBuf,
+    target: Option<PathBuf>,
+}
+
+impl Signatures {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let (folder, path) = get_path(matches);
+        let target = get_optional_path(matches, "target");
+
+        Self { folder, path, target }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let files = get_block_files(self.folder, &self.path)?;
+        let mut total = SignatureStats::new();
+        let mut blocks = self.target.as_deref().map(|target| BlockSignaturesVisitor {
+            records: RecordBatches::new(JsonWriter::new(target), "signatures", RECORD_BLOCKS),
+            error: None,
+        });
+        // the stats of each block are only gathered when they are written, next to the totals
+        {
+            let mut visitors = VisitorSet::<SerialBlock>::new();
+            visitors.push(&mut total);
+            if let Some(blocks) = blocks.as_mut() {
+                visitors.push(blocks);
+            }
+            BitcoinParser::default()
+                .visit(&files, &mut visitors)
+                .context("Unable to parse the blk files")?;
+        }
+        if let Some(err) = blocks.and_then(|blocks| blocks.error) {
+            return Err(err).context("Unable to save the signature statistics");
+        }
+        Self::print_stats(&total);
+        Ok(())
+    }
+
+    fn print_stats(stats: &SignatureStats) {
+        println!("{:<24}{:>14}", "signatures", stats.signatures());
+        println!("{:<24}{:>14}", "invalid", stats.invalid());
+        println!("{:<24}{:>14}", "strict der", stats.strict_der());
+        println!("{:<24}{:>14}", "low s", stats.low_s());
+        println!("{:<24}{:>14}", "low r", stats.low_r());
+        println!("{:<24}{:>14}", "canonical", stats.canonical());
+        for (sighash, count) in stats.sighash_types() {
+            println!("{:<24}{:>14}", format!("sighash {}", sighash), count);
+        }
+    }
+}
+
+struct BlockSignaturesVisitor<'a> {
+    records: RecordBatches<'a, BlockSignatureStats>,
+    error: Option<io::Error>,
+}
+
+impl ChainVisitor for BlockSignaturesVisitor<'_> {
+    fn on_block(&mut self, height: u32, block: &SerialBlock) {
+        if self.error.is_none() {
+            let stats = BlockSignatureStats::new(
+                *block.hash(),
+                block.transactions().iter().map(|transaction| transaction.transaction()),
+            );
+            self.error = self.records.push(height, stats).err();
+        }
+    }
+
+    fn finish(&mut self) {
+        if self.error.is_none() {
+            self.error = self.records.flush().err();
+        }
+    }
+}
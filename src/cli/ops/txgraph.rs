@@ -0,0 +1,152 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::fs;
+use std::path::PathBuf;
+
+use btlib::amount::{Amount, AmountFormat};
+use btlib::blkparser::BitcoinParser;
+use btlib::disk::JsonWriter;
+use btlib::disk::Writer;
+use btlib::graph::transactions::TransactionGraph;
+use btlib::graph::{Direction, GraphError};
+use btlib::types::Txid;
+use btlib::utxo::DatabaseStore;
+
+use super::{get_amount_format, get_block_files, get_optional_path, get_path, get_required_path};
+
+#[derive(Debug)]
+pub struct TxGraph {
+    blocks: Option<(bool, PathBuf)>,
+    target: Option<PathBuf>,
+    amounts: AmountFormat,
+    start: Option<String>,
+    height: Option<String>,
+    graph: PathBuf,
+    txid: Option<String>,
+    direction: Direction,
+    depth: Option<String>,
+    min_value: Option<String>,
+}
+
+impl TxGraph {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let blocks = Some(matches)
+            .filter(|matches| matches.is_present("file") || matches.is_present("dir"))
+            .map(get_path);
+        let direction = match matches.value_of("direction") {
+            Some("backward") => Direction::Backward,
+            _ => Direction::Forward,
+        };
+
+        Self {
+            blocks,
+            target: get_optional_path(matches, "target"),
+            amounts: get_amount_format(matches),
+            start: matches.value_of("start").map(ToOwned::to_owned),
+            height: matches.value_of("height").map(ToOwned::to_owned),
+            graph: get_required_path(matches, "graph"),
+            txid: matches.value_of("txid").map(ToOwned::to_owned),
+            direction,
+            depth: matches.value_of("depth").map(ToOwned::to_owned),
+            min_value: matches.value_of("min-value").map(ToOwned::to_owned),
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        // the store is kept along with the files of the graph, it is only valid for the graph built with it
+        fs::create_dir_all(&self.graph).context("Unable to create the graph directory")?;
+        let store = DatabaseStore::open(self.graph.join("unspent.redb")).context("Unable to open the utxo database")?;
+        let mut graph = TransactionGraph::open(&self.graph, store)
+            .with_context(|| format!("Unable to open the graph {}", self.graph.display()))?;
+        if let Some(ref start) = self.start {
+            graph = graph.start_at(
+                start
+                    .parse()
+                    .with_context(|| format!("Invalid block height {}", start))?,
+            );
+        }
+        if let Some(ref height) = self.height {
+            graph = graph.stop_at(
+                height
+                    .parse()
+                    .with_context(|| format!("Invalid block height {}", height))?,
+            );
+        }
+        if let Some((folder, ref path)) = self.blocks {
+            BitcoinParser::default()
+                .visit(&get_block_files(folder, path)?, &mut graph)
+                .context("Unable to parse the blk files")?;
+            if let Some(err) = graph.take_error() {
+                return Err(err).context("Unable to add the blocks to the graph");
+            }
+        }
+        if let Some(tip) = graph.tip() {
+            println!("{:<16}{}", "height", tip);
+        }
+        println!("{:<16}{}", "transactions", graph.len());
+        println!("{:<16}{}", "spends", graph.spend_count());
+        println!("{:<16}{}", "unspent", graph.unspent_count());
+        if let Some(ref txid) = self.txid {
+            self.walk(&graph, txid)?;
+        }
+        Ok(())
+    }
+
+    fn walk(&self, graph: &TransactionGraph<DatabaseStore>, txid: &str) -> Result<()> {
+        let txid = txid
+            .parse::<Txid>()
+            .with_context(|| format!("Invalid transaction id {}", txid))?;
+        let node = graph
+            .node(&txid)
+            .context("Unable to read the graph")?
+            .with_context(|| format!("The transaction {} is not in the graph", txid))?;
+        let mut traversal = graph.traverse(node, self.direction);
+        if let Some(ref depth) = self.depth {
+            traversal = traversal.max_depth(depth.parse().with_context(|| format!("Invalid depth {}", depth))?);
+        }
+        if let Some(ref value) = self.min_value {
+            let value = value
+                .parse::<Amount>()
+                .with_context(|| format!("Invalid amount {}", value))?;
+            traversal = traversal.min_value(value);
+        }
+        // the output followed to reach each transaction, none for the one the walk starts at
+        let steps = traversal
+            .map(|step| {
+                let step = step?;
+                let spend = step.spend();
+                Ok((
+                    step.depth(),
+                    graph.txid(step.node())?,
+                    graph.height(step.node())?,
+                    spend.map(|spend| spend.vout()),
+                    spend.map(|spend| spend.value()),
+                ))
+            })
+            .collect::<Result<Vec<_>, GraphError>>()
+            .context("Unable to read the graph")?;
+        println!();
+        println!(
+            "{:<8}{:<68}{:>10}{:>8}{:>20}",
+            "depth", "txid", "height", "vout", "value"
+        );
+        for (depth, txid, height, vout, value) in &steps {
+            println!(
+                "{:<8}{:<68}{:>10}{:>8}{:>20}",
+                depth,
+                txid.to_string(),
+                height,
+                vout.map(|vout| vout.to_string()).unwrap_or_default(),
+                value.map(|value| value.to_string()).unwrap_or_default()
+            );
+        }
+        if let Some(ref target) = self.target {
+            JsonWriter::new(target)
+                .with_amounts(self.amounts)
+                .save(&steps, "txgraph.json")
+                .context("Unable to save the transactions reached")?;
+        }
+        Ok(())
+    }
+}
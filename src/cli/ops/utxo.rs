@@ -0,0 +1,156 @@
+use anyhow::Context;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use btlib::amount::AmountFormat;
+use btlib::blkparser::BitcoinParser;
+use btlib::disk::JsonWriter;
+use btlib::disk::Writer;
+use btlib::network::Network;
+use btlib::types::BlockHash;
+use btlib::utxo::{ChainstateReader, DatabaseStore, DumpReader, MemoryStore, UtxoSet, UtxoStore};
+
+use super::{get_amount_format, get_block_files, get_optional_path, get_path};
+
+#[derive(Debug)]
+pub struct Utxo {
+    folder: bool,
+    path: PathBuf,
+    target: Option<PathBuf>,
+    amounts: AmountFormat,
+    height: Option<String>,
+    database: Option<PathBuf>,
+    dump: Option<PathBuf>,
+    from_dump: Option<PathBuf>,
+    from_chainstate: Option<PathBuf>,
+    check: Option<PathBuf>,
+    network: Network,
+}
+
+impl Utxo {
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let (folder, path) = get_path(matches);
+        let target = get_optional_path(matches, "target");
+        let height = matches.value_of("height").map(ToOwned::to_owned);
+        let database = get_optional_path(matches, "database");
+        let dump = get_optional_path(matches, "dump");
+        let from_dump = get_optional_path(matches, "from-dump");
+        let from_chainstate = get_optional_path(matches, "from-chainstate");
+        let check = get_optional_path(matches, "check");
+        let network = matches
+            .value_of("network")
+            .unwrap_or("bitcoin")
+            .parse()
+            .expect("Value is restricted to the networks present at interface.yml");
+
+        Self {
+            folder,
+            path,
+            target,
+            amounts: get_amount_format(matches),
+            height,
+            database,
+            dump,
+            from_dump,
+            from_chainstate,
+            check,
+            network,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        match self.database {
+            Some(ref database) => {
+                let store = DatabaseStore::open(database).context("Unable to open the utxo database")?;
+                self.replay(self.load(store)?)
+            }
+            None => self.replay(self.load(MemoryStore::new())?),
+        }
+    }
+
+    fn load<S: UtxoStore>(&self, store: S) -> Result<UtxoSet<S>> {
+        if let Some(ref dir) = self.from_chainstate {
+            let reader = ChainstateReader::open(dir).context("Unable to open the chainstate database")?;
+            let hash = reader.best_block().context("The chainstate has no best block")?;
+            return UtxoSet::from_chainstate(store, self.locate(hash)?, &reader)
+                .with_context(|| format!("Unable to load the chainstate {}", dir.display()));
+        }
+        match self.from_dump {
+            Some(ref file) => {
+                let reader = Self::open_dump(file)?;
+                let height = self.locate(reader.metadata().hash())?;
+                UtxoSet::from_dump(store, height, reader)
+                    .with_context(|| format!("Unable to load the snapshot {}", file.display()))
+            }
+            None => Ok(UtxoSet::with_store(store)),
+        }
+    }
+
+    // the snapshots only name the block they were taken at, its height is found in the blk files
+    fn locate(&self, hash: &BlockHash) -> Result<u32> {
+        BitcoinParser::default()
+            .locate(&get_block_files(self.folder, &self.path)?, hash)
+            .context("Unable to parse the blk files")?
+            .with_context(|| format!("The block {} of the snapshot is not in the blk files", hash))
+    }
+
+    fn replay<S: UtxoStore>(&self, mut set: UtxoSet<S>) -> Result<()> {
+        if let Some(ref height) = self.height {
+            let height = height
+                .parse()
+                .with_context(|| format!("Invalid block height {}", height))?;
+            set = set.stop_at(height).context("Unable to stop the replay")?;
+        }
+        BitcoinParser::default()
+            .visit(&get_block_files(self.folder, &self.path)?, &mut set)
+            .context("Unable to parse the blk files")?;
+        if let Some(err) = set.take_error() {
+            return Err(err).context("Unable to replay the blocks");
+        }
+        if let Some(ref file) = self.check {
+            let comparison = Self::open_dump(file)?
+                .compare(&mut set)
+                .with_context(|| format!("Unable to compare with the snapshot {}", file.display()))?;
+            println!(
+                "{:<16}{}\n{:<16}{}\n{:<16}{}\n{:<16}{}",
+                "matching",
+                comparison.matching(),
+                "different",
+                comparison.different(),
+                "only in dump",
+                comparison.only_in_dump(),
+                "only in set",
+                comparison.only_in_set()
+            );
+        }
+        if let Some(ref file) = self.dump {
+            let writer = BufWriter::new(File::create(file).context("Unable to create the snapshot file")?);
+            set.write_dump(self.network, writer)
+                .with_context(|| format!("Unable to write the snapshot {}", file.display()))?;
+        }
+        let (height, hash) = set.tip().context("No block has been applied to the set")?;
+        println!("{:<16}{}", "height", height);
+        println!("{:<16}{}", "block", hash);
+        println!("{:<16}{}", "outputs", set.len());
+        if let Some(total) = set.total_value().context("Unable to read the unspent outputs")? {
+            println!("{:<16}{}", "value", total);
+        }
+        // the coins are only gathered in memory to be written
+        if let Some(ref target) = self.target {
+            let snapshot = set.snapshot().context("Unable to read the unspent outputs")?;
+            JsonWriter::new(target)
+                .with_amounts(self.amounts)
+                .save(&snapshot, "utxos.json")
+                .context("Unable to save the utxo snapshot")?;
+        }
+        Ok(())
+    }
+
+    fn open_dump(file: &Path) -> Result<DumpReader<BufReader<File>>> {
+        let reader = BufReader::new(File::open(file).context("Unable to open the snapshot file")?);
+        DumpReader::new(reader).with_context(|| format!("Unable to read the snapshot {}", file.display()))
+    }
+}